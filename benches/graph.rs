@@ -0,0 +1,161 @@
+use std::collections::{HashMap, HashSet};
+use std::hint::black_box;
+use std::time::Instant;
+
+use maze_runner::Maze;
+use maze_runner::Pathfinding::Graph;
+use maze_runner::Pathfinding::Node::Coords;
+use maze_runner::Pathfinding::astar::Astar;
+use maze_runner::Pathfinding::solver::Solver;
+
+const SIZE: i32 = 200;
+
+// The layout the graph had before its storage was made dense: a map from each cell to its own
+// sets of neighbours and passages, copied into fresh vectors on every query.
+struct BaselineNode {
+    neighbors: HashSet<Coords>,
+    connections: HashSet<Coords>,
+}
+
+struct BaselineGraph {
+    nodes: HashMap<Coords, BaselineNode>,
+}
+
+impl BaselineGraph {
+    fn from_graph(graph: &Graph::Graph) -> BaselineGraph {
+        let mut nodes: HashMap<Coords, BaselineNode> = HashMap::new();
+
+        for node_coords in graph.iter_nodes() {
+            let node = BaselineNode {
+                neighbors: graph.iter_neighbors(node_coords).collect(),
+                connections: graph.iter_connections(node_coords).collect(),
+            };
+
+            nodes.insert(node_coords, node);
+        }
+
+        return BaselineGraph { nodes };
+    }
+
+    fn get_nodes(&self) -> Vec<Coords> {
+        return self.nodes.keys().copied().collect();
+    }
+
+    fn get_neighbors(&self, node_coords: Coords) -> Vec<Coords> {
+        match self.nodes.get(&node_coords) {
+            None => return vec![],
+            Some(node) => return node.neighbors.iter().copied().collect(),
+        }
+    }
+
+    fn get_connections(&self, node_coords: Coords) -> Vec<Coords> {
+        match self.nodes.get(&node_coords) {
+            None => return vec![],
+            Some(node) => return node.connections.iter().copied().collect(),
+        }
+    }
+
+    fn is_connected(&self, node_coords: Coords, neighbor_coords: Coords) -> bool {
+        match self.nodes.get(&node_coords) {
+            None => return true,
+            Some(node) => return node.connections.contains(&neighbor_coords),
+        }
+    }
+}
+
+// Milliseconds per run, averaged over the iterations.
+fn time<T>(iterations: u32, mut run: impl FnMut() -> T) -> f64 {
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        black_box(run());
+    }
+
+    return start.elapsed().as_secs_f64() * 1000.0 / f64::from(iterations);
+}
+
+fn report(label: &str, opt_baseline_ms: Option<f64>, dense_ms: f64) {
+    match opt_baseline_ms {
+        None => println!("{:<24} {:>12} {:>10.3} ms", label, "", dense_ms),
+        Some(baseline_ms) => println!("{:<24} {:>9.3} ms {:>10.3} ms {:>8.1}x", label, baseline_ms, dense_ms, baseline_ms / dense_ms),
+    }
+}
+
+fn generate() -> Maze::Maze {
+    let mut maze = Maze::Maze::new(SIZE);
+
+    while !maze.is_finished() {
+        maze.generate_maze();
+    }
+
+    return maze;
+}
+
+// Run with `cargo bench`.
+fn main() {
+    println!("{:<24} {:>12} {:>13} {:>9}", "", "hashmap", "dense", "speedup");
+
+    report("generate maze", None, time(3, generate));
+
+    let maze = generate();
+    let graph = &maze.graph;
+
+    let baseline = BaselineGraph::from_graph(graph);
+
+    report("node sweep", Some(time(20, || baseline.get_nodes().len())), time(20, || graph.get_nodes().len()));
+
+    report("neighbor sweep",
+        Some(time(20, || baseline.get_nodes().into_iter().map(|node_coords| baseline.get_neighbors(node_coords).len()).sum::<usize>())),
+        time(20, || graph.get_nodes().into_iter().map(|node_coords| graph.get_neighbors(node_coords).len()).sum::<usize>()));
+
+    report("connection sweep",
+        Some(time(20, || baseline.get_nodes().into_iter().map(|node_coords| baseline.get_connections(node_coords).len()).sum::<usize>())),
+        time(20, || graph.get_nodes().into_iter().map(|node_coords| graph.get_connections(node_coords).len()).sum::<usize>()));
+
+    // The old layout had no iterators, so the sweeps above are what these replace.
+    report("neighbor iteration",
+        Some(time(20, || baseline.get_nodes().into_iter().map(|node_coords| baseline.get_neighbors(node_coords).len()).sum::<usize>())),
+        time(20, || graph.iter_nodes().map(|node_coords| graph.iter_neighbors(node_coords).count()).sum::<usize>()));
+
+    report("connection iteration",
+        Some(time(20, || baseline.get_nodes().into_iter().map(|node_coords| baseline.get_connections(node_coords).len()).sum::<usize>())),
+        time(20, || graph.iter_nodes().map(|node_coords| graph.iter_connections(node_coords).count()).sum::<usize>()));
+
+    let baseline_walls = time(20, || {
+        let mut walls = 0;
+
+        for node_coords in baseline.get_nodes() {
+            for neighbor_coords in baseline.get_neighbors(node_coords) {
+                if !baseline.is_connected(node_coords, neighbor_coords) { walls += 1; }
+            }
+        }
+
+        return walls;
+    });
+
+    let dense_walls = time(20, || {
+        let mut walls = 0;
+
+        for node_coords in graph.iter_nodes() {
+            for neighbor_coords in graph.iter_neighbors(node_coords) {
+                if !graph.is_connected(node_coords, neighbor_coords) { walls += 1; }
+            }
+        }
+
+        return walls;
+    });
+
+    report("wall render sweep", Some(baseline_walls), dense_walls);
+
+    report("astar corner to corner", None, time(5, || {
+        let mut solver = Astar::new();
+
+        solver.initialize((0, 0), (SIZE - 1, SIZE - 1));
+
+        while !solver.is_finished() {
+            solver.generate_path(graph);
+        }
+
+        return solver.get_path().len();
+    }));
+}
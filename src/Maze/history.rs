@@ -0,0 +1,100 @@
+use std::fs;
+use std::io;
+
+use crate::Pathfinding::Node::Coords;
+
+// One change made while carving the maze. Each can be undone, which is what lets
+// generation be stepped backwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationEvent {
+    Visit(Coords),
+    Carve(Coords, Coords),
+    Push(Coords),
+    Pop(Coords),
+    Move(Coords, Coords),
+    Finish,
+}
+
+impl GenerationEvent {
+    pub fn to_json(self, step: usize) -> String {
+        let coords = |coords: Coords| format!("[{},{}]", coords.0, coords.1);
+
+        let fields = match self {
+            GenerationEvent::Visit(node_coords) => format!("\"event\":\"visit\",\"coords\":{}", coords(node_coords)),
+            GenerationEvent::Carve(from_coords, to_coords) => {
+                format!("\"event\":\"carve\",\"from\":{},\"to\":{}", coords(from_coords), coords(to_coords))
+            },
+            GenerationEvent::Push(node_coords) => format!("\"event\":\"push\",\"coords\":{}", coords(node_coords)),
+            GenerationEvent::Pop(node_coords) => format!("\"event\":\"pop\",\"coords\":{}", coords(node_coords)),
+            GenerationEvent::Move(from_coords, to_coords) => {
+                format!("\"event\":\"move\",\"from\":{},\"to\":{}", coords(from_coords), coords(to_coords))
+            },
+            GenerationEvent::Finish => "\"event\":\"finish\"".to_string(),
+        };
+
+        return format!("{{\"step\":{},{}}}", step, fields);
+    }
+}
+
+pub fn save(history: &[Vec<GenerationEvent>], path: &str) -> io::Result<()> {
+    let mut lines = String::new();
+
+    for (step, events) in history.iter().enumerate() {
+        for event in events {
+            lines.push_str(&event.to_json(step));
+            lines.push('\n');
+        }
+    }
+
+    return fs::write(path, lines);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::{save, GenerationEvent};
+
+    #[test]
+    fn writes_each_event_as_one_object() {
+        let cases = [
+            (GenerationEvent::Visit((0, 0)), r#"{"step":3,"event":"visit","coords":[0,0]}"#),
+            (GenerationEvent::Carve((1, 2), (1, 3)), r#"{"step":3,"event":"carve","from":[1,2],"to":[1,3]}"#),
+            (GenerationEvent::Push((4, -1)), r#"{"step":3,"event":"push","coords":[4,-1]}"#),
+            (GenerationEvent::Pop((4, 5)), r#"{"step":3,"event":"pop","coords":[4,5]}"#),
+            (GenerationEvent::Move((2, 2), (3, 2)), r#"{"step":3,"event":"move","from":[2,2],"to":[3,2]}"#),
+            (GenerationEvent::Finish, r#"{"step":3,"event":"finish"}"#),
+        ];
+
+        for (event, expected) in cases {
+            assert_eq!(event.to_json(3), expected);
+        }
+    }
+
+    #[test]
+    fn saves_every_event_under_its_step() {
+        let path = env::temp_dir().join(format!("maze_runner_history_{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let history = vec![
+            vec![GenerationEvent::Visit((0, 0)), GenerationEvent::Push((0, 0))],
+            vec![],
+            vec![GenerationEvent::Finish],
+        ];
+
+        save(&history, path).unwrap();
+
+        let text = fs::read_to_string(path).unwrap();
+
+        fs::remove_file(path).unwrap();
+
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines, vec![
+            r#"{"step":0,"event":"visit","coords":[0,0]}"#,
+            r#"{"step":0,"event":"push","coords":[0,0]}"#,
+            r#"{"step":2,"event":"finish"}"#,
+        ]);
+    }
+}
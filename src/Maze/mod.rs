@@ -1,63 +1,149 @@
+pub mod history;
+
 use std::{collections::HashSet};
+use std::io;
 
 use rand::Rng;
 
-use crate::Pathfinding::{astar, Graph, Node::{Coords}};
+use crate::Pathfinding::{Graph, Graph::Inventory, Node::{Coords}, schedule::Schedule, solver::{Solver, SolverKind}};
+
+use history::GenerationEvent;
+
+const MAX_PORTALS: usize = 8;
+
+// Passages tried for each door, keeping the one that shuts off the most of the maze.
+const DOOR_CANDIDATES: usize = 20;
 
 pub struct Maze {
     pub graph: Graph::Graph,
-    pub astar: astar::Astar,
+    pub solver: Box<dyn Solver>,
 
     visited: HashSet<Coords>,
     stack: Vec<Coords>,
     current_coords: Coords,
     is_finished: bool,
+
+    // Every generation step made so far, and how many of them are currently applied.
+    history: Vec<Vec<GenerationEvent>>,
+    position: usize,
+
+    // The cell keys were placed from, which every door can be opened from in turn.
+    opt_key_origin: Option<Coords>,
 }
 
 impl Maze {
     pub fn new(size: i32) -> Maze {
         return Maze { 
             graph: Graph::Graph::new(size), 
-            astar: astar::Astar::new(),
+            solver: SolverKind::Astar.create(),
 
             visited: HashSet::new(),
             stack: vec![],
             current_coords: (0, 0),
-            is_finished: false
+            is_finished: false,
+            history: vec![],
+            position: 0,
+            opt_key_origin: None
         };
     }
 
     pub fn generate_maze(&mut self) {
+        // Steps that were undone are redone as recorded rather than generated afresh.
+        if self.position < self.history.len() {
+            for event in self.history[self.position].clone() {
+                self.apply(event);
+            }
+
+            self.position += 1;
+
+            return;
+        }
+
         if self.is_finished { return; }
 
-        self.visited.replace(self.current_coords);
+        let mut events: Vec<GenerationEvent> = vec![];
 
-        let mut opt_neighbor_coords = self.get_random_unvisited_neighbor(self.current_coords);
+        if !self.visited.contains(&self.current_coords) {
+            events.push(GenerationEvent::Visit(self.current_coords));
 
-        if let Some(neighbor_coords) = opt_neighbor_coords {
-            self.graph.add_connection(self.current_coords, neighbor_coords);
-            self.graph.add_connection(neighbor_coords, self.current_coords);
+            self.apply(GenerationEvent::Visit(self.current_coords));
+        }
 
-            self.stack.push(self.current_coords);
+        let opt_neighbor_coords = self.get_random_unvisited_neighbor(self.current_coords);
 
-            self.current_coords = neighbor_coords;
+        let next_events = match opt_neighbor_coords {
+            Some(neighbor_coords) => vec![
+                GenerationEvent::Carve(self.current_coords, neighbor_coords),
+                GenerationEvent::Push(self.current_coords),
+                GenerationEvent::Move(self.current_coords, neighbor_coords),
+            ],
+            None => match self.stack.last() {
+                Some(previous_coords) => vec![
+                    GenerationEvent::Pop(*previous_coords),
+                    GenerationEvent::Move(self.current_coords, *previous_coords),
+                ],
+                None => vec![GenerationEvent::Finish],
+            },
+        };
 
-            return
+        for event in next_events {
+            self.apply(event);
+
+            events.push(event);
         }
 
-        opt_neighbor_coords = self.stack.pop();
+        self.history.push(events);
+        self.position += 1;
+    }
 
-        if let Some(neighbor_coords) = opt_neighbor_coords {
-            self.current_coords = neighbor_coords;
+    pub fn step_back(&mut self) {
+        if self.position == 0 { return; }
 
-            return
+        self.position -= 1;
+
+        for event in self.history[self.position].clone().into_iter().rev() {
+            self.undo(event);
         }
+    }
 
-        self.is_finished = true;
+    fn apply(&mut self, event: GenerationEvent) {
+        match event {
+            GenerationEvent::Visit(node_coords) => { self.visited.insert(node_coords); },
+            GenerationEvent::Carve(from_coords, to_coords) => {
+                self.graph.add_connection(from_coords, to_coords);
+                self.graph.add_connection(to_coords, from_coords);
+            },
+            GenerationEvent::Push(node_coords) => self.stack.push(node_coords),
+            GenerationEvent::Pop(_) => { self.stack.pop(); },
+            GenerationEvent::Move(_, to_coords) => self.current_coords = to_coords,
+            GenerationEvent::Finish => self.is_finished = true,
+        }
+    }
+
+    fn undo(&mut self, event: GenerationEvent) {
+        match event {
+            GenerationEvent::Visit(node_coords) => { self.visited.remove(&node_coords); },
+            GenerationEvent::Carve(from_coords, to_coords) => {
+                self.graph.remove_connection(from_coords, to_coords);
+                self.graph.remove_connection(to_coords, from_coords);
+            },
+            GenerationEvent::Push(_) => { self.stack.pop(); },
+            GenerationEvent::Pop(node_coords) => self.stack.push(node_coords),
+            GenerationEvent::Move(from_coords, _) => self.current_coords = from_coords,
+            GenerationEvent::Finish => self.is_finished = false,
+        }
+    }
+
+    pub fn save_history(&self, path: &str) -> io::Result<()> {
+        return history::save(&self.history[..self.position], path);
+    }
+
+    pub fn get_generation_position(&self) -> (usize, usize) {
+        return (self.position, self.history.len());
     }
 
     fn get_random_unvisited_neighbor(&self, node_coords: Coords) -> Option<Coords> {
-        let mut neighbors = self.graph.get_neighbors(node_coords);
+        let mut neighbors = self.graph.get_wall_neighbors(node_coords);
 
         let mut rng = rand::thread_rng();
 
@@ -76,6 +162,199 @@ impl Maze {
         return None::<Coords>;
     }
 
+    pub fn toggle_random_wall(&mut self) {
+        let mut rng = rand::thread_rng();
+
+        let topology = self.graph.get_topology();
+
+        let node_coords = topology.get_coords(rng.gen_range(0..topology.get_cell_count()));
+
+        let neighbors = self.graph.get_wall_neighbors(node_coords);
+
+        if neighbors.is_empty() { return; }
+
+        let neighbor_coords = neighbors[rng.gen_range(0..neighbors.len())];
+
+        if self.graph.is_connected(node_coords, neighbor_coords) {
+            self.graph.remove_connection(node_coords, neighbor_coords);
+            self.graph.remove_connection(neighbor_coords, node_coords);
+        } else {
+            self.graph.add_connection(node_coords, neighbor_coords);
+            self.graph.add_connection(neighbor_coords, node_coords);
+        }
+    }
+
+    // Links two random cells, dropping the oldest portal once there are enough of them.
+    pub fn add_random_portal(&mut self) {
+        let mut rng = rand::thread_rng();
+
+        let topology = self.graph.get_topology();
+
+        let entry_coords = topology.get_coords(rng.gen_range(0..topology.get_cell_count()));
+        let exit_coords = topology.get_coords(rng.gen_range(0..topology.get_cell_count()));
+
+        let portals = self.graph.get_portals();
+
+        if portals.len() >= MAX_PORTALS {
+            self.graph.remove_portal(portals[0].0, portals[0].1);
+        }
+
+        self.graph.add_portal(entry_coords, exit_coords);
+    }
+
+    // Closes one way through a random open passage.
+    pub fn make_random_one_way(&mut self) {
+        let mut rng = rand::thread_rng();
+
+        let topology = self.graph.get_topology();
+
+        let node_coords = topology.get_coords(rng.gen_range(0..topology.get_cell_count()));
+
+        let neighbors: Vec<Coords> = self.graph.iter_wall_neighbors(node_coords)
+            .filter(|neighbor_coords| self.graph.is_connected(node_coords, *neighbor_coords) && self.graph.is_connected(*neighbor_coords, node_coords))
+            .collect();
+
+        if neighbors.is_empty() { return; }
+
+        self.graph.set_one_way(node_coords, neighbors[rng.gen_range(0..neighbors.len())]);
+    }
+
+    // Locks passages and hides a key for each so that the maze stays solvable from the origin:
+    // every key lies where the keys before it are enough to reach.
+    pub fn place_keys(&mut self, origin_coords: Coords, count: usize) {
+        let mut rng = rand::thread_rng();
+
+        self.graph.clear_doors();
+
+        self.opt_key_origin = Some(origin_coords);
+
+        for key in 0..usize::min(count, Graph::MAX_KEYS) {
+            let placed: Inventory = (1 << key) - 1;
+
+            let reachable: Vec<Coords> = self.get_collectable(origin_coords).0.into_iter().collect();
+
+            let mut best: Option<(usize, Coords, Coords, Vec<Coords>)> = None;
+
+            for _ in 0..DOOR_CANDIDATES {
+                let node_coords = reachable[rng.gen_range(0..reachable.len())];
+
+                let neighbors: Vec<Coords> = self.graph.iter_wall_neighbors(node_coords)
+                    .filter(|neighbor_coords| self.graph.is_connected(node_coords, *neighbor_coords) && self.graph.get_door(node_coords, *neighbor_coords).is_none())
+                    .collect();
+
+                if neighbors.is_empty() { continue; }
+
+                let neighbor_coords = neighbors[rng.gen_range(0..neighbors.len())];
+
+                self.graph.add_door(node_coords, neighbor_coords, key);
+
+                let (before_door, inventory) = self.get_collectable(origin_coords);
+
+                self.graph.remove_door(node_coords, neighbor_coords);
+
+                // A door that cuts off a key already placed could never be opened in turn.
+                if inventory != placed { continue; }
+
+                let shut_off = reachable.len() - before_door.len();
+
+                if shut_off == 0 || best.as_ref().is_some_and(|(best_shut_off, _, _, _)| *best_shut_off >= shut_off) { continue; }
+
+                // The key has to be somewhere the door does not shut off.
+                let before_door: Vec<Coords> = before_door.into_iter()
+                    .filter(|coords| *coords != origin_coords && self.graph.get_key(*coords).is_none())
+                    .collect();
+
+                if before_door.is_empty() { continue; }
+
+                best = Some((shut_off, node_coords, neighbor_coords, before_door));
+            }
+
+            match best {
+                None => return,
+                Some((_, node_coords, neighbor_coords, before_door)) => {
+                    self.graph.add_door(node_coords, neighbor_coords, key);
+                    self.graph.add_key(before_door[rng.gen_range(0..before_door.len())], key);
+                },
+            }
+        }
+    }
+
+    pub fn clear_keys(&mut self) {
+        self.graph.clear_doors();
+
+        self.opt_key_origin = None;
+    }
+
+    // Walks out from the origin holding no keys, picking up every key it finds and walking out
+    // again with them until no more doors open. Returns where it got to and the keys it held.
+    fn get_collectable(&self, origin_coords: Coords) -> (HashSet<Coords>, Inventory) {
+        let mut inventory: Inventory = 0;
+
+        loop {
+            let reachable = self.get_reachable(origin_coords, inventory);
+
+            let collected = reachable.iter().fold(inventory, |collected, coords| {
+                match self.graph.get_key(*coords) {
+                    None => return collected,
+                    Some(key) => return collected | (1 << key),
+                }
+            });
+
+            if collected == inventory { return (reachable, inventory); }
+
+            inventory = collected;
+        }
+    }
+
+    fn get_reachable(&self, origin_coords: Coords, inventory: Inventory) -> HashSet<Coords> {
+        let mut reachable: HashSet<Coords> = HashSet::from([origin_coords]);
+        let mut stack: Vec<Coords> = vec![origin_coords];
+
+        while let Some(node_coords) = stack.pop() {
+            for connection_coords in self.graph.iter_connections(node_coords) {
+                if !self.graph.can_pass(node_coords, connection_coords, inventory) || !reachable.insert(connection_coords) { continue; }
+
+                stack.push(connection_coords);
+            }
+        }
+
+        return reachable;
+    }
+
+    // Puts gates on random open passages, most opening and closing on a cycle and some as
+    // bridges that are only there for a while at the start.
+    pub fn add_random_gates(&mut self, count: usize) {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..count {
+            let topology = self.graph.get_topology();
+
+            let node_coords = topology.get_coords(rng.gen_range(0..topology.get_cell_count()));
+
+            let neighbors: Vec<Coords> = self.graph.iter_wall_neighbors(node_coords)
+                .filter(|neighbor_coords| self.graph.is_connected(node_coords, *neighbor_coords) && self.graph.get_gate(node_coords, *neighbor_coords).is_none())
+                .collect();
+
+            if neighbors.is_empty() { continue; }
+
+            let neighbor_coords = neighbors[rng.gen_range(0..neighbors.len())];
+
+            let schedule = if rng.gen_bool(0.25) {
+                Schedule::Window { from: 0, until: rng.gen_range(10..40) }
+            } else {
+                let period = rng.gen_range(3..=8);
+
+                Schedule::Periodic { period, open: rng.gen_range(1..period), offset: rng.gen_range(0..period) }
+            };
+
+            self.graph.add_gate(node_coords, neighbor_coords, schedule);
+        }
+    }
+
+    pub fn get_key_origin(&self) -> Option<Coords> {
+        return self.opt_key_origin;
+    }
+
     pub fn get_current_coords(&self) -> Coords {
         return self.current_coords;
     }
@@ -84,3 +363,92 @@ impl Maze {
         return self.is_finished;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Maze;
+    use crate::Pathfinding::Node::Coords;
+
+    type Snapshot = (Vec<Vec<Coords>>, Vec<Coords>, Vec<Coords>, Coords, bool);
+
+    fn take_snapshot(maze: &Maze) -> Snapshot {
+        let connections = maze.graph.iter_nodes().map(|node_coords| {
+            let mut connections = maze.graph.get_connections(node_coords);
+
+            connections.sort();
+
+            return connections;
+        }).collect();
+
+        let mut visited: Vec<Coords> = maze.visited.iter().copied().collect();
+
+        visited.sort();
+
+        return (connections, visited, maze.stack.clone(), maze.current_coords, maze.is_finished);
+    }
+
+    #[test]
+    fn stepping_back_and_replaying_rebuilds_every_step() {
+        let mut maze = Maze::new(7);
+
+        let mut snapshots: Vec<Snapshot> = vec![take_snapshot(&maze)];
+
+        while !maze.is_finished() {
+            maze.generate_maze();
+
+            snapshots.push(take_snapshot(&maze));
+        }
+
+        let length = maze.get_generation_position().1;
+
+        assert_eq!(snapshots.len(), length + 1);
+
+        for position in (0..length).rev() {
+            maze.step_back();
+
+            assert_eq!(maze.get_generation_position(), (position, length));
+            assert_eq!(take_snapshot(&maze), snapshots[position], "stepping back to {}", position);
+        }
+
+        // Stepping back from the start stays there.
+        maze.step_back();
+
+        assert_eq!(maze.get_generation_position(), (0, length));
+        assert_eq!(maze.graph.iter_nodes().map(|node_coords| maze.graph.get_connections(node_coords).len()).sum::<usize>(), 0);
+
+        for (position, snapshot) in snapshots.iter().enumerate().skip(1) {
+            maze.generate_maze();
+
+            assert_eq!(take_snapshot(&maze), *snapshot, "replaying to {}", position);
+        }
+
+        assert!(maze.is_finished());
+        assert_eq!(maze.get_generation_position(), (length, length));
+    }
+
+    #[test]
+    fn replaying_part_of_the_way_matches_the_first_run() {
+        let mut maze = Maze::new(12);
+
+        while !maze.is_finished() {
+            maze.generate_maze();
+        }
+
+        let finished = take_snapshot(&maze);
+
+        let length = maze.get_generation_position().1;
+
+        for steps in [1, length / 3, length] {
+            for _ in 0..steps {
+                maze.step_back();
+            }
+
+            while !maze.is_finished() {
+                maze.generate_maze();
+            }
+
+            assert_eq!(take_snapshot(&maze), finished, "after stepping back {}", steps);
+            assert_eq!(maze.get_generation_position(), (length, length));
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::HashSet;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use super::Graph;
+use super::Node::Coords;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentStrategy {
+    LeftHand,
+    RightHand,
+    Pledge,
+    RandomMouse,
+}
+
+pub struct Agent {
+    strategy: AgentStrategy,
+
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    heading: Coords,
+    turn_count: i32,
+    is_following: bool,
+
+    trail: Vec<Coords>,
+    seen_states: HashSet<(Coords, Coords, i32, bool)>,
+
+    // The random mouse starts every run from the same seed, so a run can be repeated.
+    seed: u64,
+    rng: StdRng,
+
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl Agent {
+    pub fn new(strategy: AgentStrategy) -> Agent {
+        let seed = rand::random();
+
+        return Agent {
+            strategy,
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            heading: (1, 0),
+            turn_count: 0,
+            is_following: false,
+            trail: vec![],
+            seen_states: HashSet::new(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    // Headings are the four sides of a square cell. The y axis points down the screen, so these
+    // turn as seen from above.
+    fn turn_left(heading: Coords) -> Coords {
+        return (heading.1, -heading.0);
+    }
+
+    fn turn_right(heading: Coords) -> Coords {
+        return (-heading.1, heading.0);
+    }
+
+    fn is_open(&self, graph: &Graph::Graph, heading: Coords) -> bool {
+        let neighbor_coords = (self.current_coords.0 + heading.0, self.current_coords.1 + heading.1);
+
+        return graph.is_valid_node_coordinate(neighbor_coords) && graph.is_connected(self.current_coords, neighbor_coords);
+    }
+
+    fn step(&mut self, heading: Coords) {
+        self.heading = heading;
+        self.current_coords = (self.current_coords.0 + heading.0, self.current_coords.1 + heading.1);
+
+        self.trail.push(self.current_coords);
+
+        // Each move is one cell looked past, and a walker only ever holds the cell it stands on.
+        self.stats.record_expansion();
+        self.stats.record_generated(1);
+        self.stats.record_frontier(1);
+    }
+
+    // Tries each relative direction in order, returning the turn count change of the one taken.
+    fn follow_wall(&mut self, graph: &Graph::Graph, prefer_left: bool) -> Option<i32> {
+        let heading = self.heading;
+
+        let (first, last) = if prefer_left {
+            (Agent::turn_left(heading), Agent::turn_right(heading))
+        } else {
+            (Agent::turn_right(heading), Agent::turn_left(heading))
+        };
+
+        let side = if prefer_left { 1 } else { -1 };
+
+        let candidates = [
+            (first, side),
+            (heading, 0),
+            (last, -side),
+            ((-heading.0, -heading.1), -2 * side),
+        ];
+
+        for (candidate, turns) in candidates {
+            if self.is_open(graph, candidate) {
+                self.step(candidate);
+
+                return Some(turns);
+            }
+        }
+
+        return None::<i32>;
+    }
+
+    fn pledge(&mut self, graph: &Graph::Graph) {
+        if self.is_following && self.turn_count == 0 {
+            self.is_following = false;
+        }
+
+        if !self.is_following {
+            if self.is_open(graph, self.heading) {
+                self.step(self.heading);
+
+                return;
+            }
+
+            // Turn right until the way is clear so the obstacle ends up on the left hand.
+            self.is_following = true;
+
+            for _ in 0..4 {
+                self.heading = Agent::turn_right(self.heading);
+                self.turn_count -= 1;
+
+                if self.is_open(graph, self.heading) {
+                    self.step(self.heading);
+
+                    return;
+                }
+            }
+
+            return;
+        }
+
+        if let Some(turns) = self.follow_wall(graph, true) {
+            self.turn_count += turns;
+        }
+    }
+
+    fn random_mouse(&mut self, graph: &Graph::Graph) {
+        let back = (-self.heading.0, -self.heading.1);
+
+        let mut candidates: Vec<Coords> = vec![];
+
+        for heading in [self.heading, Agent::turn_left(self.heading), Agent::turn_right(self.heading)] {
+            if self.is_open(graph, heading) {
+                candidates.push(heading);
+            }
+        }
+
+        // Only turn around at a dead end.
+        if candidates.is_empty() && self.is_open(graph, back) {
+            candidates.push(back);
+        }
+
+        if candidates.is_empty() { return; }
+
+        let index = self.rng.gen_range(0..candidates.len());
+
+        self.step(candidates[index]);
+    }
+}
+
+impl Solver for Agent {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+
+        let (dx, dy) = (goal_coords.0 - start_coords.0, goal_coords.1 - start_coords.1);
+
+        // Head for the goal along the axis with the larger gap, which is also the
+        // direction the Pledge agent keeps returning to.
+        self.heading = if i32::abs(dx) >= i32::abs(dy) { (dx.signum(), 0) } else { (0, dy.signum()) };
+
+        if self.heading == (0, 0) {
+            self.heading = (1, 0);
+        }
+
+        self.turn_count = 0;
+        self.is_following = false;
+        self.trail = vec![start_coords];
+        self.seen_states = HashSet::new();
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        if self.current_coords == self.goal_coords {
+            self.path = self.trail.iter().rev().copied().collect();
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        let directions = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+        if !directions.iter().any(|heading| self.is_open(graph, *heading)) {
+            self.is_finished = true;
+
+            return;
+        }
+
+        // A deterministic agent that repeats a state is lost for good. The step limit
+        // catches the random mouse and a Pledge counter that keeps winding up.
+        let state = (self.current_coords, self.heading, self.turn_count, self.is_following);
+
+        let is_repeated = self.strategy != AgentStrategy::RandomMouse && !self.seen_states.insert(state);
+
+        if is_repeated || self.trail.len() > graph.get_topology().get_cell_count() * 16 {
+            self.is_finished = true;
+
+            return;
+        }
+
+        match self.strategy {
+            AgentStrategy::LeftHand => { self.follow_wall(graph, true); },
+            AgentStrategy::RightHand => { self.follow_wall(graph, false); },
+            AgentStrategy::Pledge => self.pledge(graph),
+            AgentStrategy::RandomMouse => self.random_mouse(graph),
+        }
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    fn get_trail(&self) -> Vec<Coords> {
+        return self.trail.clone();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::{Agent, AgentStrategy};
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::Shape;
+
+    #[test]
+    fn wall_followers_reach_the_goal_on_perfect_mazes() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let maze = testing::generate_maze(12);
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &maze.graph), testing::random_coords(&mut rng, &maze.graph));
+
+            for strategy in [AgentStrategy::LeftHand, AgentStrategy::RightHand, AgentStrategy::Pledge] {
+                let path = testing::solve(&mut Agent::new(strategy), &maze.graph, start_coords, goal_coords);
+
+                assert_eq!(path.first(), Some(&goal_coords), "seed {} with {:?}", seed, strategy);
+                assert_eq!(path.last(), Some(&start_coords), "seed {} with {:?}", seed, strategy);
+
+                testing::get_path_cost(&maze.graph, &path);
+            }
+        }
+    }
+
+    #[test]
+    fn pledge_escapes_a_room_that_a_wall_follower_circles_in() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let mut graph = testing::random_grid(&mut rng, Shape::Square, 10, 1.0);
+
+        // A room in the middle of an open grid, with its only door facing away from the goal.
+        let is_inside = |coords: (i32, i32)| (3..=7).contains(&coords.0) && (3..=7).contains(&coords.1);
+
+        for node_coords in graph.get_nodes() {
+            for neighbor_coords in graph.get_wall_neighbors(node_coords) {
+                if is_inside(node_coords) != is_inside(neighbor_coords) && ![(2, 5), (3, 5)].contains(&node_coords) {
+                    graph.remove_connection(node_coords, neighbor_coords);
+                    graph.remove_connection(neighbor_coords, node_coords);
+                }
+            }
+        }
+
+        let (start_coords, goal_coords) = ((5, 5), (9, 5));
+
+        let path = testing::solve(&mut Agent::new(AgentStrategy::Pledge), &graph, start_coords, goal_coords);
+
+        assert_eq!(path.first(), Some(&goal_coords));
+        testing::get_path_cost(&graph, &path);
+
+        // Keeping a hand on the nearest wall only leads round the open middle of the room.
+        assert!(testing::solve(&mut Agent::new(AgentStrategy::LeftHand), &graph, start_coords, goal_coords).is_empty());
+    }
+
+    #[test]
+    fn random_mice_with_the_same_seed_take_the_same_walk() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let graph = testing::random_grid(&mut rng, Shape::Square, 10, 0.8);
+
+        let trails: Vec<Vec<_>> = [7, 7, 8].iter().map(|seed| {
+            let mut mouse = Agent::new(AgentStrategy::RandomMouse);
+
+            mouse.set_seed(*seed);
+
+            testing::solve(&mut mouse, &graph, (0, 0), (9, 9));
+
+            return mouse.get_trail();
+        }).collect();
+
+        assert_eq!(trails[0], trails[1]);
+        assert_ne!(trails[0], trails[2]);
+    }
+}
@@ -1,20 +1,59 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use priority_queue::PriorityQueue;
 
-use super::Node;
 use super::Graph;
 use super::Node::Coords;
+use super::landmarks::{LandmarkSelection, Landmarks, DEFAULT_LANDMARK_COUNT};
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::{CellScores, SearchStats};
+use super::trace::{SearchTrace, TraceAction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    LargerG,
+    SmallerH,
+    Fifo,
+    Lifo,
+}
+
+impl TieBreak {
+    pub fn next(&self) -> TieBreak {
+        match self {
+            TieBreak::LargerG => return TieBreak::SmallerH,
+            TieBreak::SmallerH => return TieBreak::Fifo,
+            TieBreak::Fifo => return TieBreak::Lifo,
+            TieBreak::Lifo => return TieBreak::LargerG,
+        }
+    }
+}
+
+// Lowest f first, then the tie-break key, then insertion order.
+type Priority = (Reverse<i32>, i32, i64);
 
 pub struct Astar {
     start_coords: Coords,
     goal_coords: Coords,
     current_coords: Coords,
 
-    open_set: PriorityQueue<Coords, i32>,
+    open_set: PriorityQueue<Coords, Priority>,
+    tie_break: TieBreak,
+    push_count: i64,
     came_from: HashMap<Coords, Coords>,
+    closed_set: Vec<Coords>,
     g_score: HashMap<Coords, i32>,
     h_score: HashMap<Coords, i32>,
 
+    opt_landmark_selection: Option<LandmarkSelection>,
+    opt_landmarks: Option<Landmarks>,
+    landmark_seed: u64,
+
+    metric: Metric,
+
+    stats: SearchStats,
+    trace: SearchTrace,
+
     path: Vec<Coords>,
     is_finished: bool
 }
@@ -26,32 +65,166 @@ impl Astar {
             goal_coords: (0, 0), 
             current_coords: (0, 0),
             open_set: PriorityQueue::new(),
+            tie_break: TieBreak::LargerG,
+            push_count: 0,
             came_from: HashMap::new(),
+            closed_set: vec![],
             g_score: HashMap::new(),
             h_score: HashMap::new(),
+            opt_landmark_selection: None,
+            opt_landmarks: None,
+            landmark_seed: rand::random(),
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            trace: SearchTrace::new(),
             path: vec![],
             is_finished: true
         };
     }
 
-    pub fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+    // A* guided by landmark distances on top of the metric's distance bound.
+    pub fn with_landmarks(selection: LandmarkSelection) -> Astar {
+        let mut astar = Astar::new();
+
+        astar.opt_landmark_selection = Some(selection);
+
+        return astar;
+    }
+
+    fn reconstruct_path(&mut self) {
+        self.path.push(self.current_coords);
+
+        loop {
+            let opt_current_coords = self.came_from.get(&self.current_coords);
+
+            match opt_current_coords {
+                None => break,
+                Some(current_coords) => {
+                    self.current_coords = *current_coords;
+
+                    self.path.push(self.current_coords);
+                }
+            }
+        }
+    }
+
+    fn add_to_open_set(&mut self, node_coords: Coords) {
+        let f = self.f(node_coords);
+
+        let tie = match self.tie_break {
+            TieBreak::LargerG => self.g(node_coords),
+            TieBreak::SmallerH => -self.h(node_coords),
+            TieBreak::Fifo | TieBreak::Lifo => 0,
+        };
+
+        self.push_count += 1;
+
+        let previous_f = self.open_set.get_priority(&node_coords).map(|priority| priority.0.0);
+
+        let order = match self.tie_break {
+            TieBreak::Lifo => self.push_count,
+            _ => -self.push_count,
+        };
+
+        self.open_set.push(node_coords, (Reverse(f), tie, order));
+
+        self.trace.record(TraceAction::Push { coords: node_coords, f, previous_f });
+
+        self.stats.record_generated(1);
+        self.stats.record_frontier(self.open_set.len());
+    }
+
+    fn get_next(&mut self) -> Option<Coords> {
+        let opt_node_coords = self.open_set.pop();
+
+        match opt_node_coords {
+            None => return None::<Coords>,
+            Some((node_coords, priority)) => {
+                self.trace.record(TraceAction::Pop { coords: node_coords, f: priority.0.0 });
+
+                return Some(node_coords);
+            }
+        }
+    }
+
+    // Cells that have not been reached yet are infinitely far away.
+    fn g(&self, node_coords: Coords) -> i32 {
+        return *self.g_score.get(&node_coords).unwrap_or(&i32::MAX);
+    }
+
+    fn h(&mut self, node_coords: Coords) -> i32 {
+        if let Some(h) = self.h_score.get(&node_coords) {
+            return *h;
+        }
+
+        let h = self.estimate(node_coords);
+
+        self.h_score.insert(node_coords, h);
+
+        return h;
+    }
+
+    fn estimate(&self, node_coords: Coords) -> i32 {
+        let mut h = self.metric.get_distance(node_coords, self.goal_coords);
+
+        if let Some(landmarks) = &self.opt_landmarks {
+            h = i32::max(h, landmarks.estimate(node_coords, self.goal_coords));
+        }
+
+        return h;
+    }
+
+    fn f(&mut self, node_coords: Coords) -> i32 {
+        return self.g(node_coords) + self.h(node_coords);
+    }
+}
+
+impl Default for Astar {
+    fn default() -> Astar {
+        return Astar::new();
+    }
+}
+
+impl Solver for Astar {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
         self.start_coords = start_coords;
         self.goal_coords = goal_coords;
         self.current_coords = start_coords;
         self.open_set = PriorityQueue::new();
+        self.push_count = 0;
         self.came_from = HashMap::new();
+        self.closed_set = vec![];
         self.g_score = HashMap::new();
         self.h_score = HashMap::new();
+        self.stats = SearchStats::default();
+        self.trace = SearchTrace::new();
         self.path = vec![];
         self.is_finished = false;
 
+        self.trace.record(TraceAction::Initialize { start: start_coords, goal: goal_coords });
+        self.trace.record(TraceAction::ScoreUpdate { coords: start_coords, g: 0, previous_g: None });
+
         self.g_score.insert(start_coords, 0);
         self.add_to_open_set(start_coords);
     }
 
-    pub fn generate_path(&mut self, graph: &Graph::Graph) {
+    fn generate_path(&mut self, graph: &Graph::Graph) {
         if self.is_finished { return; }
 
+        self.trace.next_step();
+
+        if let Some(selection) = self.opt_landmark_selection {
+            let is_stale = match &self.opt_landmarks {
+                None => true,
+                Some(landmarks) => landmarks.is_stale(graph),
+            };
+
+            if is_stale {
+                self.opt_landmarks = Some(Landmarks::new(graph, selection, DEFAULT_LANDMARK_COUNT, self.landmark_seed));
+                self.h_score = HashMap::new();
+            }
+        }
+
         let current_coords_opt = self.get_next();
 
         if current_coords_opt.is_none() {
@@ -65,114 +238,262 @@ impl Astar {
         if self.current_coords == self.goal_coords {
             self.reconstruct_path();
 
+            self.trace.record(TraceAction::PathFound { path: self.path.clone() });
+
             self.is_finished = true;
 
             return;
         }
 
-        for connection_coords in graph.get_connections(self.current_coords) {
+        self.stats.record_expansion();
+        self.closed_set.push(self.current_coords);
+
+        let mut connections = graph.get_connections(self.current_coords);
+
+        connections.sort();
+
+        for connection_coords in connections {
             let tentative_g_score = self.g(self.current_coords) + 
-                Node::Node::get_distance_between(self.current_coords, connection_coords);
+                self.metric.get_step_cost(self.current_coords, connection_coords);
 
             if tentative_g_score < self.g(connection_coords) {
-                self.came_from.insert(connection_coords, self.current_coords);
+                let previous_parent = self.came_from.insert(connection_coords, self.current_coords);
+
+                let previous_g = self.g_score.insert(connection_coords, tentative_g_score);
 
-                self.g_score.insert(connection_coords, tentative_g_score);
+                self.trace.record(TraceAction::ParentChange { coords: connection_coords, parent: self.current_coords, previous_parent });
+                self.trace.record(TraceAction::ScoreUpdate { coords: connection_coords, g: tentative_g_score, previous_g });
 
                 self.add_to_open_set(connection_coords);
             }
         }
     }
 
-    fn reconstruct_path(&mut self) {
-        self.path.push(self.current_coords);
-
-        loop {
-            let opt_current_coords = self.came_from.get(&self.current_coords);
+    fn set_tie_break(&mut self, tie_break: TieBreak) {
+        self.tie_break = tie_break;
+    }
 
-            match opt_current_coords {
-                None => break,
-                Some(current_coords) => {
-                    self.current_coords = *current_coords;
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
 
-                    self.path.push(self.current_coords);
-                }
-            }
-        }
+    fn set_seed(&mut self, seed: u64) {
+        self.landmark_seed = seed;
+        self.opt_landmarks = None;
     }
 
-    fn add_to_open_set(&mut self, node_coords: Coords) {
-        if let Some(_) = self.open_set.get(&node_coords) { return; }
+    // Searches again with the metric alone, so the expansions the landmarks saved can be shown.
+    fn compare_with_baseline(&mut self, graph: &Graph::Graph) {
+        if self.opt_landmarks.is_none() || !self.is_finished { return; }
 
-        let f = self.f(node_coords);
+        let mut baseline = Astar::new();
 
-        self.open_set.push(node_coords, f);
+        baseline.set_metric(self.metric.clone());
+        baseline.initialize(self.start_coords, self.goal_coords);
+
+        while !baseline.is_finished {
+            baseline.generate_path(graph);
+        }
+
+        self.stats.opt_baseline_expansions = Some(baseline.stats.expansions);
     }
 
-    fn get_next(&mut self) -> Option<Coords> {
-        let opt_node_coords = self.open_set.pop();
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
 
-        match opt_node_coords {
-            None => return None::<Coords>,
-            Some(node_coords) => return Some(node_coords.0)
-        }
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
     }
 
-    fn g(&mut self, node_coords: Coords) -> i32 {
-        if let Some(g) = self.g_score.get(&node_coords) {
-            return *g;
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        let mut nodes: Vec<Coords> = vec![];
+
+        for node in &self.open_set {
+            nodes.push(*node.0);
         }
 
-        let g = 100_000;
+        return nodes;
+    }
 
-        self.g_score.insert(node_coords, g);
+    fn get_closed_set(&self) -> Vec<Coords> {
+        return self.closed_set.clone();
+    }
 
-        return g;
+    fn get_came_from(&self) -> Vec<(Coords, Coords)> {
+        return self.came_from.iter().map(|(node_coords, parent_coords)| (*node_coords, *parent_coords)).collect();
     }
 
-    fn h(&mut self, node_coords: Coords) -> i32 {
-        if let Some(h) = self.h_score.get(&node_coords) {
-            return *h;
-        }
+    fn get_trace(&self) -> Option<&SearchTrace> {
+        return Some(&self.trace);
+    }
 
-        let h = Node::Node::get_distance_between(node_coords, self.goal_coords);
+    fn get_cell_scores(&self, coords: Coords) -> Option<CellScores> {
+        let h = match self.h_score.get(&coords) {
+            None => self.estimate(coords),
+            Some(h) => *h,
+        };
 
-        self.h_score.insert(node_coords, h);
+        return Some(CellScores { g: self.g_score.get(&coords).copied(), h, parent: self.came_from.get(&coords).copied() });
+    }
 
-        return h;
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
     }
 
-    fn f(&mut self, node_coords: Coords) -> i32 {
-        return 100_000_000 - self.g(node_coords) + self.h(node_coords)
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
     }
 
-    pub fn get_start_coords(&self) -> Coords {
-        return self.start_coords;
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
     }
 
-    pub fn get_current_coords(&self) -> Coords {
-        return self.current_coords;
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
     }
+}
 
-    pub fn get_goal_coords(&self) -> Coords {
-        return self.goal_coords;
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::{Astar, TieBreak};
+    use crate::Pathfinding::Graph;
+    use crate::Pathfinding::Node::Coords;
+    use crate::Pathfinding::landmarks::LandmarkSelection;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape};
+    use crate::Pathfinding::trace::TraceAction;
+
+    const TIE_BREAKS: [TieBreak; 4] = [TieBreak::LargerG, TieBreak::SmallerH, TieBreak::Fifo, TieBreak::Lifo];
+
+    fn solve_with(tie_break: TieBreak, graph: &Graph::Graph, start_coords: Coords, goal_coords: Coords) -> Astar {
+        let mut astar = Astar::new();
+
+        astar.set_tie_break(tie_break);
+
+        testing::solve(&mut astar, graph, start_coords, goal_coords);
+
+        return astar;
     }
 
-    pub fn get_open_set(&self) -> Vec<Coords> {
-        let mut nodes: Vec<Coords> = vec![];
+    #[test]
+    fn every_pop_takes_the_lowest_f_then_the_tie_break_then_push_order() {
+        for seed in 0..40 {
+            let mut rng = StdRng::seed_from_u64(seed);
 
-        for node in &self.open_set {
-            nodes.push(*node.0);
+            let size = rng.gen_range(3..15);
+
+            let graph = testing::random_grid(&mut rng, Shape::Square, size, [0.6, 0.8, 1.0][seed as usize % 3]);
+
+            let start_coords = testing::random_coords(&mut rng, &graph);
+            let goal_coords = testing::random_coords(&mut rng, &graph);
+
+            for tie_break in TIE_BREAKS {
+                let astar = solve_with(tie_break, &graph, start_coords, goal_coords);
+
+                // Replays the trace into the open set it describes, as (f, g, push number) per cell.
+                let mut g_score: HashMap<Coords, i32> = HashMap::new();
+                let mut open_set: HashMap<Coords, (i32, i32, usize)> = HashMap::new();
+
+                for (index, event) in astar.get_trace().unwrap().get_events().iter().enumerate() {
+                    match event.action {
+                        TraceAction::ScoreUpdate { coords, g, .. } => { g_score.insert(coords, g); },
+                        TraceAction::Push { coords, f, .. } => { open_set.insert(coords, (f, g_score[&coords], index)); },
+                        TraceAction::Pop { coords, .. } => {
+                            let key = |(f, g, push): (i32, i32, usize)| {
+                                let h = f - g;
+
+                                match tie_break {
+                                    TieBreak::LargerG => return (f, -g, push as i64),
+                                    TieBreak::SmallerH => return (f, h, push as i64),
+                                    TieBreak::Fifo => return (f, 0, push as i64),
+                                    TieBreak::Lifo => return (f, 0, -(push as i64)),
+                                }
+                            };
+
+                            let best = open_set.values().map(|entry| key(*entry)).min().unwrap();
+
+                            assert_eq!(key(open_set[&coords]), best, "seed {} with {:?}: popped {:?}", seed, tie_break, coords);
+
+                            open_set.remove(&coords);
+                        },
+                        _ => {},
+                    }
+                }
+            }
         }
+    }
 
-        return nodes;
+    #[test]
+    fn larger_g_runs_straight_at_the_goal_across_an_open_grid() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let graph = testing::random_grid(&mut rng, Shape::Square, 8, 1.0);
+
+        let expansions = TIE_BREAKS.map(|tie_break| solve_with(tie_break, &graph, (0, 0), (7, 7)).get_stats().expansions);
+
+        // Every cell between the corners has the same f, so only the tie-break decides how many are looked at.
+        assert_eq!(expansions[0], 14);
+        assert_eq!(expansions[1], 14);
+        assert!(expansions[2] > 50, "{:?}", expansions);
+        assert_eq!(expansions[3], 14);
     }
 
-    pub fn get_path(&self) -> Vec<Coords> {
-        return self.path.clone();
+    #[test]
+    fn records_baseline_expansions_only_when_asked() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let maze = testing::generate_maze(20);
+
+        let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &maze.graph), testing::random_coords(&mut rng, &maze.graph));
+
+        let mut baseline = Astar::new();
+        let mut alt = Astar::with_landmarks(LandmarkSelection::FarthestPoint);
+
+        testing::solve(&mut baseline, &maze.graph, start_coords, goal_coords);
+        testing::solve(&mut alt, &maze.graph, start_coords, goal_coords);
+
+        assert_eq!(alt.get_stats().opt_baseline_expansions, None);
+
+        alt.compare_with_baseline(&maze.graph);
+
+        assert_eq!(alt.get_stats().opt_baseline_expansions, Some(baseline.get_stats().expansions));
     }
 
-    pub fn is_finished(&self) -> bool {
-        return self.is_finished;
+    #[test]
+    fn finds_routes_longer_than_any_fixed_bound_on_large_diagonal_mazes() {
+        let size = 200;
+
+        let mut graph = Graph::Graph::with_topology(Shape::Diagonal(CornerCutting::Disallowed).create(size));
+
+        // A single corridor snaking through every row, so the goal is 40,000 steps from the start.
+        let mut snake: Vec<Coords> = vec![];
+
+        for y in 0..size {
+            for x in 0..size {
+                snake.push(if y % 2 == 0 { (x, y) } else { (size - 1 - x, y) });
+            }
+        }
+
+        for step in snake.windows(2) {
+            graph.add_connection(step[0], step[1]);
+            graph.add_connection(step[1], step[0]);
+        }
+
+        let (start_coords, goal_coords) = (snake[0], snake[snake.len() - 1]);
+
+        let path = testing::solve(&mut Astar::new(), &graph, start_coords, goal_coords);
+
+        assert_eq!(path.len(), snake.len());
+        assert_eq!(Some(testing::get_path_cost(&graph, &path)), testing::dijkstra(&graph, start_coords, goal_coords));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,409 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use priority_queue::PriorityQueue;
+
+use super::Graph;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+struct Frontier {
+    open_set: PriorityQueue<Coords, (Reverse<i32>, i64)>,
+    came_from: HashMap<Coords, Coords>,
+    g_score: HashMap<Coords, i32>,
+
+    push_count: i64,
+}
+
+impl Frontier {
+    fn new(origin_coords: Coords) -> Frontier {
+        let mut frontier = Frontier {
+            open_set: PriorityQueue::new(),
+            came_from: HashMap::new(),
+            g_score: HashMap::new(),
+            push_count: 0,
+        };
+
+        frontier.g_score.insert(origin_coords, 0);
+        frontier.push(origin_coords, 0);
+
+        return frontier;
+    }
+
+    fn push(&mut self, node_coords: Coords, f: i32) {
+        self.push_count += 1;
+
+        self.open_set.push(node_coords, (Reverse(f), -self.push_count));
+    }
+
+    fn g(&self, node_coords: Coords) -> Option<i32> {
+        return self.g_score.get(&node_coords).copied();
+    }
+
+    fn min_f(&self) -> Option<i32> {
+        match self.open_set.peek() {
+            None => return None::<i32>,
+            Some((_, (Reverse(f), _))) => return Some(*f),
+        }
+    }
+
+    fn trace(&self, node_coords: Coords) -> Vec<Coords> {
+        let mut path = vec![node_coords];
+
+        let mut current_coords = node_coords;
+
+        while let Some(parent_coords) = self.came_from.get(&current_coords) {
+            current_coords = *parent_coords;
+
+            path.push(current_coords);
+        }
+
+        return path;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        let mut nodes: Vec<Coords> = vec![];
+
+        for node in &self.open_set {
+            nodes.push(*node.0);
+        }
+
+        return nodes;
+    }
+}
+
+pub struct Bidirectional {
+    use_heuristic: bool,
+
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    forward: Frontier,
+    backward: Frontier,
+
+    best_cost: i32,
+    meeting_coords: Option<Coords>,
+    closed_set: Vec<Coords>,
+
+    metric: Metric,
+
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool,
+}
+
+impl Bidirectional {
+    pub fn new(use_heuristic: bool) -> Bidirectional {
+        return Bidirectional {
+            use_heuristic,
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            forward: Frontier::new((0, 0)),
+            backward: Frontier::new((0, 0)),
+            best_cost: i32::MAX,
+            meeting_coords: None,
+            closed_set: vec![],
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true,
+        };
+    }
+
+    // Passages can be one-way, so the backward search bounds the cost from the start to a cell
+    // rather than from the cell to the start.
+    fn h(&self, node_coords: Coords, is_forward: bool) -> i32 {
+        if !self.use_heuristic { return 0; }
+
+        if is_forward {
+            return self.metric.get_distance(node_coords, self.goal_coords);
+        }
+
+        return self.metric.get_distance(self.start_coords, node_coords);
+    }
+
+    fn should_stop(&self) -> bool {
+        let (opt_forward_f, opt_backward_f) = (self.forward.min_f(), self.backward.min_f());
+
+        if opt_forward_f.is_none() || opt_backward_f.is_none() { return true; }
+
+        let (forward_f, backward_f) = (opt_forward_f.unwrap(), opt_backward_f.unwrap());
+
+        // With a consistent heuristic neither side can improve on the best meeting
+        // once it is no larger than either frontier's minimum f. Without one the
+        // sum of the two smallest g values is the tighter bound.
+        if self.best_cost <= i32::max(forward_f, backward_f) { return true; }
+
+        return !self.use_heuristic && self.best_cost <= forward_f + backward_f;
+    }
+
+    fn expand(&mut self, graph: &Graph::Graph, is_forward: bool) {
+        let (frontier, other) = if is_forward {
+            (&mut self.forward, &self.backward)
+        } else {
+            (&mut self.backward, &self.forward)
+        };
+
+        let opt_node_coords = frontier.open_set.pop();
+
+        if opt_node_coords.is_none() { return; }
+
+        let node_coords = opt_node_coords.unwrap().0;
+
+        self.current_coords = node_coords;
+
+        self.stats.record_expansion();
+        self.closed_set.push(node_coords);
+
+        let mut candidates = if is_forward {
+            graph.get_connections(node_coords)
+        } else {
+            graph.get_predecessors(node_coords)
+        };
+
+        candidates.sort();
+
+        let g = frontier.g(node_coords).unwrap_or(i32::MAX);
+
+        let mut updates: Vec<(Coords, i32)> = vec![];
+
+        for candidate_coords in candidates {
+            let step_cost = if is_forward {
+                graph.get_step_cost(node_coords, candidate_coords)
+            } else {
+                graph.get_step_cost(candidate_coords, node_coords)
+            };
+
+            let tentative_g_score = g + step_cost;
+
+            if tentative_g_score >= frontier.g(candidate_coords).unwrap_or(i32::MAX) { continue; }
+
+            frontier.came_from.insert(candidate_coords, node_coords);
+            frontier.g_score.insert(candidate_coords, tentative_g_score);
+
+            updates.push((candidate_coords, tentative_g_score));
+
+            if let Some(other_g) = other.g(candidate_coords) {
+                if tentative_g_score + other_g < self.best_cost {
+                    self.best_cost = tentative_g_score + other_g;
+                    self.meeting_coords = Some(candidate_coords);
+                }
+            }
+        }
+
+        self.stats.record_generated(updates.len());
+
+        for (candidate_coords, g) in updates {
+            let f = g + self.h(candidate_coords, is_forward);
+
+            if is_forward {
+                self.forward.push(candidate_coords, f);
+            } else {
+                self.backward.push(candidate_coords, f);
+            }
+        }
+
+        self.stats.record_frontier(self.forward.open_set.len() + self.backward.open_set.len());
+    }
+
+    fn reconstruct_path(&mut self, meeting_coords: Coords) {
+        let mut path = self.backward.trace(meeting_coords);
+
+        path.reverse();
+
+        path.extend(self.forward.trace(meeting_coords).into_iter().skip(1));
+
+        self.path = path;
+    }
+}
+
+impl Solver for Bidirectional {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.forward = Frontier::new(start_coords);
+        self.backward = Frontier::new(goal_coords);
+        self.best_cost = i32::MAX;
+        self.meeting_coords = None;
+        self.closed_set = vec![];
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+
+        if start_coords == goal_coords {
+            self.best_cost = 0;
+            self.meeting_coords = Some(start_coords);
+        }
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        if self.should_stop() {
+            if let Some(meeting_coords) = self.meeting_coords {
+                self.reconstruct_path(meeting_coords);
+            }
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        let is_forward = self.forward.open_set.len() <= self.backward.open_set.len();
+
+        self.expand(graph, is_forward);
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return self.forward.get_open_set();
+    }
+
+    fn get_backward_open_set(&self) -> Vec<Coords> {
+        return self.backward.get_open_set();
+    }
+
+    fn get_meeting_coords(&self) -> Option<Coords> {
+        return self.meeting_coords;
+    }
+
+    fn get_closed_set(&self) -> Vec<Coords> {
+        return self.closed_set.clone();
+    }
+
+    // Both trees at once, with the backward one pointing towards the goal.
+    fn get_came_from(&self) -> Vec<(Coords, Coords)> {
+        return self.forward.came_from.iter()
+            .chain(self.backward.came_from.iter())
+            .map(|(node_coords, parent_coords)| (*node_coords, *parent_coords))
+            .collect();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::Bidirectional;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape};
+
+    #[test]
+    fn keeps_searching_past_a_meeting_that_is_off_the_best_route() {
+        let mut off_route_meetings = 0;
+
+        for seed in 0..120 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let shape = [Shape::Square, Shape::Diagonal(CornerCutting::Allowed)][seed as usize % 2];
+
+            let size = rng.gen_range(4..12);
+
+            let graph = testing::random_grid(&mut rng, shape, size, [0.6, 0.8, 1.0][seed as usize / 2 % 3]);
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+            for use_heuristic in [false, true] {
+                let mut bidirectional = Bidirectional::new(use_heuristic);
+
+                bidirectional.set_metric(graph.get_metric());
+                bidirectional.initialize(start_coords, goal_coords);
+
+                let mut meetings = vec![];
+
+                while !bidirectional.is_finished() {
+                    bidirectional.generate_path(&graph);
+
+                    if let Some(meeting_coords) = bidirectional.get_meeting_coords() {
+                        if !meetings.contains(&meeting_coords) { meetings.push(meeting_coords); }
+                    }
+                }
+
+                let path = bidirectional.get_path();
+
+                let opt_cost = testing::dijkstra(&graph, start_coords, goal_coords);
+
+                assert_eq!(opt_cost.map(|_| testing::get_path_cost(&graph, &path)), opt_cost, "seed {} with heuristic {}", seed, use_heuristic);
+
+                off_route_meetings += meetings.iter().filter(|meeting_coords| !path.contains(meeting_coords)).count();
+            }
+        }
+
+        // The frontiers first touching is not enough to stop on, or none of these would have been passed over.
+        assert!(off_route_meetings > 0);
+    }
+
+    #[test]
+    fn a_start_that_is_the_goal_is_its_own_route() {
+        let graph = testing::random_grid(&mut StdRng::seed_from_u64(0), Shape::Square, 5, 0.0);
+
+        for use_heuristic in [false, true] {
+            let mut bidirectional = Bidirectional::new(use_heuristic);
+
+            let path = testing::solve(&mut bidirectional, &graph, (2, 3), (2, 3));
+
+            assert_eq!(path, vec![(2, 3)]);
+            assert_eq!(bidirectional.get_meeting_coords(), Some((2, 3)));
+            assert_eq!(bidirectional.get_stats().expansions, 0);
+        }
+    }
+
+    #[test]
+    fn one_way_passages_can_leave_the_goal_out_of_reach() {
+        let mut graph = testing::random_grid(&mut StdRng::seed_from_u64(0), Shape::Square, 5, 1.0);
+
+        // Only a single passage crosses between the two middle columns, and it leads back towards the start.
+        for y in 0..5 {
+            graph.remove_connection((1, y), (2, y));
+            graph.remove_connection((2, y), (1, y));
+        }
+
+        graph.set_one_way((2, 0), (1, 0));
+
+        for use_heuristic in [false, true] {
+            assert!(testing::solve(&mut Bidirectional::new(use_heuristic), &graph, (0, 0), (4, 4)).is_empty(), "heuristic {}", use_heuristic);
+
+            let path = testing::solve(&mut Bidirectional::new(use_heuristic), &graph, (4, 4), (0, 0));
+
+            assert_eq!(Some(testing::get_path_cost(&graph, &path)), testing::dijkstra(&graph, (4, 4), (0, 0)), "heuristic {}", use_heuristic);
+        }
+    }
+}
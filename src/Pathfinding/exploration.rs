@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+
+use super::Graph;
+use super::Node::Coords;
+use super::astar::Astar;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+pub struct Explorer {
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    // What the agent believes the maze looks like, with every unseen wall assumed open.
+    belief: Option<Graph::Graph>,
+    known: HashSet<Coords>,
+    planner: Astar,
+
+    stats: SearchStats,
+
+    trail: Vec<Coords>,
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl Explorer {
+    pub fn new() -> Explorer {
+        return Explorer {
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            belief: None,
+            known: HashSet::new(),
+            planner: Astar::new(),
+            stats: SearchStats::default(),
+            trail: vec![],
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn create_belief(graph: &Graph::Graph) -> Graph::Graph {
+        let mut belief = Graph::Graph::with_topology(graph.get_topology().clone_box());
+
+        for node_coords in belief.get_nodes() {
+            for neighbor_coords in belief.get_neighbors(node_coords) {
+                belief.add_connection(node_coords, neighbor_coords);
+            }
+        }
+
+        return belief;
+    }
+
+    fn sense(&mut self, graph: &Graph::Graph) {
+        let belief = self.belief.as_mut().unwrap();
+
+        // Every wall between the cells around the agent is seen, since steps across corners depend on
+        // walls that do not touch the agent's own cell. Each way through is seen separately.
+        let mut nearby = graph.get_neighbors(self.current_coords);
+
+        nearby.push(self.current_coords);
+
+        for node_coords in &nearby {
+            for neighbor_coords in graph.iter_wall_neighbors(*node_coords) {
+                if !nearby.contains(&neighbor_coords) { continue; }
+
+                if graph.is_connected(*node_coords, neighbor_coords) {
+                    belief.add_connection(*node_coords, neighbor_coords);
+                } else {
+                    belief.remove_connection(*node_coords, neighbor_coords);
+                }
+            }
+        }
+
+        // A portal shows only to an agent standing at one of its ends. Until then the belief has no
+        // portal there, so a goal that only an unvisited portal leads to can be given up on.
+        for (entry_coords, exit_coords) in graph.get_portals() {
+            if entry_coords == self.current_coords || exit_coords == self.current_coords {
+                belief.add_portal(entry_coords, exit_coords);
+            }
+        }
+
+        self.known.insert(self.current_coords);
+    }
+
+    fn plan(&mut self) -> Vec<Coords> {
+        let belief = self.belief.as_ref().unwrap();
+
+        self.planner.initialize(self.current_coords, self.goal_coords);
+
+        while !self.planner.is_finished() {
+            self.planner.generate_path(belief);
+        }
+
+        self.stats.absorb(&self.planner.get_stats());
+
+        return self.planner.get_path();
+    }
+}
+
+impl Default for Explorer {
+    fn default() -> Explorer {
+        return Explorer::new();
+    }
+}
+
+impl Solver for Explorer {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.belief = None;
+        self.known = HashSet::new();
+        self.stats = SearchStats::default();
+        self.trail = vec![start_coords];
+        self.path = vec![];
+        self.is_finished = false;
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        if self.belief.is_none() {
+            self.belief = Some(Explorer::create_belief(graph));
+        }
+
+        if self.current_coords == self.goal_coords {
+            self.path = self.trail.iter().rev().copied().collect();
+            self.is_finished = true;
+
+            return;
+        }
+
+        self.sense(graph);
+
+        let mut plan = self.plan();
+
+        // Even the most hopeful reading of what is known leaves no way through.
+        if plan.len() < 2 {
+            self.path = vec![];
+            self.is_finished = true;
+
+            return;
+        }
+
+        plan.pop();
+
+        self.current_coords = *plan.last().unwrap();
+        self.trail.push(self.current_coords);
+
+        self.path = plan;
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.planner.set_metric(metric);
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    fn get_unknown_set(&self) -> Vec<Coords> {
+        let mut unknown: Vec<Coords> = vec![];
+
+        if let Some(belief) = &self.belief {
+            for node_coords in belief.iter_nodes() {
+                if !self.known.contains(&node_coords) {
+                    unknown.push(node_coords);
+                }
+            }
+        }
+
+        return unknown;
+    }
+
+    fn get_trail(&self) -> Vec<Coords> {
+        return self.trail.clone();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::Explorer;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape};
+
+    #[test]
+    fn reaches_the_goal_along_real_passages_whenever_there_is_a_route() {
+        let shapes = [Shape::Square, Shape::Diagonal(CornerCutting::Allowed), Shape::Diagonal(CornerCutting::Disallowed), Shape::Brick];
+
+        for seed in 0..80 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let shape = shapes[seed as usize % 4];
+
+            let size = rng.gen_range(3..12);
+
+            let graph = testing::random_grid(&mut rng, shape, size, [0.5, 0.7, 0.9][seed as usize / 4 % 3]);
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+            let mut explorer = Explorer::new();
+
+            let path = testing::solve(&mut explorer, &graph, start_coords, goal_coords);
+
+            for step in explorer.get_trail().windows(2) {
+                assert!(graph.is_connected(step[0], step[1]), "seed {} on {:?}: walked through a wall from {:?} to {:?}", seed, shape, step[0], step[1]);
+            }
+
+            match testing::dijkstra(&graph, start_coords, goal_coords) {
+                None => assert!(path.is_empty(), "seed {} on {:?}: a route was found that is not there", seed, shape),
+                Some(_) => assert_eq!((path.last(), path.first()), (Some(&start_coords), Some(&goal_coords)), "seed {} on {:?}", seed, shape),
+            }
+        }
+    }
+
+    #[test]
+    fn gives_up_once_the_goal_is_seen_to_be_walled_off() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let mut graph = testing::random_grid(&mut rng, Shape::Square, 8, 1.0);
+
+        let goal_coords = (6, 5);
+
+        for neighbor_coords in graph.get_neighbors(goal_coords) {
+            graph.remove_connection(goal_coords, neighbor_coords);
+            graph.remove_connection(neighbor_coords, goal_coords);
+        }
+
+        let mut explorer = Explorer::new();
+
+        assert!(testing::solve(&mut explorer, &graph, (0, 0), goal_coords).is_empty());
+        assert!(explorer.is_finished());
+    }
+
+    #[test]
+    fn portals_are_learned_only_at_one_of_their_ends() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let mut graph = testing::random_grid(&mut rng, Shape::Square, 6, 1.0);
+
+        graph.add_portal((0, 0), (5, 5));
+
+        let mut explorer = Explorer::new();
+
+        explorer.initialize((1, 0), (3, 3));
+        explorer.belief = Some(Explorer::create_belief(&graph));
+
+        for (current_coords, is_learned) in [((1, 0), false), ((4, 5), false), ((5, 5), true)] {
+            explorer.current_coords = current_coords;
+
+            explorer.sense(&graph);
+
+            assert_eq!(explorer.belief.as_ref().unwrap().is_portal((0, 0), (5, 5)), is_learned, "standing on {:?}", current_coords);
+        }
+    }
+}
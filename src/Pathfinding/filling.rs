@@ -0,0 +1,360 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::Graph;
+use super::Node::Coords;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillStrategy {
+    DeadEnd,
+    CulDeSac,
+}
+
+pub struct Filler {
+    strategy: FillStrategy,
+
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    queue: VecDeque<Coords>,
+    filled: HashSet<Coords>,
+    fill_order: Vec<Coords>,
+    is_prepared: bool,
+
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl Filler {
+    pub fn new(strategy: FillStrategy) -> Filler {
+        return Filler {
+            strategy,
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            queue: VecDeque::new(),
+            filled: HashSet::new(),
+            fill_order: vec![],
+            is_prepared: false,
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn is_end(&self, node_coords: Coords) -> bool {
+        return node_coords == self.start_coords || node_coords == self.goal_coords;
+    }
+
+    fn get_open_connections(&self, graph: &Graph::Graph, node_coords: Coords) -> Vec<Coords> {
+        return graph.get_connections(node_coords)
+            .into_iter()
+            .filter(|connection_coords| !self.filled.contains(connection_coords))
+            .collect();
+    }
+
+    fn prepare(&mut self, graph: &Graph::Graph) {
+        let mut nodes = graph.get_nodes();
+
+        nodes.sort();
+
+        match self.strategy {
+            FillStrategy::DeadEnd => {
+                for node_coords in nodes {
+                    if !self.is_end(node_coords) && graph.iter_connections(node_coords).count() <= 1 {
+                        self.queue.push_back(node_coords);
+                    }
+                }
+            },
+            FillStrategy::CulDeSac => {
+                for node_coords in self.find_cul_de_sacs(graph) {
+                    self.queue.push_back(node_coords);
+                }
+            }
+        }
+
+        self.is_prepared = true;
+
+        self.stats.record_generated(self.queue.len());
+        self.stats.record_frontier(self.queue.len());
+    }
+
+    // Anything cut off from the start by a bridge that does not also cut off the goal is a
+    // cul-de-sac, whether it is a dead-end corridor or a loop with a single way in. The cells
+    // come back deepest first so filling creeps in from the far ends.
+    fn find_cul_de_sacs(&self, graph: &Graph::Graph) -> Vec<Coords> {
+        let mut entry_time: HashMap<Coords, usize> = HashMap::new();
+        let mut exit_time: HashMap<Coords, usize> = HashMap::new();
+        let mut low: HashMap<Coords, usize> = HashMap::new();
+        let mut order: Vec<Coords> = vec![];
+        let mut bridge_children: Vec<Coords> = vec![];
+
+        let mut stack: Vec<(Coords, Option<Coords>, Vec<Coords>)> = vec![(self.start_coords, None, graph.get_connections(self.start_coords))];
+
+        entry_time.insert(self.start_coords, 0);
+        low.insert(self.start_coords, 0);
+        order.push(self.start_coords);
+
+        while let Some((node_coords, opt_parent_coords, connections)) = stack.last_mut() {
+            let node_coords = *node_coords;
+
+            if let Some(connection_coords) = connections.pop() {
+                if Some(connection_coords) == *opt_parent_coords { continue; }
+
+                if let Some(time) = entry_time.get(&connection_coords) {
+                    let time = *time;
+
+                    low.insert(node_coords, usize::min(low[&node_coords], time));
+
+                    continue;
+                }
+
+                let time = order.len();
+
+                entry_time.insert(connection_coords, time);
+                low.insert(connection_coords, time);
+                order.push(connection_coords);
+
+                stack.push((connection_coords, Some(node_coords), graph.get_connections(connection_coords)));
+
+                continue;
+            }
+
+            let opt_parent_coords = *opt_parent_coords;
+
+            stack.pop();
+
+            exit_time.insert(node_coords, order.len());
+
+            if let Some(parent_coords) = opt_parent_coords {
+                low.insert(parent_coords, usize::min(low[&parent_coords], low[&node_coords]));
+
+                if low[&node_coords] > entry_time[&parent_coords] {
+                    bridge_children.push(node_coords);
+                }
+            }
+        }
+
+        let mut cells: Vec<Coords> = vec![];
+
+        let opt_goal_time = entry_time.get(&self.goal_coords).copied();
+
+        for child_coords in bridge_children {
+            let (entry, exit) = (entry_time[&child_coords], exit_time[&child_coords]);
+
+            if let Some(goal_time) = opt_goal_time {
+                if entry <= goal_time && goal_time < exit { continue; }
+            }
+
+            cells.extend(order[entry..exit].iter().copied());
+        }
+
+        cells.sort_by_key(|cell_coords| std::cmp::Reverse(entry_time[cell_coords]));
+        cells.dedup();
+
+        return cells;
+    }
+
+    fn reconstruct_path(&mut self, graph: &Graph::Graph) {
+        let mut came_from: HashMap<Coords, Coords> = HashMap::new();
+        let mut queue: VecDeque<Coords> = VecDeque::from([self.start_coords]);
+
+        let mut is_found = false;
+
+        while let Some(node_coords) = queue.pop_front() {
+            if node_coords == self.goal_coords {
+                is_found = true;
+
+                break;
+            }
+
+            for connection_coords in self.get_open_connections(graph, node_coords) {
+                if connection_coords == self.start_coords || came_from.contains_key(&connection_coords) { continue; }
+
+                came_from.insert(connection_coords, node_coords);
+
+                queue.push_back(connection_coords);
+            }
+        }
+
+        if !is_found { return; }
+
+        let mut node_coords = self.goal_coords;
+
+        self.path.push(node_coords);
+
+        while let Some(parent_coords) = came_from.get(&node_coords) {
+            node_coords = *parent_coords;
+
+            self.path.push(node_coords);
+        }
+    }
+}
+
+impl Solver for Filler {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.queue = VecDeque::new();
+        self.filled = HashSet::new();
+        self.fill_order = vec![];
+        self.is_prepared = false;
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        if !self.is_prepared {
+            self.prepare(graph);
+
+            return;
+        }
+
+        let opt_node_coords = self.queue.pop_front();
+
+        if opt_node_coords.is_none() {
+            self.reconstruct_path(graph);
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        let node_coords = opt_node_coords.unwrap();
+
+        if !self.filled.insert(node_coords) { return; }
+
+        self.current_coords = node_coords;
+        self.fill_order.push(node_coords);
+
+        self.stats.record_expansion();
+
+        if self.strategy != FillStrategy::DeadEnd { return; }
+
+        // Filling a dead end can turn the cell before it into a new one.
+        for neighbor_coords in self.get_open_connections(graph, node_coords) {
+            if self.is_end(neighbor_coords) { continue; }
+
+            if self.get_open_connections(graph, neighbor_coords).len() <= 1 {
+                self.queue.push_back(neighbor_coords);
+
+                self.stats.record_generated(1);
+                self.stats.record_frontier(self.queue.len());
+            }
+        }
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return self.queue.iter().copied().collect();
+    }
+
+    fn get_filled_set(&self) -> Vec<Coords> {
+        return self.fill_order.clone();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::{FillStrategy, Filler};
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::Shape;
+
+    const STRATEGIES: [FillStrategy; 2] = [FillStrategy::DeadEnd, FillStrategy::CulDeSac];
+
+    #[test]
+    fn perfect_mazes_are_filled_down_to_the_only_route() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let maze = testing::generate_maze(12);
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &maze.graph), testing::random_coords(&mut rng, &maze.graph));
+
+            let opt_cost = testing::dijkstra(&maze.graph, start_coords, goal_coords);
+
+            for strategy in STRATEGIES {
+                let mut filler = Filler::new(strategy);
+
+                let path = testing::solve(&mut filler, &maze.graph, start_coords, goal_coords);
+
+                assert_eq!(Some(testing::get_path_cost(&maze.graph, &path)), opt_cost, "seed {} with {:?}", seed, strategy);
+                assert_eq!((path.last(), path.first()), (Some(&start_coords), Some(&goal_coords)), "seed {} with {:?}", seed, strategy);
+
+                let filled = filler.get_filled_set();
+
+                for cell_coords in &path {
+                    assert!(!filled.contains(cell_coords), "seed {} with {:?}: {:?} on the route was filled", seed, strategy, cell_coords);
+                }
+
+                // With no loops anywhere, everything off the route is a dead end.
+                assert_eq!(filled.len() + path.len(), maze.graph.get_topology().get_cell_count(), "seed {} with {:?}", seed, strategy);
+            }
+        }
+    }
+
+    #[test]
+    fn filling_leaves_a_route_whenever_there_is_one() {
+        for seed in 0..80 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let graph = testing::random_grid(&mut rng, Shape::Square, 10, [0.5, 0.7, 0.9][seed as usize % 3]);
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+            let opt_cost = testing::dijkstra(&graph, start_coords, goal_coords);
+
+            for strategy in STRATEGIES {
+                let path = testing::solve(&mut Filler::new(strategy), &graph, start_coords, goal_coords);
+
+                match opt_cost {
+                    None => assert!(path.is_empty(), "seed {} with {:?}: a route was found that is not there", seed, strategy),
+                    Some(_) => {
+                        assert_eq!((path.last(), path.first()), (Some(&start_coords), Some(&goal_coords)), "seed {} with {:?}", seed, strategy);
+
+                        testing::get_path_cost(&graph, &path);
+                    },
+                }
+            }
+        }
+    }
+}
@@ -1,146 +1,611 @@
-use std::collections::HashMap;
+use std::collections::VecDeque;
 
 use super::Node;
 use super::Node::Coords;
+use super::metric::Metric;
+use super::schedule::Schedule;
+use super::topology::{CornerCutting, SquareTopology, Topology};
+
+// The keys held while walking the maze, one bit per key.
+pub type Inventory = u32;
+
+pub const MAX_KEYS: usize = 32;
+
+// How many recent changes are kept for planners to catch up on. Anyone further behind starts over.
+pub const MAX_CHANGES: usize = 4096;
 
 #[derive(Debug)]
 pub struct Graph {
-    nodes: HashMap<Coords, Node::Node>,
+    topology: Box<dyn Topology>,
+
+    // One cell per topology index, each holding its passages as bit flags.
+    nodes: Vec<Node::Node>,
+
+    // One-way links between cells that need not be neighbours, as (entry, exit).
+    portals: Vec<(Coords, Coords)>,
+
+    // Locked passages between neighbours as (cell, neighbour, key), closed both ways without their key.
+    doors: Vec<(Coords, Coords, usize)>,
+
+    // Keys lying in cells, picked up by stepping into them.
+    keys: Vec<(Coords, usize)>,
 
-    size: i32,
+    // Passages between neighbours that open and close over time, as (cell, neighbour, schedule).
+    gates: Vec<(Coords, Coords, Schedule)>,
+
+    // Passages closed one way on purpose, as (from, to), so they are not taken for broken walls.
+    one_ways: Vec<(Coords, Coords)>,
+
+    // The latest connections added or removed, in order, so planners can repair their searches.
+    changes: VecDeque<(Coords, Coords)>,
+
+    // How many changes were ever made, which versions the graph for anyone caching work on it.
+    change_count: usize,
 }
 
 impl Graph {
     pub fn new(size: i32) -> Graph {
-        let mut graph = Graph { nodes: HashMap::new(), size };
+        return Graph::with_topology(Box::new(SquareTopology::new(size)));
+    }
 
-        graph.create_nodes();
-        graph.create_neighbors();
+    pub fn with_topology(topology: Box<dyn Topology>) -> Graph {
+        let nodes = vec![Node::Node::default(); topology.get_cell_count()];
 
-        return graph;
+        return Graph { topology, nodes, portals: vec![], doors: vec![], keys: vec![], gates: vec![], one_ways: vec![], changes: VecDeque::new(), change_count: 0 };
     }
 
-    fn create_nodes(&mut self) {
-        for x in 0..self.size {
-            for y in 0..self.size {
-                self.add_node(x, y);
+    // Moves the walls over to a topology with the same cells, such as the same grid with diagonals.
+    pub fn set_topology(&mut self, topology: Box<dyn Topology>) {
+        let mut connections: Vec<(Coords, Coords)> = vec![];
+
+        for node_coords in self.iter_nodes() {
+            for neighbor_coords in self.iter_wall_neighbors(node_coords) {
+                if self.is_connected(node_coords, neighbor_coords) {
+                    connections.push((node_coords, neighbor_coords));
+                }
             }
         }
-    }
 
-    fn create_neighbors(&mut self) {
         for node_coords in self.get_nodes() {
-            let candidates: Vec<Coords> = vec![
-                (node_coords.0 + 1, node_coords.1),
-                (node_coords.0 - 1, node_coords.1),
-                (node_coords.0, node_coords.1 - 1),
-                (node_coords.0, node_coords.1 + 1)
-            ];
+            for neighbor_coords in self.get_connections(node_coords) {
+                self.record_change((node_coords, neighbor_coords));
+            }
+        }
 
-            for neighbor_coords in candidates {
-                if !self.is_valid_node_coordinate(neighbor_coords) { continue; }
+        self.nodes = vec![Node::Node::default(); topology.get_cell_count()];
+        self.topology = topology;
 
-                if node_coords == neighbor_coords { continue; }
+        for (node_coords, neighbor_coords) in connections {
+            self.add_connection(node_coords, neighbor_coords);
+        }
+    }
 
-                {
-                    let opt_node = self.get_node_mut(node_coords);
+    // Only walls can be carved or closed, so steps across corners are left to refresh_corners.
+    fn get_cell(&self, node_coords: Coords, neighbor_coords: Coords) -> Option<(usize, usize)> {
+        let index = self.topology.get_index(node_coords)?;
+        let direction = self.topology.get_direction(node_coords, neighbor_coords)?;
 
-                    if opt_node.is_none() { continue; }
+        if direction >= self.topology.get_wall_count(node_coords) { return None; }
 
-                    let node = opt_node.unwrap();
+        return Some((index, direction));
+    }
 
-                    node.add_neighbor(neighbor_coords);
-                }
+    // Each route around the corner also has to pass is_leg_open on both of its steps.
+    fn is_corner_open(&self, node_coords: Coords, direction: usize, corner_cutting: CornerCutting, is_leg_open: &dyn Fn(Coords, Coords) -> bool) -> bool {
+        let offset = self.topology.get_directions(node_coords)[direction];
 
-                let opt_neighbor = self.get_node_mut(neighbor_coords);
+        let neighbor_coords = (node_coords.0 + offset.0, node_coords.1 + offset.1);
 
-                if opt_neighbor.is_none() { return; }
+        if !self.is_valid_node_coordinate(neighbor_coords) { return false; }
 
-                let neighbor = opt_neighbor.unwrap();
+        let mut open_routes = 0;
 
-                neighbor.add_neighbor(node_coords);
+        for corner_coords in self.topology.get_corner_cells(node_coords, direction) {
+            if self.is_connected(node_coords, corner_coords) && self.is_connected(corner_coords, neighbor_coords)
+                && is_leg_open(node_coords, corner_coords) && is_leg_open(corner_coords, neighbor_coords) {
+                open_routes += 1;
             }
         }
+
+        match corner_cutting {
+            CornerCutting::Allowed => return open_routes > 0,
+            CornerCutting::Disallowed => return open_routes == 2,
+        }
     }
 
-    pub fn add_node(&mut self, x: i32, y: i32) {
-        let node = Node::Node::new(x, y);
+    // Recomputes the steps across corners next to a cell after one of its walls changed.
+    fn refresh_corners(&mut self, coords: Coords) {
+        let opt_corner_cutting = self.topology.get_corner_cutting();
+
+        if opt_corner_cutting.is_none() { return; }
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let node_coords = (coords.0 + dx, coords.1 + dy);
+
+                let opt_index = self.topology.get_index(node_coords);
+
+                if opt_index.is_none() { continue; }
 
-        self.nodes.insert(node.get_coords(), node);
+                let index = opt_index.unwrap();
+
+                for direction in self.topology.get_wall_count(node_coords)..self.topology.get_directions(node_coords).len() {
+                    let is_open = self.is_corner_open(node_coords, direction, opt_corner_cutting.unwrap(), &|_, _| true);
+
+                    if is_open == self.nodes[index].is_connected_to(direction) { continue; }
+
+                    if is_open {
+                        self.nodes[index].add_connection(direction);
+                    } else {
+                        self.nodes[index].remove_connection(direction);
+                    }
+
+                    let offset = self.topology.get_directions(node_coords)[direction];
+
+                    self.record_change((node_coords, (node_coords.0 + offset.0, node_coords.1 + offset.1)));
+                }
+            }
+        }
     }
 
     pub fn add_connection(&mut self, node_coords: Coords, neighbor_coords: Coords) {
-        let opt_node = self.get_node_mut(node_coords);
+        let opt_cell = self.get_cell(node_coords, neighbor_coords);
 
-        match opt_node {
-            None => {},
-            Some(node) => {
-                node.add_connection(neighbor_coords);
-            }
+        match opt_cell {
+            None => return,
+            Some((index, direction)) => self.nodes[index].add_connection(direction),
         }
+
+        self.record_change((node_coords, neighbor_coords));
+
+        self.refresh_corners(node_coords);
     }
 
     pub fn remove_connection(&mut self, node_coords: Coords, neighbor_coords: Coords) {
-        let opt_node = self.get_node_mut(node_coords);
+        let opt_cell = self.get_cell(node_coords, neighbor_coords);
 
-        match opt_node {
-            None => {},
-            Some(node) => {
-                node.remove_connection(neighbor_coords);
-            }
+        match opt_cell {
+            None => return,
+            Some((index, direction)) => self.nodes[index].remove_connection(direction),
         }
+
+        self.record_change((node_coords, neighbor_coords));
+
+        self.refresh_corners(node_coords);
     }
 
-    pub fn get_node(&self, coords: Coords) -> Option<&Node::Node> {
-        return self.nodes.get(&coords);
+    pub fn iter_nodes(&self) -> impl Iterator<Item = Coords> + '_ {
+        return (0..self.topology.get_cell_count()).map(|index| self.topology.get_coords(index));
     }
 
-    pub fn get_node_mut(&mut self, coords: Coords) -> Option<&mut Node::Node> {
-        return self.nodes.get_mut(&coords);
+    pub fn iter_neighbors(&self, node_coords: Coords) -> impl Iterator<Item = Coords> + '_ {
+        let is_valid = self.is_valid_node_coordinate(node_coords);
+
+        return self.topology.get_directions(node_coords).iter()
+            .map(move |direction| (node_coords.0 + direction.0, node_coords.1 + direction.1))
+            .filter(move |neighbor_coords| is_valid && self.is_valid_node_coordinate(*neighbor_coords));
     }
 
-    pub fn get_nodes(&self) -> Vec<Coords> {
-        let mut nodes: Vec<Coords> = vec![];
+    pub fn add_portal(&mut self, entry_coords: Coords, exit_coords: Coords) {
+        if !self.is_valid_node_coordinate(entry_coords) || !self.is_valid_node_coordinate(exit_coords) { return; }
+
+        // Neighbours already have a passage between them for this.
+        if entry_coords == exit_coords || self.topology.get_direction(entry_coords, exit_coords).is_some() { return; }
 
-        for coords in self.nodes.keys() {
-            nodes.push(*coords);
+        if self.portals.contains(&(entry_coords, exit_coords)) { return; }
+
+        self.portals.push((entry_coords, exit_coords));
+
+        self.record_change((entry_coords, exit_coords));
+    }
+
+    pub fn remove_portal(&mut self, entry_coords: Coords, exit_coords: Coords) {
+        let opt_position = self.portals.iter().position(|portal| *portal == (entry_coords, exit_coords));
+
+        match opt_position {
+            None => return,
+            Some(position) => { self.portals.remove(position); },
         }
 
-        return nodes;
+        self.record_change((entry_coords, exit_coords));
     }
 
-    pub fn get_neighbors(&self, node_coords: Coords) -> Vec<Coords> {
-        let opt_node = self.get_node(node_coords);
+    pub fn get_portals(&self) -> Vec<(Coords, Coords)> {
+        return self.portals.clone();
+    }
+
+    pub fn is_portal(&self, entry_coords: Coords, exit_coords: Coords) -> bool {
+        return self.portals.contains(&(entry_coords, exit_coords));
+    }
 
-        match opt_node {
-            None => return vec![],
-            Some(node) => return node.get_neighbors(),
+    pub fn add_door(&mut self, node_coords: Coords, neighbor_coords: Coords, key: usize) {
+        if key >= MAX_KEYS || self.get_cell(node_coords, neighbor_coords).is_none() { return; }
+
+        if !self.is_valid_node_coordinate(neighbor_coords) || self.get_door(node_coords, neighbor_coords).is_some() { return; }
+
+        self.doors.push((node_coords, neighbor_coords, key));
+
+        self.record_change((node_coords, neighbor_coords));
+        self.record_change((neighbor_coords, node_coords));
+    }
+
+    pub fn remove_door(&mut self, node_coords: Coords, neighbor_coords: Coords) {
+        let opt_position = self.doors.iter().position(|(from_coords, to_coords, _)| {
+            return (*from_coords, *to_coords) == (node_coords, neighbor_coords) || (*from_coords, *to_coords) == (neighbor_coords, node_coords);
+        });
+
+        match opt_position {
+            None => return,
+            Some(position) => { self.doors.remove(position); },
         }
+
+        self.record_change((node_coords, neighbor_coords));
+        self.record_change((neighbor_coords, node_coords));
     }
 
-    pub fn get_connections(&self, node_coords: Coords) -> Vec<Coords> {
-        let opt_node = self.get_node(node_coords);
+    pub fn add_key(&mut self, coords: Coords, key: usize) {
+        if key >= MAX_KEYS || !self.is_valid_node_coordinate(coords) || self.get_key(coords).is_some() { return; }
+
+        self.keys.push((coords, key));
+
+        self.record_change((coords, coords));
+    }
+
+    pub fn clear_doors(&mut self) {
+        for (node_coords, neighbor_coords, _) in self.doors.clone() {
+            self.record_change((node_coords, neighbor_coords));
+            self.record_change((neighbor_coords, node_coords));
+        }
 
-        match opt_node {
-            None => return vec![],
-            Some(node) => return node.get_connections(),
+        for (coords, _) in self.keys.clone() {
+            self.record_change((coords, coords));
         }
+
+        self.doors.clear();
+        self.keys.clear();
     }
 
-    pub fn is_connected(&self, node_coords: Coords, neighbor_coords: Coords) -> bool {
-        let opt_node = self.get_node(node_coords);
+    // The key a passage needs, whichever side it is crossed from.
+    pub fn get_door(&self, node_coords: Coords, neighbor_coords: Coords) -> Option<usize> {
+        return self.doors.iter()
+            .find(|(from_coords, to_coords, _)| (*from_coords, *to_coords) == (node_coords, neighbor_coords) || (*from_coords, *to_coords) == (neighbor_coords, node_coords))
+            .map(|(_, _, key)| *key);
+    }
+
+    pub fn get_doors(&self) -> Vec<(Coords, Coords, usize)> {
+        return self.doors.clone();
+    }
+
+    pub fn get_key(&self, coords: Coords) -> Option<usize> {
+        return self.keys.iter().find(|(key_coords, _)| *key_coords == coords).map(|(_, key)| *key);
+    }
 
-        match opt_node {
+    pub fn get_keys(&self) -> Vec<(Coords, usize)> {
+        return self.keys.clone();
+    }
+
+    // What holding the keys changes about one step. Only the passage itself is checked, not its walls.
+    fn is_unlocked(&self, node_coords: Coords, neighbor_coords: Coords, inventory: Inventory) -> bool {
+        match self.get_door(node_coords, neighbor_coords) {
             None => return true,
-            Some(node) => return node.is_connected_to(neighbor_coords),
+            Some(key) => return inventory & (1 << key) != 0,
+        }
+    }
+
+    // Whether a step is open when the passages it uses also have to pass is_leg_open. A step across
+    // a corner is judged by the routes around it, so it cannot slip past a closed door or gate.
+    fn is_step_open(&self, node_coords: Coords, neighbor_coords: Coords, is_leg_open: &dyn Fn(Coords, Coords) -> bool) -> bool {
+        if !self.is_connected(node_coords, neighbor_coords) { return false; }
+
+        let opt_direction = self.topology.get_direction(node_coords, neighbor_coords);
+
+        let opt_corner_cutting = self.topology.get_corner_cutting();
+
+        if opt_direction.is_none() || opt_corner_cutting.is_none() || opt_direction.unwrap() < self.topology.get_wall_count(node_coords) {
+            return is_leg_open(node_coords, neighbor_coords);
+        }
+
+        return self.is_corner_open(node_coords, opt_direction.unwrap(), opt_corner_cutting.unwrap(), is_leg_open);
+    }
+
+    // Whether a step is open to someone holding the given keys.
+    pub fn can_pass(&self, node_coords: Coords, neighbor_coords: Coords, inventory: Inventory) -> bool {
+        return self.is_step_open(node_coords, neighbor_coords, &|from_coords, to_coords| self.is_unlocked(from_coords, to_coords, inventory));
+    }
+
+    pub fn add_gate(&mut self, node_coords: Coords, neighbor_coords: Coords, schedule: Schedule) {
+        if self.get_cell(node_coords, neighbor_coords).is_none() || !self.is_valid_node_coordinate(neighbor_coords) { return; }
+
+        if self.get_gate(node_coords, neighbor_coords).is_some() { return; }
+
+        self.gates.push((node_coords, neighbor_coords, schedule));
+
+        self.record_change((node_coords, neighbor_coords));
+        self.record_change((neighbor_coords, node_coords));
+    }
+
+    pub fn clear_gates(&mut self) {
+        for (node_coords, neighbor_coords, _) in self.gates.clone() {
+            self.record_change((node_coords, neighbor_coords));
+            self.record_change((neighbor_coords, node_coords));
+        }
+
+        self.gates.clear();
+    }
+
+    // The schedule on a passage, whichever side it is crossed from.
+    pub fn get_gate(&self, node_coords: Coords, neighbor_coords: Coords) -> Option<Schedule> {
+        return self.gates.iter()
+            .find(|(from_coords, to_coords, _)| (*from_coords, *to_coords) == (node_coords, neighbor_coords) || (*from_coords, *to_coords) == (neighbor_coords, node_coords))
+            .map(|(_, _, schedule)| *schedule);
+    }
+
+    pub fn get_gates(&self) -> Vec<(Coords, Coords, Schedule)> {
+        return self.gates.clone();
+    }
+
+    fn is_gate_open(&self, node_coords: Coords, neighbor_coords: Coords, time: usize) -> bool {
+        match self.get_gate(node_coords, neighbor_coords) {
+            None => return true,
+            Some(schedule) => return schedule.is_open(time),
+        }
+    }
+
+    // Whether a step can be started at the given timestep.
+    pub fn is_open_at(&self, node_coords: Coords, neighbor_coords: Coords, time: usize) -> bool {
+        return self.is_step_open(node_coords, neighbor_coords, &|from_coords, to_coords| self.is_gate_open(from_coords, to_coords, time));
+    }
+
+    // Opens the passage from one cell to the other and closes it the other way.
+    pub fn set_one_way(&mut self, from_coords: Coords, to_coords: Coords) {
+        self.add_connection(from_coords, to_coords);
+        self.remove_connection(to_coords, from_coords);
+
+        self.one_ways.retain(|passage| *passage != (to_coords, from_coords));
+
+        if !self.one_ways.contains(&(from_coords, to_coords)) {
+            self.one_ways.push((from_coords, to_coords));
+        }
+    }
+
+    pub fn is_one_way(&self, node_coords: Coords, neighbor_coords: Coords) -> bool {
+        return self.is_connected(node_coords, neighbor_coords) != self.is_connected(neighbor_coords, node_coords);
+    }
+
+    // Whether a passage is open only from this cell because set_one_way made it so.
+    pub fn is_set_one_way(&self, from_coords: Coords, to_coords: Coords) -> bool {
+        return self.one_ways.contains(&(from_coords, to_coords)) && self.is_connected(from_coords, to_coords) && !self.is_connected(to_coords, from_coords);
+    }
+
+    // Neighbours across a wall, which is what a maze is carved between.
+    pub fn iter_wall_neighbors(&self, node_coords: Coords) -> impl Iterator<Item = Coords> + '_ {
+        let is_valid = self.is_valid_node_coordinate(node_coords);
+
+        let wall_count = self.topology.get_wall_count(node_coords);
+
+        return self.topology.get_directions(node_coords)[..wall_count].iter()
+            .map(move |direction| (node_coords.0 + direction.0, node_coords.1 + direction.1))
+            .filter(move |neighbor_coords| is_valid && self.is_valid_node_coordinate(*neighbor_coords));
+    }
+
+    pub fn iter_connections(&self, node_coords: Coords) -> impl Iterator<Item = Coords> + '_ {
+        let opt_node = self.topology.get_index(node_coords).map(|index| self.nodes[index]);
+
+        return self.topology.get_directions(node_coords).iter()
+            .enumerate()
+            .filter(move |(direction, _)| opt_node.is_some_and(|node| node.is_connected_to(*direction)))
+            .map(move |(_, direction)| (node_coords.0 + direction.0, node_coords.1 + direction.1))
+            .chain(self.portals.iter().filter(move |(entry_coords, _)| *entry_coords == node_coords).map(|(_, exit_coords)| *exit_coords));
+    }
+
+    // The cells with a passage or portal into this one, the reverse of iter_connections.
+    pub fn iter_predecessors(&self, node_coords: Coords) -> impl Iterator<Item = Coords> + '_ {
+        return self.iter_neighbors(node_coords)
+            .filter(move |neighbor_coords| self.is_connected(*neighbor_coords, node_coords))
+            .chain(self.portals.iter().filter(move |(_, exit_coords)| *exit_coords == node_coords).map(|(entry_coords, _)| *entry_coords));
+    }
+
+    pub fn get_nodes(&self) -> Vec<Coords> {
+        return self.iter_nodes().collect();
+    }
+
+    pub fn get_neighbors(&self, node_coords: Coords) -> Vec<Coords> {
+        return self.iter_neighbors(node_coords).collect();
+    }
+
+    pub fn get_wall_neighbors(&self, node_coords: Coords) -> Vec<Coords> {
+        return self.iter_wall_neighbors(node_coords).collect();
+    }
+
+    pub fn get_connections(&self, node_coords: Coords) -> Vec<Coords> {
+        return self.iter_connections(node_coords).collect();
+    }
+
+    pub fn get_predecessors(&self, node_coords: Coords) -> Vec<Coords> {
+        return self.iter_predecessors(node_coords).collect();
+    }
+
+    pub fn is_connected(&self, node_coords: Coords, neighbor_coords: Coords) -> bool {
+        let opt_index = self.topology.get_index(node_coords);
+
+        if opt_index.is_none() { return true; }
+
+        let opt_direction = self.topology.get_direction(node_coords, neighbor_coords);
+
+        match opt_direction {
+            None => return self.is_portal(node_coords, neighbor_coords),
+            Some(direction) => return self.nodes[opt_index.unwrap()].is_connected_to(direction),
+        }
+    }
+
+    pub fn get_step_cost(&self, node_coords: Coords, neighbor_coords: Coords) -> i32 {
+        if self.is_portal(node_coords, neighbor_coords) {
+            return self.topology.get_unit_cost();
         }
+
+        return self.topology.get_distance(node_coords, neighbor_coords);
+    }
+
+    // A snapshot of the costs for solvers, which keep it rather than the graph between steps.
+    pub fn get_metric(&self) -> Metric {
+        return Metric::new(self.topology.clone_box(), self.portals.clone());
+    }
+
+    pub fn get_topology(&self) -> &dyn Topology {
+        return self.topology.as_ref();
+    }
+
+    fn record_change(&mut self, change: (Coords, Coords)) {
+        if self.changes.len() == MAX_CHANGES {
+            self.changes.pop_front();
+        }
+
+        self.changes.push_back(change);
+
+        self.change_count += 1;
+    }
+
+    pub fn get_change_count(&self) -> usize {
+        return self.change_count;
+    }
+
+    // The changes made after the given count, or None once some of them have been dropped.
+    pub fn get_changes_since(&self, change_count: usize) -> Option<impl Iterator<Item = &(Coords, Coords)>> {
+        let first_kept = self.change_count - self.changes.len();
+
+        if change_count < first_kept { return None; }
+
+        return Some(self.changes.iter().skip(usize::min(change_count, self.change_count) - first_kept));
     }
 
     pub fn is_valid_node_coordinate(&self, coords: Coords) -> bool {
-        return 0 <= coords.0 && coords.0 < self.size && 0 <= coords.1 && coords.1 < self.size
+        return self.topology.get_index(coords).is_some();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Graph, MAX_CHANGES};
+
+    #[test]
+    fn connections_open_and_close_one_way_at_a_time() {
+        let mut graph = Graph::new(4);
+
+        graph.add_connection((1, 1), (1, 2));
+
+        assert!(graph.is_connected((1, 1), (1, 2)));
+        assert!(!graph.is_connected((1, 2), (1, 1)));
+
+        assert_eq!(graph.iter_connections((1, 1)).collect::<Vec<_>>(), vec![(1, 2)]);
+        assert_eq!(graph.iter_connections((1, 2)).count(), 0);
+
+        graph.add_connection((1, 1), (0, 1));
+
+        let mut connections: Vec<_> = graph.iter_connections((1, 1)).collect();
+
+        connections.sort();
+
+        assert_eq!(connections, vec![(0, 1), (1, 2)]);
+
+        graph.remove_connection((1, 1), (1, 2));
+
+        assert!(!graph.is_connected((1, 1), (1, 2)));
+        assert_eq!(graph.iter_connections((1, 1)).collect::<Vec<_>>(), vec![(0, 1)]);
+
+        // Closing what is already closed changes nothing.
+        graph.remove_connection((1, 1), (1, 2));
+
+        assert_eq!(graph.iter_connections((1, 1)).collect::<Vec<_>>(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn cells_that_are_not_neighbours_cannot_be_connected() {
+        let mut graph = Graph::new(4);
+
+        for neighbor_coords in [(2, 2), (1, 1), (0, 0), (3, 0)] {
+            graph.add_connection((0, 0), neighbor_coords);
+
+            assert!(!graph.is_connected((0, 0), neighbor_coords), "{:?}", neighbor_coords);
+        }
+
+        graph.remove_connection((0, 0), (2, 2));
+
+        assert_eq!(graph.iter_connections((0, 0)).count(), 0);
+        assert_eq!(graph.get_change_count(), 0);
     }
 
-    pub fn get_size(&self) -> i32 {
-        return self.size;
+    #[test]
+    fn out_of_bounds_cells_hold_no_connections() {
+        let mut graph = Graph::new(3);
+
+        graph.add_connection((-1, 0), (0, 0));
+        graph.add_connection((3, 3), (2, 3));
+
+        assert_eq!(graph.iter_connections((-1, 0)).count(), 0);
+        assert_eq!(graph.iter_connections((3, 3)).count(), 0);
+        assert!(!graph.is_connected((0, 0), (-1, 0)));
+
+        // Anything outside the grid is taken as open, so nothing is drawn or searched past its edge.
+        assert!(graph.is_connected((-1, 0), (0, 0)));
+        assert!(graph.is_connected((5, 5), (9, 9)));
+
+        // A passage out through the edge can still be carved, which validation reports.
+        graph.add_connection((0, 0), (-1, 0));
+
+        assert!(graph.is_connected((0, 0), (-1, 0)));
+        assert_eq!(graph.iter_connections((0, 0)).collect::<Vec<_>>(), vec![(-1, 0)]);
+
+        graph.remove_connection((0, 0), (-1, 0));
+
+        assert_eq!(graph.iter_connections((0, 0)).count(), 0);
+    }
+
+    #[test]
+    fn change_log_keeps_only_the_latest_changes() {
+        let mut graph = Graph::new(4);
+
+        for _ in 0..MAX_CHANGES {
+            graph.add_connection((0, 0), (1, 0));
+            graph.remove_connection((0, 0), (1, 0));
+        }
+
+        graph.add_connection((2, 2), (2, 3));
+
+        let change_count = graph.get_change_count();
+
+        assert_eq!(change_count, MAX_CHANGES * 2 + 1);
+
+        assert!(graph.get_changes_since(0).is_none());
+        assert!(graph.get_changes_since(change_count - MAX_CHANGES - 1).is_none());
+
+        assert_eq!(graph.get_changes_since(change_count - MAX_CHANGES).unwrap().count(), MAX_CHANGES);
+        assert_eq!(graph.get_changes_since(change_count - 1).unwrap().collect::<Vec<_>>(), vec![&((2, 2), (2, 3))]);
+        assert_eq!(graph.get_changes_since(change_count).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn one_way_passages_open_only_from_where_they_start() {
+        let mut graph = Graph::new(3);
+
+        graph.add_connection((1, 1), (2, 1));
+        graph.add_connection((2, 1), (1, 1));
+
+        graph.set_one_way((2, 1), (1, 1));
+
+        assert!(graph.is_connected((2, 1), (1, 1)));
+        assert!(!graph.is_connected((1, 1), (2, 1)));
+
+        assert!(graph.is_one_way((2, 1), (1, 1)));
+        assert!(graph.is_one_way((1, 1), (2, 1)));
+
+        assert_eq!(graph.get_connections((1, 1)), vec![]);
+        assert_eq!(graph.get_predecessors((1, 1)), vec![(2, 1)]);
+
+        // A closed wall can be opened one way as well.
+        graph.set_one_way((1, 1), (1, 2));
+
+        assert_eq!(graph.get_connections((1, 1)), vec![(1, 2)]);
+        assert_eq!(graph.get_predecessors((1, 1)), vec![(2, 1)]);
+        assert!(!graph.is_one_way((0, 0), (1, 0)));
     }
 }
@@ -0,0 +1,494 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+use priority_queue::PriorityQueue;
+
+use super::Graph;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+pub const DEFAULT_CLUSTER_SIZE: i32 = 8;
+
+type Edges = HashMap<Coords, Vec<(Coords, i32)>>;
+type Routes = HashMap<(Coords, Coords), Vec<Coords>>;
+type Crossing = (Coords, Coords);
+
+// Entrance cells joined by their costs, so long queries only touch a handful of nodes per cluster.
+struct Abstraction {
+    change_count: usize,
+    cell_count: usize,
+
+    edges: Edges,
+    // Cell by cell walks inside a cluster that refine an abstract edge, listed from its source.
+    routes: Routes,
+}
+
+pub struct HierarchicalPlanner {
+    cluster_size: i32,
+    opt_abstraction: Option<Abstraction>,
+
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    // Edges and routes that hook the start and goal into the abstraction for one query.
+    query_edges: Edges,
+    query_routes: Routes,
+    is_query_inserted: bool,
+
+    open_set: PriorityQueue<Coords, (Reverse<i32>, i32)>,
+    came_from: HashMap<Coords, Coords>,
+    closed_set: Vec<Coords>,
+    g_score: HashMap<Coords, i32>,
+
+    metric: Metric,
+
+    stats: SearchStats,
+
+    abstract_path: Vec<Coords>,
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl HierarchicalPlanner {
+    pub fn new(cluster_size: i32) -> HierarchicalPlanner {
+        return HierarchicalPlanner {
+            cluster_size,
+            opt_abstraction: None,
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            query_edges: HashMap::new(),
+            query_routes: HashMap::new(),
+            is_query_inserted: false,
+            open_set: PriorityQueue::new(),
+            came_from: HashMap::new(),
+            closed_set: vec![],
+            g_score: HashMap::new(),
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            abstract_path: vec![],
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn get_cluster(&self, coords: Coords) -> Coords {
+        return (coords.0 / self.cluster_size, coords.1 / self.cluster_size);
+    }
+
+    fn build_abstraction(&self, graph: &Graph::Graph) -> Abstraction {
+        let mut entrances: HashSet<Coords> = HashSet::new();
+        let mut edges: Edges = HashMap::new();
+        let mut routes: Routes = HashMap::new();
+
+        // Open passages from one cluster into another, listed once under the pair of clusters they join.
+        let mut crossings: HashMap<(Coords, Coords), Vec<Crossing>> = HashMap::new();
+
+        for node_coords in graph.iter_nodes() {
+            for neighbor_coords in graph.iter_wall_neighbors(node_coords) {
+                let (cluster, neighbor_cluster) = (self.get_cluster(node_coords), self.get_cluster(neighbor_coords));
+
+                if cluster >= neighbor_cluster { continue; }
+
+                if !graph.is_connected(node_coords, neighbor_coords) && !graph.is_connected(neighbor_coords, node_coords) { continue; }
+
+                crossings.entry((cluster, neighbor_cluster)).or_default().push((node_coords, neighbor_coords));
+            }
+        }
+
+        let mut borders: Vec<((Coords, Coords), Vec<Crossing>)> = crossings.into_iter().collect();
+
+        borders.sort();
+
+        // Each run of crossings along a border becomes a single crossing at its middle.
+        for (_, border_crossings) in borders {
+            for run in HierarchicalPlanner::get_runs(graph, &border_crossings) {
+                let (inside_coords, outside_coords) = run[run.len() / 2];
+
+                entrances.insert(inside_coords);
+                entrances.insert(outside_coords);
+
+                for (from_coords, to_coords) in [(inside_coords, outside_coords), (outside_coords, inside_coords)] {
+                    if !graph.is_connected(from_coords, to_coords) { continue; }
+
+                    edges.entry(from_coords).or_default().push((to_coords, graph.get_step_cost(from_coords, to_coords)));
+                    routes.insert((from_coords, to_coords), vec![from_coords, to_coords]);
+                }
+            }
+        }
+
+        // Portals jump between clusters wherever they like, so both ends join the abstraction as entrances.
+        for (entry_coords, exit_coords) in graph.get_portals() {
+            entrances.insert(entry_coords);
+            entrances.insert(exit_coords);
+
+            edges.entry(entry_coords).or_default().push((exit_coords, graph.get_step_cost(entry_coords, exit_coords)));
+            routes.insert((entry_coords, exit_coords), vec![entry_coords, exit_coords]);
+        }
+
+        let mut clusters: HashMap<Coords, Vec<Coords>> = HashMap::new();
+
+        for entrance_coords in &entrances {
+            clusters.entry(self.get_cluster(*entrance_coords)).or_default().push(*entrance_coords);
+        }
+
+        for members in clusters.values() {
+            for from_coords in members {
+                self.connect_within_cluster(graph, *from_coords, members, &mut edges, &mut routes);
+            }
+        }
+
+        return Abstraction { change_count: graph.get_change_count(), cell_count: graph.get_topology().get_cell_count(), edges, routes };
+    }
+
+    // Splits the crossings of one border, taken in order along it, into runs where each crossing's cells are
+    // linked to the previous crossing's on both sides.
+    fn get_runs(graph: &Graph::Graph, crossings: &[Crossing]) -> Vec<Vec<Crossing>> {
+        let mut runs: Vec<Vec<Crossing>> = vec![];
+
+        for crossing in crossings {
+            let opt_run = runs.iter_mut().find(|run| {
+                let previous = run.last().unwrap();
+
+                return graph.is_connected(previous.0, crossing.0) && graph.is_connected(previous.1, crossing.1);
+            });
+
+            match opt_run {
+                None => runs.push(vec![*crossing]),
+                Some(run) => run.push(*crossing),
+            }
+        }
+
+        return runs;
+    }
+
+    // Breadth-first search that never leaves the cluster of `from_coords`, adding an edge to every target it reaches.
+    fn connect_within_cluster(&self, graph: &Graph::Graph, from_coords: Coords, targets: &[Coords], edges: &mut Edges, routes: &mut Routes) {
+        let cluster = self.get_cluster(from_coords);
+
+        let mut came_from: HashMap<Coords, Coords> = HashMap::new();
+        let mut visited: HashSet<Coords> = HashSet::from([from_coords]);
+        let mut queue: VecDeque<Coords> = VecDeque::from([from_coords]);
+
+        while let Some(node_coords) = queue.pop_front() {
+            for connection_coords in graph.iter_connections(node_coords) {
+                if self.get_cluster(connection_coords) != cluster || !visited.insert(connection_coords) { continue; }
+
+                came_from.insert(connection_coords, node_coords);
+
+                queue.push_back(connection_coords);
+            }
+        }
+
+        for to_coords in targets {
+            if *to_coords == from_coords || !visited.contains(to_coords) { continue; }
+
+            let mut route = vec![*to_coords];
+
+            while let Some(parent_coords) = came_from.get(route.last().unwrap()) {
+                route.push(*parent_coords);
+            }
+
+            route.reverse();
+
+            let cost = route.windows(2).map(|pair| graph.get_step_cost(pair[0], pair[1])).sum();
+
+            edges.entry(from_coords).or_default().push((*to_coords, cost));
+            routes.insert((from_coords, *to_coords), route);
+        }
+    }
+
+    fn insert_query(&mut self, graph: &Graph::Graph) {
+        let abstraction = self.opt_abstraction.as_ref().unwrap();
+
+        let mut query_edges: Edges = HashMap::new();
+        let mut query_routes: Routes = HashMap::new();
+
+        let start_members: Vec<Coords> = abstraction.edges.keys()
+            .filter(|coords| self.get_cluster(**coords) == self.get_cluster(self.start_coords))
+            .copied()
+            .chain([self.goal_coords])
+            .collect();
+
+        self.connect_within_cluster(graph, self.start_coords, &start_members, &mut query_edges, &mut query_routes);
+
+        let goal_members: Vec<Coords> = abstraction.edges.keys()
+            .filter(|coords| self.get_cluster(**coords) == self.get_cluster(self.goal_coords))
+            .copied()
+            .collect();
+
+        for from_coords in goal_members {
+            self.connect_within_cluster(graph, from_coords, &[self.goal_coords], &mut query_edges, &mut query_routes);
+        }
+
+        self.query_edges = query_edges;
+        self.query_routes = query_routes;
+    }
+
+    fn get_edges(&self, node_coords: Coords) -> Vec<(Coords, i32)> {
+        let abstraction = self.opt_abstraction.as_ref().unwrap();
+
+        let mut edges = abstraction.edges.get(&node_coords).cloned().unwrap_or_default();
+
+        edges.extend(self.query_edges.get(&node_coords).cloned().unwrap_or_default());
+
+        return edges;
+    }
+
+    fn refine_path(&mut self) {
+        let abstraction = self.opt_abstraction.as_ref().unwrap();
+
+        let mut path = vec![self.goal_coords];
+
+        for pair in self.abstract_path.windows(2) {
+            let key = (pair[1], pair[0]);
+
+            let opt_route = self.query_routes.get(&key).or(abstraction.routes.get(&key));
+
+            path.extend(opt_route.unwrap().iter().rev().skip(1));
+        }
+
+        self.path = path;
+    }
+}
+
+impl Solver for HierarchicalPlanner {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.query_edges = HashMap::new();
+        self.query_routes = HashMap::new();
+        self.is_query_inserted = false;
+        self.open_set = PriorityQueue::new();
+        self.came_from = HashMap::new();
+        self.closed_set = vec![];
+        self.g_score = HashMap::from([(start_coords, 0)]);
+        self.stats = SearchStats::default();
+        self.abstract_path = vec![];
+        self.path = vec![];
+        self.is_finished = false;
+
+        self.open_set.push(start_coords, (Reverse(self.metric.get_distance(start_coords, goal_coords)), 0));
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        let is_stale = match &self.opt_abstraction {
+            None => true,
+            Some(abstraction) => abstraction.change_count != graph.get_change_count() || abstraction.cell_count != graph.get_topology().get_cell_count(),
+        };
+
+        if is_stale {
+            self.opt_abstraction = Some(self.build_abstraction(graph));
+        }
+
+        if !self.is_query_inserted {
+            self.insert_query(graph);
+
+            self.is_query_inserted = true;
+        }
+
+        let opt_current_coords = self.open_set.pop();
+
+        if opt_current_coords.is_none() {
+            self.is_finished = true;
+
+            return;
+        }
+
+        self.current_coords = opt_current_coords.unwrap().0;
+
+        if self.current_coords == self.goal_coords {
+            self.abstract_path = vec![self.goal_coords];
+
+            while let Some(parent_coords) = self.came_from.get(self.abstract_path.last().unwrap()) {
+                self.abstract_path.push(*parent_coords);
+            }
+
+            self.refine_path();
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        self.stats.record_expansion();
+        self.closed_set.push(self.current_coords);
+
+        let g = self.g_score[&self.current_coords];
+
+        for (edge_coords, cost) in self.get_edges(self.current_coords) {
+            let tentative_g_score = g + cost;
+
+            if tentative_g_score >= *self.g_score.get(&edge_coords).unwrap_or(&i32::MAX) { continue; }
+
+            self.came_from.insert(edge_coords, self.current_coords);
+            self.g_score.insert(edge_coords, tentative_g_score);
+
+            let f = tentative_g_score + self.metric.get_distance(edge_coords, self.goal_coords);
+
+            self.open_set.push(edge_coords, (Reverse(f), tentative_g_score));
+
+            self.stats.record_generated(1);
+            self.stats.record_frontier(self.open_set.len());
+        }
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return self.open_set.iter().map(|(node_coords, _)| *node_coords).collect();
+    }
+
+    fn get_cluster_size(&self) -> Option<i32> {
+        return Some(self.cluster_size);
+    }
+
+    fn get_abstract_path(&self) -> Vec<Coords> {
+        return self.abstract_path.clone();
+    }
+
+    fn get_closed_set(&self) -> Vec<Coords> {
+        return self.closed_set.clone();
+    }
+
+    fn get_came_from(&self) -> Vec<(Coords, Coords)> {
+        return self.came_from.iter().map(|(node_coords, parent_coords)| (*node_coords, *parent_coords)).collect();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::HierarchicalPlanner;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape};
+
+    #[test]
+    fn finds_a_route_whenever_one_exists_on_every_shape() {
+        let shapes = [Shape::Square, Shape::Diagonal(CornerCutting::Allowed), Shape::Diagonal(CornerCutting::Disallowed), Shape::Brick];
+
+        for seed in 0..120 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let shape = shapes[seed as usize % 4];
+            let open_chance = [0.5, 0.7, 0.9, 1.0][seed as usize / 4 % 4];
+
+            let size = rng.gen_range(2..30);
+
+            let graph = testing::random_grid(&mut rng, shape, size, open_chance);
+
+            let start_coords = testing::random_coords(&mut rng, &graph);
+            let goal_coords = testing::random_coords(&mut rng, &graph);
+
+            let path = testing::solve(&mut HierarchicalPlanner::new(rng.gen_range(2..9)), &graph, start_coords, goal_coords);
+
+            let opt_cost = testing::dijkstra(&graph, start_coords, goal_coords);
+
+            assert_eq!(!path.is_empty(), opt_cost.is_some(), "seed {} on {:?}: {:?} -> {:?}", seed, shape, start_coords, goal_coords);
+
+            if path.is_empty() { continue; }
+
+            assert_eq!((path[path.len() - 1], path[0]), (start_coords, goal_coords), "seed {}", seed);
+
+            assert!(testing::get_path_cost(&graph, &path) >= opt_cost.unwrap(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn open_square_grids_stray_at_most_a_cluster_per_border_crossed() {
+        for seed in 0..60 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let size = rng.gen_range(8..30);
+            let cluster_size = rng.gen_range(2..9);
+
+            let graph = testing::random_grid(&mut rng, Shape::Square, size, 1.0);
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+            let path = testing::solve(&mut HierarchicalPlanner::new(cluster_size), &graph, start_coords, goal_coords);
+
+            let opt_cost = testing::dijkstra(&graph, start_coords, goal_coords).unwrap();
+
+            // A straight route crosses this many borders, and going through the middle of each one
+            // instead of where the route crosses it adds at most a cluster's width.
+            let borders = i32::abs(start_coords.0 / cluster_size - goal_coords.0 / cluster_size) + i32::abs(start_coords.1 / cluster_size - goal_coords.1 / cluster_size);
+
+            let cost = testing::get_path_cost(&graph, &path);
+
+            assert!(cost <= opt_cost + cluster_size * borders, "seed {}: {} against {} over {} borders of {}", seed, cost, opt_cost, borders, cluster_size);
+        }
+    }
+
+    #[test]
+    fn rebuilds_the_abstraction_once_the_walls_change() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let mut graph = testing::random_grid(&mut rng, Shape::Square, 16, 0.8);
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+            let mut planner = HierarchicalPlanner::new(4);
+
+            let path = testing::solve(&mut planner, &graph, start_coords, goal_coords);
+
+            if path.len() < 2 { continue; }
+
+            // Closing a passage on the route found makes any abstraction built before it wrong.
+            let (node_coords, neighbor_coords) = (path[path.len() / 2], path[path.len() / 2 - 1]);
+
+            graph.remove_connection(node_coords, neighbor_coords);
+            graph.remove_connection(neighbor_coords, node_coords);
+
+            assert_ne!(planner.opt_abstraction.as_ref().unwrap().change_count, graph.get_change_count());
+
+            let path = testing::solve(&mut planner, &graph, start_coords, goal_coords);
+
+            assert_eq!(planner.opt_abstraction.as_ref().unwrap().change_count, graph.get_change_count(), "seed {}", seed);
+
+            assert_eq!(!path.is_empty(), testing::dijkstra(&graph, start_coords, goal_coords).is_some(), "seed {}", seed);
+
+            // A route refined from the old abstraction would step through the closed passage here.
+            testing::get_path_cost(&graph, &path);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+
+use super::Graph;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+struct Frame {
+    coords: Coords,
+    g: i32,
+    children: Option<Vec<Coords>>,
+}
+
+pub struct IdaStar {
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    threshold: i32,
+    next_threshold: i32,
+
+    stack: Vec<Frame>,
+    on_stack: HashSet<Coords>,
+    // The cheapest g each cell has been reached with this iteration. Reaching a cell again for no
+    // less only repeats work, which on grids with loops grows without bound. Keeping it makes this
+    // IDA* with a transposition table rather than the plain kind, so memory grows with the cells
+    // reached instead of the depth of the stack.
+    best_g: HashMap<Coords, i32>,
+
+    metric: Metric,
+
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl IdaStar {
+    pub fn new() -> IdaStar {
+        return IdaStar {
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            threshold: 0,
+            next_threshold: i32::MAX,
+            stack: vec![],
+            on_stack: HashSet::new(),
+            best_g: HashMap::new(),
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn h(&self, node_coords: Coords) -> i32 {
+        return self.metric.get_distance(node_coords, self.goal_coords);
+    }
+
+    fn push(&mut self, node_coords: Coords, g: i32) {
+        self.stack.push(Frame { coords: node_coords, g, children: None });
+
+        self.on_stack.insert(node_coords);
+        self.best_g.insert(node_coords, g);
+
+        self.stats.record_generated(1);
+        self.stats.record_frontier(self.best_g.len());
+    }
+
+    fn start_iteration(&mut self) {
+        self.stack = vec![];
+        self.on_stack = HashSet::new();
+        self.best_g = HashMap::new();
+        self.next_threshold = i32::MAX;
+
+        self.push(self.start_coords, 0);
+    }
+}
+
+impl Default for IdaStar {
+    fn default() -> IdaStar {
+        return IdaStar::new();
+    }
+}
+
+impl Solver for IdaStar {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.threshold = self.h(start_coords);
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+
+        self.start_iteration();
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        if self.stack.is_empty() {
+            if self.next_threshold == i32::MAX {
+                self.is_finished = true;
+
+                return;
+            }
+
+            self.threshold = self.next_threshold;
+
+            self.start_iteration();
+
+            return;
+        }
+
+        let top = self.stack.last_mut().unwrap();
+
+        self.current_coords = top.coords;
+
+        if top.coords == self.goal_coords {
+            self.path = self.stack.iter().rev().map(|frame| frame.coords).collect();
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        if top.children.is_none() {
+            let mut children = graph.get_connections(top.coords);
+
+            // Children are popped from the back, so this visits them in ascending order.
+            children.sort();
+            children.reverse();
+
+            top.children = Some(children);
+
+            self.stats.record_expansion();
+        }
+
+        let (node_coords, g) = (top.coords, top.g);
+
+        let opt_child_coords = top.children.as_mut().unwrap().pop();
+
+        if opt_child_coords.is_none() {
+            self.stack.pop();
+            self.on_stack.remove(&node_coords);
+
+            return;
+        }
+
+        let child_coords = opt_child_coords.unwrap();
+
+        if self.on_stack.contains(&child_coords) { return; }
+
+        let child_g = g + self.metric.get_step_cost(node_coords, child_coords);
+
+        if child_g >= *self.best_g.get(&child_coords).unwrap_or(&i32::MAX) { return; }
+
+        let child_f = child_g + self.h(child_coords);
+
+        if child_f > self.threshold {
+            self.next_threshold = i32::min(self.next_threshold, child_f);
+
+            return;
+        }
+
+        self.push(child_coords, child_g);
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return self.stack.iter().map(|frame| frame.coords).collect();
+    }
+
+    fn get_threshold(&self) -> Option<i32> {
+        return Some(self.threshold);
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::IdaStar;
+    use crate::Pathfinding::astar::Astar;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape, SquareTopology};
+
+    #[test]
+    fn matches_astar_cost_on_random_grids() {
+        let shapes = [Shape::Square, Shape::Diagonal(CornerCutting::Allowed), Shape::Diagonal(CornerCutting::Disallowed), Shape::Brick];
+
+        for seed in 0..80 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let shape = shapes[seed as usize % 4];
+
+            let size = rng.gen_range(2..10);
+
+            let graph = testing::random_grid(&mut rng, shape, size, [0.5, 0.7, 1.0][seed as usize / 4 % 3]);
+
+            let start_coords = testing::random_coords(&mut rng, &graph);
+            let goal_coords = testing::random_coords(&mut rng, &graph);
+
+            let astar_path = testing::solve(&mut Astar::new(), &graph, start_coords, goal_coords);
+            let ida_path = testing::solve(&mut IdaStar::new(), &graph, start_coords, goal_coords);
+
+            assert_eq!(ida_path.is_empty(), astar_path.is_empty(), "seed {} on {:?}: {:?} -> {:?}", seed, shape, start_coords, goal_coords);
+
+            assert_eq!(testing::get_path_cost(&graph, &ida_path), testing::get_path_cost(&graph, &astar_path), "seed {} on {:?}", seed, shape);
+        }
+    }
+
+    #[test]
+    fn finishes_on_diagonal_mazes_with_corner_cutting() {
+        for seed in 0..5 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let mut maze = testing::generate_maze(14);
+
+            maze.graph.set_topology(Box::new(SquareTopology::with_diagonals(14, CornerCutting::Allowed)));
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &maze.graph), testing::random_coords(&mut rng, &maze.graph));
+
+            let mut ida_star = IdaStar::new();
+
+            let path = testing::solve(&mut ida_star, &maze.graph, start_coords, goal_coords);
+
+            assert_eq!(Some(testing::get_path_cost(&maze.graph, &path)), testing::dijkstra(&maze.graph, start_coords, goal_coords), "seed {}", seed);
+
+            // The frontier counts every cell in the table, not just the stack the route was read off.
+            let max_frontier = ida_star.get_stats().max_frontier;
+
+            assert!(max_frontier >= path.len() && max_frontier <= maze.graph.get_topology().get_cell_count(), "seed {}: {}", seed, max_frontier);
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use priority_queue::PriorityQueue;
+
+use super::Graph;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+const INFINITY: i32 = i32::MAX / 4;
+
+// LPA* searches forward from the start and repairs its plan in place. D* Lite is the same
+// search run backwards from the goal, which lets an agent walk the plan while it is repaired.
+pub struct IncrementalPlanner {
+    moves_agent: bool,
+
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+    last_coords: Coords,
+    key_modifier: i32,
+
+    open_set: PriorityQueue<Coords, Reverse<(i32, i32)>>,
+    g_score: HashMap<Coords, i32>,
+    rhs_score: HashMap<Coords, i32>,
+    seen_changes: Option<usize>,
+
+    metric: Metric,
+
+    stats: SearchStats,
+
+    trail: Vec<Coords>,
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl IncrementalPlanner {
+    pub fn new(moves_agent: bool) -> IncrementalPlanner {
+        return IncrementalPlanner {
+            moves_agent,
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            last_coords: (0, 0),
+            key_modifier: 0,
+            open_set: PriorityQueue::new(),
+            g_score: HashMap::new(),
+            rhs_score: HashMap::new(),
+            seen_changes: None,
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            trail: vec![],
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn source(&self) -> Coords {
+        return if self.moves_agent { self.goal_coords } else { self.start_coords };
+    }
+
+    fn target(&self) -> Coords {
+        return if self.moves_agent { self.current_coords } else { self.goal_coords };
+    }
+
+    fn g(&self, node_coords: Coords) -> i32 {
+        return *self.g_score.get(&node_coords).unwrap_or(&INFINITY);
+    }
+
+    fn rhs(&self, node_coords: Coords) -> i32 {
+        return *self.rhs_score.get(&node_coords).unwrap_or(&INFINITY);
+    }
+
+    fn cost(graph: &Graph::Graph, node_coords: Coords, neighbor_coords: Coords) -> i32 {
+        if !graph.is_valid_node_coordinate(neighbor_coords) || !graph.is_connected(node_coords, neighbor_coords) {
+            return INFINITY;
+        }
+
+        return graph.get_step_cost(node_coords, neighbor_coords);
+    }
+
+    // The cells a cell's rhs is computed from, with the cost of the step between them.
+    fn get_sources(&self, graph: &Graph::Graph, node_coords: Coords) -> Vec<(Coords, i32)> {
+        let mut sources: Vec<(Coords, i32)> = vec![];
+
+        let candidates = if self.moves_agent {
+            graph.get_connections(node_coords)
+        } else {
+            graph.get_predecessors(node_coords)
+        };
+
+        for neighbor_coords in candidates {
+            let cost = if self.moves_agent {
+                IncrementalPlanner::cost(graph, node_coords, neighbor_coords)
+            } else {
+                IncrementalPlanner::cost(graph, neighbor_coords, node_coords)
+            };
+
+            if cost < INFINITY {
+                sources.push((neighbor_coords, cost));
+            }
+        }
+
+        return sources;
+    }
+
+    // The cells whose rhs may change when this cell's g does. Passages can be one-way and
+    // portals join cells that are not neighbours, so these follow the edges rather than adjacency.
+    fn get_dependents(&self, graph: &Graph::Graph, node_coords: Coords) -> Vec<Coords> {
+        if self.moves_agent {
+            return graph.get_predecessors(node_coords);
+        }
+
+        return graph.get_connections(node_coords);
+    }
+
+    fn calculate_key(&self, node_coords: Coords) -> (i32, i32) {
+        let best = i32::min(self.g(node_coords), self.rhs(node_coords));
+
+        // Searching back from the goal needs a bound from the agent to the cell rather than onwards from it.
+        let h = if self.moves_agent {
+            self.metric.get_distance(self.target(), node_coords)
+        } else {
+            self.metric.get_distance(node_coords, self.target())
+        };
+
+        return (i32::min(best + h + self.key_modifier, INFINITY), best);
+    }
+
+    fn update_vertex(&mut self, graph: &Graph::Graph, node_coords: Coords) {
+        if node_coords != self.source() {
+            let mut rhs = INFINITY;
+
+            for (neighbor_coords, cost) in self.get_sources(graph, node_coords) {
+                rhs = i32::min(rhs, i32::min(self.g(neighbor_coords) + cost, INFINITY));
+            }
+
+            self.rhs_score.insert(node_coords, rhs);
+        }
+
+        if self.g(node_coords) != self.rhs(node_coords) {
+            let key = self.calculate_key(node_coords);
+
+            self.open_set.push(node_coords, Reverse(key));
+
+            self.stats.record_generated(1);
+            self.stats.record_frontier(self.open_set.len());
+        } else {
+            self.open_set.remove(&node_coords);
+        }
+    }
+
+    fn is_consistent(&self) -> bool {
+        let target = self.target();
+
+        let opt_top = self.open_set.peek();
+
+        let is_settled = match opt_top {
+            None => true,
+            Some((_, Reverse(key))) => *key >= self.calculate_key(target),
+        };
+
+        return is_settled && self.rhs(target) == self.g(target);
+    }
+
+    fn expand(&mut self, graph: &Graph::Graph) {
+        let (node_coords, Reverse(old_key)) = self.open_set.pop().unwrap();
+
+        if !self.moves_agent {
+            self.current_coords = node_coords;
+        }
+
+        let new_key = self.calculate_key(node_coords);
+
+        if old_key < new_key {
+            self.open_set.push(node_coords, Reverse(new_key));
+
+            return;
+        }
+
+        self.stats.record_expansion();
+
+        if self.g(node_coords) > self.rhs(node_coords) {
+            self.g_score.insert(node_coords, self.rhs(node_coords));
+        } else {
+            self.g_score.insert(node_coords, INFINITY);
+
+            self.update_vertex(graph, node_coords);
+        }
+
+        for dependent_coords in self.get_dependents(graph, node_coords) {
+            self.update_vertex(graph, dependent_coords);
+        }
+    }
+
+    // Starts the search over from the source, keeping the agent where it stands.
+    fn reset_search(&mut self) {
+        self.last_coords = self.current_coords;
+        self.key_modifier = 0;
+        self.open_set = PriorityQueue::new();
+        self.g_score = HashMap::new();
+        self.rhs_score = HashMap::new();
+
+        let source = self.source();
+
+        self.rhs_score.insert(source, 0);
+
+        let key = self.calculate_key(source);
+
+        self.open_set.push(source, Reverse(key));
+
+        self.stats.record_generated(1);
+        self.stats.record_frontier(1);
+    }
+
+    fn apply_changes(&mut self, graph: &Graph::Graph) {
+        let seen_changes = match self.seen_changes {
+            None => graph.get_change_count(),
+            Some(seen_changes) => seen_changes,
+        };
+
+        self.seen_changes = Some(graph.get_change_count());
+
+        if seen_changes == graph.get_change_count() { return; }
+
+        let opt_changes = graph.get_changes_since(seen_changes);
+
+        // The graph no longer remembers everything that changed, so there is nothing to repair from.
+        if opt_changes.is_none() {
+            self.reset_search();
+
+            return;
+        }
+
+        if self.moves_agent {
+            self.key_modifier += self.metric.get_distance(self.last_coords, self.current_coords);
+
+            self.last_coords = self.current_coords;
+        }
+
+        for &(node_coords, neighbor_coords) in opt_changes.unwrap() {
+            self.update_vertex(graph, node_coords);
+            self.update_vertex(graph, neighbor_coords);
+        }
+    }
+
+    // Follows the cheapest step out of each cell, from the target back to the source.
+    fn get_plan(&self, graph: &Graph::Graph) -> Vec<Coords> {
+        let mut plan: Vec<Coords> = vec![self.target()];
+
+        let mut node_coords = self.target();
+
+        while node_coords != self.source() && plan.len() <= self.g_score.len() {
+            let mut best: Option<(i32, Coords)> = None;
+
+            for (neighbor_coords, cost) in self.get_sources(graph, node_coords) {
+                let candidate = (self.g(neighbor_coords) + cost, neighbor_coords);
+
+                if best.is_none() || candidate < best.unwrap() {
+                    best = Some(candidate);
+                }
+            }
+
+            match best {
+                None => return vec![],
+                Some((_, next_coords)) => {
+                    node_coords = next_coords;
+
+                    plan.push(node_coords);
+                }
+            }
+        }
+
+        return plan;
+    }
+}
+
+impl Solver for IncrementalPlanner {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.seen_changes = None;
+        self.stats = SearchStats::default();
+        self.trail = vec![start_coords];
+        self.path = vec![];
+        self.is_finished = false;
+
+        self.reset_search();
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        self.apply_changes(graph);
+
+        if !self.is_consistent() {
+            self.expand(graph);
+
+            return;
+        }
+
+        if self.moves_agent && self.current_coords == self.goal_coords {
+            self.path = self.trail.iter().rev().copied().collect();
+            self.is_finished = true;
+
+            return;
+        }
+
+        if self.g(self.target()) >= INFINITY {
+            self.path = vec![];
+            self.is_finished = true;
+
+            return;
+        }
+
+        // Plans run from the target back to the source, which puts the goal first for LPA*.
+        let mut plan = self.get_plan(graph);
+
+        if !self.moves_agent {
+            self.path = plan;
+            self.is_finished = true;
+
+            return;
+        }
+
+        self.current_coords = plan[1];
+        self.trail.push(self.current_coords);
+
+        plan.remove(0);
+        plan.reverse();
+
+        self.path = plan;
+    }
+
+    fn notify_graph_changed(&mut self) {
+        let has_arrived = self.moves_agent && self.current_coords == self.goal_coords;
+
+        if !has_arrived {
+            self.is_finished = false;
+        }
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        let mut nodes: Vec<Coords> = vec![];
+
+        for node in &self.open_set {
+            nodes.push(*node.0);
+        }
+
+        return nodes;
+    }
+
+    fn get_trail(&self) -> Vec<Coords> {
+        if !self.moves_agent { return vec![]; }
+
+        return self.trail.clone();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::IncrementalPlanner;
+    use crate::Pathfinding::Graph::{Graph, MAX_CHANGES};
+    use crate::Pathfinding::astar::Astar;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::Shape;
+
+    fn toggle_random_wall(rng: &mut StdRng, graph: &mut Graph) {
+        let node_coords = testing::random_coords(rng, graph);
+
+        let neighbors = graph.get_wall_neighbors(node_coords);
+
+        let neighbor_coords = neighbors[rng.gen_range(0..neighbors.len())];
+
+        if graph.is_connected(node_coords, neighbor_coords) {
+            graph.remove_connection(node_coords, neighbor_coords);
+            graph.remove_connection(neighbor_coords, node_coords);
+        } else {
+            graph.add_connection(node_coords, neighbor_coords);
+            graph.add_connection(neighbor_coords, node_coords);
+        }
+    }
+
+    #[test]
+    fn starts_over_once_the_change_log_has_moved_on() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let mut graph = testing::random_grid(&mut rng, Shape::Square, 16, 0.7);
+
+            let start_coords = testing::random_coords(&mut rng, &graph);
+            let goal_coords = testing::random_coords(&mut rng, &graph);
+
+            let mut solver = IncrementalPlanner::new(seed % 2 == 1);
+
+            solver.set_metric(graph.get_metric());
+            solver.initialize(start_coords, goal_coords);
+
+            // A few expansions in, before the agent has taken a step.
+            for _ in 0..3 {
+                solver.generate_path(&graph);
+            }
+
+            let change_count = graph.get_change_count();
+
+            while graph.get_change_count() <= change_count + MAX_CHANGES {
+                toggle_random_wall(&mut rng, &mut graph);
+            }
+
+            assert!(graph.get_changes_since(change_count).is_none(), "seed {}", seed);
+
+            solver.notify_graph_changed();
+
+            while !solver.is_finished() {
+                solver.generate_path(&graph);
+            }
+
+            let path = solver.get_path();
+
+            match testing::dijkstra(&graph, start_coords, goal_coords) {
+                None => assert!(path.is_empty(), "seed {}", seed),
+                Some(cost) => assert_eq!(testing::get_path_cost(&graph, &path), cost, "seed {}", seed),
+            }
+        }
+    }
+
+    #[test]
+    fn repaired_plans_match_a_fresh_astar_after_walls_toggle() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let mut maze = testing::generate_maze(16);
+
+            let start_coords = testing::random_coords(&mut rng, &maze.graph);
+            let goal_coords = testing::random_coords(&mut rng, &maze.graph);
+
+            let moves_agent = seed % 2 == 1;
+
+            let mut solver = IncrementalPlanner::new(moves_agent);
+
+            solver.set_metric(maze.graph.get_metric());
+            solver.initialize(start_coords, goal_coords);
+
+            // LPA* finishes its plan before each change, D* Lite is caught partway along its walk.
+            for round in 0..if moves_agent { 1 } else { 4 } {
+                let step_limit = if moves_agent { rng.gen_range(0..200) } else { usize::MAX };
+
+                for _ in 0..step_limit {
+                    if solver.is_finished() { break; }
+
+                    solver.generate_path(&maze.graph);
+                }
+
+                for _ in 0..20 {
+                    maze.toggle_random_wall();
+                }
+
+                solver.notify_graph_changed();
+
+                // LPA* shows the cell it last expanded as its current one, so only D* Lite's agent has moved on.
+                let from_coords = if moves_agent { solver.get_current_coords() } else { start_coords };
+
+                let trail_length = solver.get_trail().len();
+
+                while !solver.is_finished() {
+                    solver.generate_path(&maze.graph);
+                }
+
+                let mut route = solver.get_path();
+
+                // Only what D* Lite walked after the change has to be a shortest route.
+                if moves_agent && !route.is_empty() {
+                    route.truncate(route.len() - (trail_length - 1));
+                }
+
+                let fresh_path = testing::solve(&mut Astar::new(), &maze.graph, from_coords, goal_coords);
+
+                assert_eq!(route.is_empty(), fresh_path.is_empty(), "seed {} round {}", seed, round);
+
+                assert_eq!(testing::get_path_cost(&maze.graph, &route), testing::get_path_cost(&maze.graph, &fresh_path), "seed {} round {}", seed, round);
+            }
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use priority_queue::PriorityQueue;
+
+use super::Graph;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::{CellScores, SearchStats};
+use super::topology::Shape;
+
+const DIRECTIONS: [Coords; 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+pub struct JumpPointSearch {
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    open_set: PriorityQueue<Coords, (Reverse<i32>, i32, i64)>,
+    came_from: HashMap<Coords, Coords>,
+    closed_set: Vec<Coords>,
+    g_score: HashMap<Coords, i32>,
+
+    push_count: i64,
+
+    metric: Metric,
+
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl JumpPointSearch {
+    pub fn new() -> JumpPointSearch {
+        return JumpPointSearch {
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            open_set: PriorityQueue::new(),
+            came_from: HashMap::new(),
+            closed_set: vec![],
+            g_score: HashMap::new(),
+            push_count: 0,
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn is_open(graph: &Graph::Graph, node_coords: Coords, neighbor_coords: Coords) -> bool {
+        return graph.is_valid_node_coordinate(neighbor_coords) && graph.is_connected(node_coords, neighbor_coords);
+    }
+
+    // A side exit at the current cell is forced when the same side cell cannot be
+    // reached by stepping sideways one cell earlier, since otherwise an equally short
+    // path turns before this cell and is covered by an earlier jump.
+    fn has_forced_neighbor(graph: &Graph::Graph, previous_coords: Coords, node_coords: Coords, direction: Coords) -> bool {
+        for side in [(direction.1, direction.0), (-direction.1, -direction.0)] {
+            let side_coords = (node_coords.0 + side.0, node_coords.1 + side.1);
+            let previous_side_coords = (previous_coords.0 + side.0, previous_coords.1 + side.1);
+
+            if !JumpPointSearch::is_open(graph, node_coords, side_coords) { continue; }
+
+            if JumpPointSearch::is_open(graph, previous_coords, previous_side_coords) &&
+                JumpPointSearch::is_open(graph, previous_side_coords, side_coords) { continue; }
+
+            return true;
+        }
+
+        return false;
+    }
+
+    fn jump(&self, graph: &Graph::Graph, from_coords: Coords, direction: Coords) -> Option<Coords> {
+        let mut previous_coords = from_coords;
+
+        loop {
+            let node_coords = (previous_coords.0 + direction.0, previous_coords.1 + direction.1);
+
+            if !JumpPointSearch::is_open(graph, previous_coords, node_coords) { return None::<Coords>; }
+
+            if node_coords == self.goal_coords { return Some(node_coords); }
+
+            // A portal leaves the line, so its entry has to be expanded like any other turn.
+            if graph.iter_connections(node_coords).any(|connection_coords| graph.is_portal(node_coords, connection_coords)) {
+                return Some(node_coords);
+            }
+
+            if JumpPointSearch::has_forced_neighbor(graph, previous_coords, node_coords, direction) { return Some(node_coords); }
+
+            // Horizontal jumps scan vertically at every cell, which is how turns enter the search.
+            if direction.1 == 0 {
+                for vertical in [(0, 1), (0, -1)] {
+                    if self.jump(graph, node_coords, vertical).is_some() { return Some(node_coords); }
+                }
+            }
+
+            previous_coords = node_coords;
+        }
+    }
+
+    fn add_to_open_set(&mut self, node_coords: Coords, g: i32) {
+        let f = g + self.metric.get_distance(node_coords, self.goal_coords);
+
+        self.push_count += 1;
+
+        self.open_set.push(node_coords, (Reverse(f), g, -self.push_count));
+
+        self.stats.record_generated(1);
+        self.stats.record_frontier(self.open_set.len());
+    }
+
+    fn reconstruct_path(&mut self, graph: &Graph::Graph) {
+        let mut node_coords = self.current_coords;
+
+        self.path.push(node_coords);
+
+        while let Some(parent_coords) = self.came_from.get(&node_coords) {
+            let parent_coords = *parent_coords;
+
+            if graph.is_portal(parent_coords, node_coords) {
+                node_coords = parent_coords;
+
+                self.path.push(node_coords);
+
+                continue;
+            }
+
+            let step = ((parent_coords.0 - node_coords.0).signum(), (parent_coords.1 - node_coords.1).signum());
+
+            while node_coords != parent_coords {
+                node_coords = (node_coords.0 + step.0, node_coords.1 + step.1);
+
+                self.path.push(node_coords);
+            }
+        }
+    }
+}
+
+impl Default for JumpPointSearch {
+    fn default() -> JumpPointSearch {
+        return JumpPointSearch::new();
+    }
+}
+
+impl Solver for JumpPointSearch {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.open_set = PriorityQueue::new();
+        self.came_from = HashMap::new();
+        self.closed_set = vec![];
+        self.g_score = HashMap::new();
+        self.push_count = 0;
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+
+        self.g_score.insert(start_coords, 0);
+        self.add_to_open_set(start_coords, 0);
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        let opt_node_coords = self.open_set.pop();
+
+        if opt_node_coords.is_none() {
+            self.is_finished = true;
+
+            return;
+        }
+
+        self.current_coords = opt_node_coords.unwrap().0;
+
+        if self.current_coords == self.goal_coords {
+            self.reconstruct_path(graph);
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        self.stats.record_expansion();
+        self.closed_set.push(self.current_coords);
+
+        let opt_parent_coords = self.came_from.get(&self.current_coords).copied();
+
+        let g = self.g_score[&self.current_coords];
+
+        let mut successors: Vec<(Coords, i32)> = vec![];
+
+        // Jumps only run along the rows and columns of a plain square grid, so on any other shape they
+        // would miss shorter routes. There every connection is a successor, as in plain A*.
+        let can_jump = graph.get_topology().get_shape() == Shape::Square;
+
+        if can_jump {
+            for direction in DIRECTIONS {
+                // Jumping back towards the parent only revisits cells the parent's jump already passed.
+                // A cell reached through a portal has no such jump behind it.
+                if let Some(parent_coords) = opt_parent_coords.filter(|parent_coords| !graph.is_portal(*parent_coords, self.current_coords)) {
+                    let back = ((parent_coords.0 - self.current_coords.0).signum(), (parent_coords.1 - self.current_coords.1).signum());
+
+                    if back == direction { continue; }
+                }
+
+                let opt_jump_coords = self.jump(graph, self.current_coords, direction);
+
+                if opt_jump_coords.is_none() { continue; }
+
+                let jump_coords = opt_jump_coords.unwrap();
+
+                successors.push((jump_coords, g + graph.get_topology().get_distance(self.current_coords, jump_coords)));
+            }
+        }
+
+        for connection_coords in graph.iter_connections(self.current_coords) {
+            if !can_jump || graph.is_portal(self.current_coords, connection_coords) {
+                successors.push((connection_coords, g + graph.get_step_cost(self.current_coords, connection_coords)));
+            }
+        }
+
+        for (jump_coords, tentative_g_score) in successors {
+            if tentative_g_score < *self.g_score.get(&jump_coords).unwrap_or(&i32::MAX) {
+                self.came_from.insert(jump_coords, self.current_coords);
+
+                self.g_score.insert(jump_coords, tentative_g_score);
+
+                self.add_to_open_set(jump_coords, tentative_g_score);
+            }
+        }
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        let mut nodes: Vec<Coords> = vec![];
+
+        for node in &self.open_set {
+            nodes.push(*node.0);
+        }
+
+        return nodes;
+    }
+
+    fn get_closed_set(&self) -> Vec<Coords> {
+        return self.closed_set.clone();
+    }
+
+    fn get_came_from(&self) -> Vec<(Coords, Coords)> {
+        return self.came_from.iter().map(|(node_coords, parent_coords)| (*node_coords, *parent_coords)).collect();
+    }
+
+    fn get_cell_scores(&self, coords: Coords) -> Option<CellScores> {
+        let h = self.metric.get_distance(coords, self.goal_coords);
+
+        return Some(CellScores { g: self.g_score.get(&coords).copied(), h, parent: self.came_from.get(&coords).copied() });
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::JumpPointSearch;
+    use crate::Pathfinding::astar::Astar;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape};
+
+    #[test]
+    fn matches_astar_path_length_on_random_grids() {
+        // Jumps only apply to the plain square grid, and the other shapes have to fall back without losing anything.
+        let shapes = [Shape::Square, Shape::Diagonal(CornerCutting::Allowed), Shape::Diagonal(CornerCutting::Disallowed), Shape::Brick];
+
+        for seed in 0..160 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let shape = shapes[seed as usize % 4];
+            let open_chance = [0.5, 0.7, 0.9, 1.0][seed as usize / 4 % 4];
+
+            let size = rng.gen_range(2..25);
+
+            let graph = testing::random_grid(&mut rng, shape, size, open_chance);
+
+            let start_coords = (rng.gen_range(0..size), rng.gen_range(0..size));
+            let goal_coords = (rng.gen_range(0..size), rng.gen_range(0..size));
+
+            let astar_path = testing::solve(&mut Astar::new(), &graph, start_coords, goal_coords);
+            let jps_path = testing::solve(&mut JumpPointSearch::new(), &graph, start_coords, goal_coords);
+
+            assert_eq!(jps_path.len(), astar_path.len(), "seed {} on {:?}: {:?} -> {:?}", seed, shape, start_coords, goal_coords);
+
+            assert_eq!(testing::get_path_cost(&graph, &jps_path), testing::get_path_cost(&graph, &astar_path), "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use priority_queue::PriorityQueue;
+
+use super::Graph;
+use super::Graph::Inventory;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+// Where the search stands: a cell and the keys picked up on the way to it.
+type State = (Coords, Inventory);
+
+// A* over cells paired with the keys held, so a route can double back to fetch a key
+// and reach the same cell again once it can open more doors.
+pub struct KeySearch {
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    open_set: PriorityQueue<State, (Reverse<i32>, i32, i64)>,
+    came_from: HashMap<State, State>,
+    closed_set: Vec<Coords>,
+    g_score: HashMap<State, i32>,
+
+    push_count: i64,
+
+    metric: Metric,
+
+    stats: SearchStats,
+
+    collected_keys: Vec<Coords>,
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl KeySearch {
+    pub fn new() -> KeySearch {
+        return KeySearch {
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            open_set: PriorityQueue::new(),
+            came_from: HashMap::new(),
+            closed_set: vec![],
+            g_score: HashMap::new(),
+            push_count: 0,
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            collected_keys: vec![],
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn pick_up(graph: &Graph::Graph, coords: Coords, inventory: Inventory) -> Inventory {
+        match graph.get_key(coords) {
+            None => return inventory,
+            Some(key) => return inventory | (1 << key),
+        }
+    }
+
+    fn add_to_open_set(&mut self, state: State, g: i32) {
+        let f = g + self.metric.get_distance(state.0, self.goal_coords);
+
+        self.push_count += 1;
+
+        self.open_set.push(state, (Reverse(f), g, -self.push_count));
+
+        self.stats.record_generated(1);
+        self.stats.record_frontier(self.open_set.len());
+    }
+
+    fn reconstruct_path(&mut self, goal_state: State) {
+        let mut state = goal_state;
+
+        self.path.push(state.0);
+
+        while let Some(parent_state) = self.came_from.get(&state) {
+            if parent_state.1 != state.1 {
+                self.collected_keys.push(state.0);
+            }
+
+            state = *parent_state;
+
+            self.path.push(state.0);
+        }
+
+        // A key lying on the start is picked up before the first step.
+        if state.1 != 0 {
+            self.collected_keys.push(state.0);
+        }
+
+        self.collected_keys.reverse();
+    }
+}
+
+impl Default for KeySearch {
+    fn default() -> KeySearch {
+        return KeySearch::new();
+    }
+}
+
+impl Solver for KeySearch {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.open_set = PriorityQueue::new();
+        self.came_from = HashMap::new();
+        self.closed_set = vec![];
+        self.g_score = HashMap::new();
+        self.push_count = 0;
+        self.stats = SearchStats::default();
+        self.collected_keys = vec![];
+        self.path = vec![];
+        self.is_finished = false;
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        // Keys are only known once the graph is, so the start state is set up on the first call.
+        if self.g_score.is_empty() {
+            let start_state = (self.start_coords, KeySearch::pick_up(graph, self.start_coords, 0));
+
+            self.g_score.insert(start_state, 0);
+            self.add_to_open_set(start_state, 0);
+        }
+
+        let opt_state = self.open_set.pop();
+
+        if opt_state.is_none() {
+            self.is_finished = true;
+
+            return;
+        }
+
+        let (node_coords, inventory) = opt_state.unwrap().0;
+
+        self.current_coords = node_coords;
+
+        if node_coords == self.goal_coords {
+            self.reconstruct_path((node_coords, inventory));
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        self.stats.record_expansion();
+        self.closed_set.push(node_coords);
+
+        let g = self.g_score[&(node_coords, inventory)];
+
+        for connection_coords in graph.get_connections(node_coords) {
+            if !graph.can_pass(node_coords, connection_coords, inventory) { continue; }
+
+            let connection_state = (connection_coords, KeySearch::pick_up(graph, connection_coords, inventory));
+
+            let tentative_g_score = g + graph.get_step_cost(node_coords, connection_coords);
+
+            if tentative_g_score >= *self.g_score.get(&connection_state).unwrap_or(&i32::MAX) { continue; }
+
+            self.came_from.insert(connection_state, (node_coords, inventory));
+            self.g_score.insert(connection_state, tentative_g_score);
+
+            self.add_to_open_set(connection_state, tentative_g_score);
+        }
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        let mut nodes: Vec<Coords> = vec![];
+
+        for (state, _) in &self.open_set {
+            nodes.push(state.0);
+        }
+
+        return nodes;
+    }
+
+    fn get_closed_set(&self) -> Vec<Coords> {
+        return self.closed_set.clone();
+    }
+
+    fn get_came_from(&self) -> Vec<(Coords, Coords)> {
+        return self.came_from.iter()
+            .filter(|(state, parent_state)| state.0 != parent_state.0)
+            .map(|(state, parent_state)| (state.0, parent_state.0))
+            .collect();
+    }
+
+    fn get_collected_keys(&self) -> Vec<Coords> {
+        return self.collected_keys.clone();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::KeySearch;
+    use crate::Maze;
+    use crate::Pathfinding::solver::Solver;
+
+    #[test]
+    fn every_goal_stays_reachable_with_many_keys() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let size = rng.gen_range(8..20);
+
+            let mut maze = Maze::Maze::new(size);
+
+            while !maze.is_finished() {
+                maze.generate_maze();
+            }
+
+            for _ in 0..rng.gen_range(0..4) {
+                maze.add_random_portal();
+            }
+
+            let origin_coords = (rng.gen_range(0..size), rng.gen_range(0..size));
+
+            maze.place_keys(origin_coords, 12);
+
+            assert!(!maze.graph.get_doors().is_empty(), "seed {}: no doors placed", seed);
+
+            for _ in 0..10 {
+                let goal_coords = (rng.gen_range(0..size), rng.gen_range(0..size));
+
+                let mut solver = KeySearch::new();
+
+                solver.set_metric(maze.graph.get_metric());
+                solver.initialize(origin_coords, goal_coords);
+
+                while !solver.is_finished() {
+                    solver.generate_path(&maze.graph);
+                }
+
+                let path = solver.get_path();
+
+                assert_eq!(path.first(), Some(&goal_coords), "seed {}: {:?} to {:?} unreachable", seed, origin_coords, goal_coords);
+                assert_eq!(path.last(), Some(&origin_coords), "seed {}", seed);
+            }
+        }
+    }
+}
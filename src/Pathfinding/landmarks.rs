@@ -0,0 +1,248 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use priority_queue::PriorityQueue;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use super::Graph;
+use super::Node::Coords;
+
+pub const DEFAULT_LANDMARK_COUNT: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LandmarkSelection {
+    FarthestPoint,
+    Random,
+    Corner,
+}
+
+// Exact distances from a few chosen cells, which bound the distance between any two
+// other cells through the triangle inequality.
+pub struct Landmarks {
+    change_count: usize,
+    cell_count: usize,
+
+    distances: Vec<HashMap<Coords, i32>>,
+
+    // Distances to each landmark rather than from it, which differ once passages are one-way.
+    reverse_distances: Vec<HashMap<Coords, i32>>,
+}
+
+impl Landmarks {
+    // The seed picks the random cells Random and FarthestPoint start from, so the same seed on the same
+    // graph always gives the same landmarks.
+    pub fn new(graph: &Graph::Graph, selection: LandmarkSelection, count: usize, seed: u64) -> Landmarks {
+        let mut landmarks: Vec<Coords> = vec![];
+        let mut distances: Vec<HashMap<Coords, i32>> = vec![];
+
+        let cell_count = graph.get_topology().get_cell_count();
+
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        match selection {
+            LandmarkSelection::Corner => {
+                // The cells farthest out along either diagonal, which on a square grid are its corners.
+                let extremes: [fn(&Coords) -> i32; 4] = [
+                    |coords| -coords.0 - coords.1,
+                    |coords| coords.0 + coords.1,
+                    |coords| coords.0 - coords.1,
+                    |coords| coords.1 - coords.0,
+                ];
+
+                landmarks = extremes.iter().map(|extreme| graph.iter_nodes().max_by_key(extreme).unwrap()).collect();
+
+                landmarks.truncate(count);
+                landmarks.dedup();
+            },
+            LandmarkSelection::Random => {
+                for _ in 0..count {
+                    landmarks.push(graph.get_topology().get_coords(rng.gen_range(0..cell_count)));
+                }
+            },
+            LandmarkSelection::FarthestPoint => {
+                // Starts from whatever lies farthest from a random cell, then keeps adding the cell
+                // farthest from every landmark chosen so far.
+                let seed_distances = Landmarks::measure_distances(graph, graph.get_topology().get_coords(rng.gen_range(0..cell_count)), false);
+
+                let mut candidate = Landmarks::get_farthest(&seed_distances);
+
+                while landmarks.len() < count && !landmarks.contains(&candidate) {
+                    landmarks.push(candidate);
+                    distances.push(Landmarks::measure_distances(graph, candidate, false));
+
+                    let mut nearest: HashMap<Coords, i32> = HashMap::new();
+
+                    for table in &distances {
+                        for (node_coords, distance) in table {
+                            let entry = nearest.entry(*node_coords).or_insert(i32::MAX);
+
+                            *entry = i32::min(*entry, *distance);
+                        }
+                    }
+
+                    candidate = Landmarks::get_farthest(&nearest);
+                }
+            },
+        }
+
+        if distances.len() != landmarks.len() {
+            distances = landmarks.iter().map(|landmark| Landmarks::measure_distances(graph, *landmark, false)).collect();
+        }
+
+        let reverse_distances = landmarks.iter().map(|landmark| Landmarks::measure_distances(graph, *landmark, true)).collect();
+
+        return Landmarks { change_count: graph.get_change_count(), cell_count, distances, reverse_distances };
+    }
+
+    // Dijkstra out of a cell, or into it when reversed, so step costs and portals are counted exactly.
+    fn measure_distances(graph: &Graph::Graph, origin_coords: Coords, is_reversed: bool) -> HashMap<Coords, i32> {
+        let mut distances: HashMap<Coords, i32> = HashMap::from([(origin_coords, 0)]);
+        let mut open_set: PriorityQueue<Coords, Reverse<i32>> = PriorityQueue::new();
+
+        open_set.push(origin_coords, Reverse(0));
+
+        while let Some((node_coords, Reverse(distance))) = open_set.pop() {
+            let candidates = if is_reversed {
+                graph.get_predecessors(node_coords)
+            } else {
+                graph.get_connections(node_coords)
+            };
+
+            for candidate_coords in candidates {
+                let step_cost = if is_reversed {
+                    graph.get_step_cost(candidate_coords, node_coords)
+                } else {
+                    graph.get_step_cost(node_coords, candidate_coords)
+                };
+
+                let candidate_distance = distance + step_cost;
+
+                if candidate_distance >= *distances.get(&candidate_coords).unwrap_or(&i32::MAX) { continue; }
+
+                distances.insert(candidate_coords, candidate_distance);
+
+                open_set.push(candidate_coords, Reverse(candidate_distance));
+            }
+        }
+
+        return distances;
+    }
+
+    fn get_farthest(distances: &HashMap<Coords, i32>) -> Coords {
+        let mut farthest: Vec<(i32, Coords)> = distances.iter().map(|(node_coords, distance)| (*distance, *node_coords)).collect();
+
+        farthest.sort();
+
+        return farthest.last().unwrap().1;
+    }
+
+    // Lower bound on the distance from one cell to another, taken from whichever landmark separates them most.
+    // From a landmark L, d(L, goal) <= d(L, node) + d(node, goal); into it, d(node, L) <= d(node, goal) + d(goal, L).
+    pub fn estimate(&self, node_coords: Coords, goal_coords: Coords) -> i32 {
+        let mut estimate = 0;
+
+        for table in &self.distances {
+            let (opt_node, opt_goal) = (table.get(&node_coords), table.get(&goal_coords));
+
+            if opt_node.is_none() || opt_goal.is_none() { continue; }
+
+            estimate = i32::max(estimate, opt_goal.unwrap() - opt_node.unwrap());
+        }
+
+        for table in &self.reverse_distances {
+            let (opt_node, opt_goal) = (table.get(&node_coords), table.get(&goal_coords));
+
+            if opt_node.is_none() || opt_goal.is_none() { continue; }
+
+            estimate = i32::max(estimate, opt_node.unwrap() - opt_goal.unwrap());
+        }
+
+        return estimate;
+    }
+
+    pub fn is_stale(&self, graph: &Graph::Graph) -> bool {
+        return self.change_count != graph.get_change_count() || self.cell_count != graph.get_topology().get_cell_count();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::{LandmarkSelection, Landmarks};
+    use crate::Pathfinding::Node::Coords;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::Shape;
+
+    #[test]
+    fn reverse_tables_hold_distances_into_each_landmark_through_one_way_passages() {
+        let selections = [LandmarkSelection::FarthestPoint, LandmarkSelection::Random, LandmarkSelection::Corner];
+
+        for seed in 0..30 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let size = rng.gen_range(3..12);
+
+            let mut graph = testing::random_grid(&mut rng, Shape::Square, size, 0.8);
+
+            for _ in 0..size * 2 {
+                let node_coords = testing::random_coords(&mut rng, &graph);
+
+                let neighbors: Vec<_> = graph.iter_connections(node_coords).collect();
+
+                if neighbors.is_empty() { continue; }
+
+                graph.set_one_way(node_coords, neighbors[rng.gen_range(0..neighbors.len())]);
+            }
+
+            graph.add_portal(testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+            let landmarks = Landmarks::new(&graph, selections[seed as usize % 3], 4, seed);
+
+            for (table, reverse_table) in landmarks.distances.iter().zip(&landmarks.reverse_distances) {
+                let landmark_coords = *table.iter().find(|(_, distance)| **distance == 0).unwrap().0;
+
+                for node_coords in graph.iter_nodes() {
+                    assert_eq!(reverse_table.get(&node_coords).copied(), testing::dijkstra(&graph, node_coords, landmark_coords), "seed {}: {:?} into {:?}", seed, node_coords, landmark_coords);
+                    assert_eq!(table.get(&node_coords).copied(), testing::dijkstra(&graph, landmark_coords, node_coords), "seed {}: {:?} out to {:?}", seed, landmark_coords, node_coords);
+                }
+            }
+
+            for _ in 0..40 {
+                let (node_coords, goal_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+                let opt_cost = testing::dijkstra(&graph, node_coords, goal_coords);
+
+                if opt_cost.is_none() { continue; }
+
+                assert!(landmarks.estimate(node_coords, goal_coords) <= opt_cost.unwrap(), "seed {}: {:?} -> {:?} overestimated", seed, node_coords, goal_coords);
+            }
+        }
+    }
+
+    // Each landmark is the one cell its own table puts no distance from.
+    fn get_landmarks(landmarks: &Landmarks) -> Vec<Coords> {
+        return landmarks.distances.iter().map(|table| *table.iter().find(|(_, distance)| **distance == 0).unwrap().0).collect();
+    }
+
+    #[test]
+    fn the_same_seed_picks_the_same_landmarks() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let graph = testing::random_grid(&mut rng, Shape::Square, 16, 0.8);
+
+        for selection in [LandmarkSelection::FarthestPoint, LandmarkSelection::Random, LandmarkSelection::Corner] {
+            let picks: Vec<Vec<Coords>> = (0..10).map(|seed| get_landmarks(&Landmarks::new(&graph, selection, 4, seed))).collect();
+
+            for seed in 0..10 {
+                assert_eq!(get_landmarks(&Landmarks::new(&graph, selection, 4, seed)), picks[seed as usize], "seed {} with {:?}", seed, selection);
+            }
+
+            // Only the corners take no notice of the seed.
+            let is_seeded = picks.iter().any(|pick| *pick != picks[0]);
+
+            assert_eq!(is_seeded, selection != LandmarkSelection::Corner, "{:?}", selection);
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+use priority_queue::PriorityQueue;
+
+use super::Graph;
+use super::Node::Coords;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+// Conflict trees can blow up on crowded mazes, so the search gives up after this many nodes.
+const MAX_CONSTRAINT_NODES: usize = 5000;
+
+// A cell at a timestep, ordered by f and then by the later time.
+type TimedPriority = Reverse<(usize, Reverse<usize>)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Constraint {
+    Vertex(usize, Coords, usize),
+    Edge(usize, Coords, Coords, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conflict {
+    Vertex(usize, usize, Coords, usize),
+    Swap(usize, usize, Coords, Coords, usize),
+}
+
+struct ConstraintNode {
+    constraints: Vec<Constraint>,
+    routes: Vec<Vec<Coords>>,
+    resolved: Vec<Coords>,
+}
+
+pub struct ConflictBasedSearch {
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    extra_agents: Vec<(Coords, Coords)>,
+    agents: Vec<(Coords, Coords)>,
+    distances: Vec<HashMap<Coords, i32>>,
+
+    nodes: Vec<ConstraintNode>,
+    open_set: PriorityQueue<usize, Reverse<(usize, usize)>>,
+
+    routes: Vec<Vec<Coords>>,
+    resolved: Vec<Coords>,
+    time: usize,
+    is_solved: bool,
+
+    // Counts constraint tree nodes rather than cells, since that is where the search branches.
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl ConflictBasedSearch {
+    pub fn new() -> ConflictBasedSearch {
+        return ConflictBasedSearch {
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            extra_agents: vec![],
+            agents: vec![],
+            distances: vec![],
+            nodes: vec![],
+            open_set: PriorityQueue::new(),
+            routes: vec![],
+            resolved: vec![],
+            time: 0,
+            is_solved: false,
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn get_position(route: &[Coords], time: usize) -> Coords {
+        return route[usize::min(time, route.len() - 1)];
+    }
+
+    fn get_cost(routes: &[Vec<Coords>]) -> usize {
+        return routes.iter().map(|route| route.len()).sum();
+    }
+
+    // True distances back from the goal make an exact heuristic for the time-expanded search.
+    fn measure_distances(graph: &Graph::Graph, goal_coords: Coords) -> HashMap<Coords, i32> {
+        let mut distances: HashMap<Coords, i32> = HashMap::from([(goal_coords, 0)]);
+        let mut queue: VecDeque<Coords> = VecDeque::from([goal_coords]);
+
+        while let Some(node_coords) = queue.pop_front() {
+            for neighbor_coords in graph.iter_predecessors(node_coords) {
+                if distances.contains_key(&neighbor_coords) { continue; }
+
+                distances.insert(neighbor_coords, distances[&node_coords] + 1);
+
+                queue.push_back(neighbor_coords);
+            }
+        }
+
+        return distances;
+    }
+
+    // A* over (cell, time) where waiting in place is also a move.
+    fn plan_route(&self, graph: &Graph::Graph, agent: usize, constraints: &[Constraint]) -> Option<Vec<Coords>> {
+        let (start_coords, goal_coords) = self.agents[agent];
+
+        let distances = &self.distances[agent];
+
+        if !distances.contains_key(&start_coords) { return None; }
+
+        let mut blocked_cells: HashSet<(Coords, usize)> = HashSet::new();
+        let mut blocked_moves: HashSet<(Coords, Coords, usize)> = HashSet::new();
+
+        let mut last_goal_time: Option<usize> = None;
+        let mut last_time = 0;
+
+        for constraint in constraints {
+            match *constraint {
+                Constraint::Vertex(owner, coords, time) if owner == agent => {
+                    blocked_cells.insert((coords, time));
+
+                    last_time = usize::max(last_time, time);
+
+                    if coords == goal_coords {
+                        last_goal_time = Some(usize::max(last_goal_time.unwrap_or(0), time));
+                    }
+                },
+                Constraint::Edge(owner, from_coords, to_coords, time) if owner == agent => {
+                    blocked_moves.insert((from_coords, to_coords, time));
+
+                    last_time = usize::max(last_time, time);
+                },
+                _ => {}
+            }
+        }
+
+        let max_time = last_time + graph.get_nodes().len();
+
+        let mut open_set: PriorityQueue<(Coords, usize), TimedPriority> = PriorityQueue::new();
+        let mut came_from: HashMap<(Coords, usize), (Coords, usize)> = HashMap::new();
+        let mut seen: HashSet<(Coords, usize)> = HashSet::new();
+
+        if blocked_cells.contains(&(start_coords, 0)) { return None; }
+
+        open_set.push((start_coords, 0), Reverse((distances[&start_coords] as usize, Reverse(0))));
+        seen.insert((start_coords, 0));
+
+        while let Some(((node_coords, time), _)) = open_set.pop() {
+            let can_stay = last_goal_time.is_none() || time > last_goal_time.unwrap();
+
+            if node_coords == goal_coords && can_stay {
+                let mut route = vec![node_coords];
+
+                let mut state = (node_coords, time);
+
+                while let Some(previous_state) = came_from.get(&state) {
+                    state = *previous_state;
+
+                    route.push(state.0);
+                }
+
+                route.reverse();
+
+                return Some(route);
+            }
+
+            if time >= max_time { continue; }
+
+            let mut moves = graph.get_connections(node_coords);
+
+            moves.sort();
+            moves.push(node_coords);
+
+            for next_coords in moves {
+                let next_state = (next_coords, time + 1);
+
+                if seen.contains(&next_state) || blocked_cells.contains(&next_state) { continue; }
+
+                if blocked_moves.contains(&(node_coords, next_coords, time)) { continue; }
+
+                let opt_distance = distances.get(&next_coords);
+
+                if opt_distance.is_none() { continue; }
+
+                seen.insert(next_state);
+                came_from.insert(next_state, (node_coords, time));
+
+                open_set.push(next_state, Reverse((time + 1 + *opt_distance.unwrap() as usize, Reverse(time + 1))));
+            }
+        }
+
+        return None;
+    }
+
+    fn find_conflict(routes: &[Vec<Coords>]) -> Option<Conflict> {
+        let horizon = routes.iter().map(|route| route.len()).max().unwrap_or(0);
+
+        for time in 0..horizon {
+            for first in 0..routes.len() {
+                for second in first + 1..routes.len() {
+                    let first_coords = ConflictBasedSearch::get_position(&routes[first], time);
+                    let second_coords = ConflictBasedSearch::get_position(&routes[second], time);
+
+                    if first_coords == second_coords {
+                        return Some(Conflict::Vertex(first, second, first_coords, time));
+                    }
+
+                    let first_next = ConflictBasedSearch::get_position(&routes[first], time + 1);
+                    let second_next = ConflictBasedSearch::get_position(&routes[second], time + 1);
+
+                    if first_coords == second_next && second_coords == first_next {
+                        return Some(Conflict::Swap(first, second, first_coords, second_coords, time));
+                    }
+                }
+            }
+        }
+
+        return None;
+    }
+
+    fn add_node(&mut self, constraints: Vec<Constraint>, routes: Vec<Vec<Coords>>, resolved: Vec<Coords>) {
+        let id = self.nodes.len();
+
+        let cost = ConflictBasedSearch::get_cost(&routes);
+
+        self.nodes.push(ConstraintNode { constraints, routes, resolved });
+
+        self.open_set.push(id, Reverse((cost, id)));
+
+        self.stats.record_generated(1);
+        self.stats.record_frontier(self.open_set.len());
+    }
+
+    fn prepare(&mut self, graph: &Graph::Graph) {
+        self.distances = self.agents.iter().map(|agent| ConflictBasedSearch::measure_distances(graph, agent.1)).collect();
+
+        let mut routes: Vec<Vec<Coords>> = vec![];
+
+        for agent in 0..self.agents.len() {
+            match self.plan_route(graph, agent, &[]) {
+                None => return,
+                Some(route) => routes.push(route),
+            }
+        }
+
+        self.add_node(vec![], routes, vec![]);
+    }
+
+    fn branch(&mut self, graph: &Graph::Graph, id: usize, conflict: Conflict) {
+        let (constraints, cell_coords) = match conflict {
+            Conflict::Vertex(first, second, coords, time) => (
+                [Constraint::Vertex(first, coords, time), Constraint::Vertex(second, coords, time)],
+                coords
+            ),
+            Conflict::Swap(first, second, first_coords, second_coords, time) => (
+                [Constraint::Edge(first, first_coords, second_coords, time), Constraint::Edge(second, second_coords, first_coords, time)],
+                first_coords
+            ),
+        };
+
+        for constraint in constraints {
+            let agent = match constraint {
+                Constraint::Vertex(agent, _, _) => agent,
+                Constraint::Edge(agent, _, _, _) => agent,
+            };
+
+            let mut child_constraints = self.nodes[id].constraints.clone();
+
+            child_constraints.push(constraint);
+
+            let opt_route = self.plan_route(graph, agent, &child_constraints);
+
+            if opt_route.is_none() { continue; }
+
+            let mut routes = self.nodes[id].routes.clone();
+
+            routes[agent] = opt_route.unwrap();
+
+            let mut resolved = self.nodes[id].resolved.clone();
+
+            resolved.push(cell_coords);
+
+            self.add_node(child_constraints, routes, resolved);
+        }
+    }
+}
+
+impl Default for ConflictBasedSearch {
+    fn default() -> ConflictBasedSearch {
+        return ConflictBasedSearch::new();
+    }
+}
+
+impl Solver for ConflictBasedSearch {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+
+        // Agents sharing a start or a goal could never be separated, so later ones are dropped.
+        self.agents = vec![(start_coords, goal_coords)];
+
+        for (agent_start, agent_goal) in &self.extra_agents {
+            if self.agents.iter().any(|agent| agent.0 == *agent_start || agent.1 == *agent_goal) { continue; }
+
+            self.agents.push((*agent_start, *agent_goal));
+        }
+
+        self.distances = vec![];
+        self.nodes = vec![];
+        self.open_set = PriorityQueue::new();
+        self.routes = vec![];
+        self.resolved = vec![];
+        self.time = 0;
+        self.is_solved = false;
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        // Once solved, each step plays every agent one timestep further along its route.
+        if self.is_solved {
+            self.time += 1;
+
+            self.current_coords = ConflictBasedSearch::get_position(&self.routes[0], self.time);
+
+            let horizon = self.routes.iter().map(|route| route.len()).max().unwrap_or(0);
+
+            if self.time + 1 >= horizon {
+                self.is_finished = true;
+            }
+
+            return;
+        }
+
+        if self.nodes.is_empty() {
+            self.prepare(graph);
+
+            if self.nodes.is_empty() {
+                self.is_finished = true;
+            }
+
+            return;
+        }
+
+        let opt_id = self.open_set.pop();
+
+        if opt_id.is_none() || self.nodes.len() > MAX_CONSTRAINT_NODES {
+            self.is_finished = true;
+
+            return;
+        }
+
+        let id = opt_id.unwrap().0;
+
+        self.stats.record_expansion();
+
+        self.routes = self.nodes[id].routes.clone();
+
+        match ConflictBasedSearch::find_conflict(&self.routes) {
+            None => {
+                self.resolved = self.nodes[id].resolved.clone();
+                self.path = self.routes[0].iter().rev().copied().collect();
+                self.is_solved = true;
+            },
+            Some(conflict) => self.branch(graph, id, conflict),
+        }
+    }
+
+    fn set_agents(&mut self, agents: Vec<(Coords, Coords)>) {
+        self.extra_agents = agents;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    fn get_agents(&self) -> Vec<Coords> {
+        return self.routes.iter().map(|route| ConflictBasedSearch::get_position(route, self.time)).collect();
+    }
+
+    fn get_conflicts(&self) -> Vec<Coords> {
+        return self.resolved.clone();
+    }
+
+    // Every agent's goal, numbered by agent.
+    fn get_waypoints(&self) -> Vec<Coords> {
+        return self.agents.iter().map(|agent| agent.1).collect();
+    }
+
+    fn get_legs(&self) -> Vec<Vec<Coords>> {
+        if !self.is_solved { return vec![]; }
+
+        return self.routes.clone();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::ConflictBasedSearch;
+    use crate::Pathfinding::Graph::Graph;
+    use crate::Pathfinding::Node::Coords;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::Shape;
+
+    fn get_position(route: &[Coords], time: usize) -> Coords {
+        return route[usize::min(time, route.len() - 1)];
+    }
+
+    // Plans until the routes are settled, which is before the agents start walking them.
+    fn solve(graph: &Graph, agents: &[(Coords, Coords)]) -> (ConflictBasedSearch, Vec<Vec<Coords>>) {
+        let mut solver = ConflictBasedSearch::new();
+
+        solver.set_agents(agents[1..].to_vec());
+        solver.initialize(agents[0].0, agents[0].1);
+
+        while !solver.is_finished() && !solver.is_solved {
+            solver.generate_path(graph);
+        }
+
+        let routes = solver.get_legs();
+
+        return (solver, routes);
+    }
+
+    fn assert_conflict_free(graph: &Graph, agents: &[(Coords, Coords)], routes: &[Vec<Coords>], label: &str) {
+        assert_eq!(routes.len(), agents.len(), "{}", label);
+
+        for (route, (start_coords, goal_coords)) in routes.iter().zip(agents) {
+            assert_eq!((route[0], route[route.len() - 1]), (*start_coords, *goal_coords), "{}", label);
+
+            for step in route.windows(2) {
+                assert!(step[0] == step[1] || graph.is_connected(step[0], step[1]), "{}: {:?} is not a move", label, step);
+            }
+        }
+
+        let horizon = routes.iter().map(|route| route.len()).max().unwrap();
+
+        for time in 0..horizon {
+            for first in 0..routes.len() {
+                for second in first + 1..routes.len() {
+                    let (first_coords, second_coords) = (get_position(&routes[first], time), get_position(&routes[second], time));
+
+                    assert_ne!(first_coords, second_coords, "{}: agents {} and {} meet at {}", label, first, second, time);
+
+                    let (first_next, second_next) = (get_position(&routes[first], time + 1), get_position(&routes[second], time + 1));
+
+                    assert!(first_coords != second_next || second_coords != first_next, "{}: agents {} and {} swap at {}", label, first, second, time);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn agents_pass_each_other_through_a_side_pocket() {
+        let mut graph = Graph::new(3);
+
+        for (node_coords, neighbor_coords) in [((0, 0), (1, 0)), ((1, 0), (2, 0)), ((1, 0), (1, 1))] {
+            graph.add_connection(node_coords, neighbor_coords);
+            graph.add_connection(neighbor_coords, node_coords);
+        }
+
+        let agents = [((0, 0), (2, 0)), ((2, 0), (0, 0))];
+
+        let (solver, routes) = solve(&graph, &agents);
+
+        assert!(solver.is_solved);
+        assert!(!solver.get_conflicts().is_empty());
+
+        assert_conflict_free(&graph, &agents, &routes, "pocket");
+    }
+
+    #[test]
+    fn routes_have_no_vertex_or_swap_conflicts() {
+        let mut solved_count = 0;
+
+        for seed in 0..40 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let size = rng.gen_range(3..9);
+
+            let graph = testing::random_grid(&mut rng, Shape::Square, size, [0.8, 1.0][seed as usize % 2]);
+
+            let agent_count = rng.gen_range(2..6);
+
+            let mut agents: Vec<(Coords, Coords)> = vec![];
+
+            while agents.len() < agent_count {
+                let agent = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+                if agents.iter().any(|other| other.0 == agent.0 || other.1 == agent.1) { continue; }
+
+                agents.push(agent);
+            }
+
+            let (solver, routes) = solve(&graph, &agents);
+
+            if !solver.is_solved { continue; }
+
+            solved_count += 1;
+
+            assert_conflict_free(&graph, &agents, &routes, &format!("seed {}", seed));
+        }
+
+        assert!(solved_count >= 20, "only {} solved", solved_count);
+    }
+}
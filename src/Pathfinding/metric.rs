@@ -0,0 +1,132 @@
+use super::Node::Coords;
+use super::topology::{SquareTopology, Topology};
+
+// What a solver needs to know about costs: the cost of a single step and a lower bound
+// on the cost between any two cells. Portals can make far-apart cells close, so the bound
+// is the cheapest route through the topology's distance and any chain of portals.
+#[derive(Debug)]
+pub struct Metric {
+    topology: Box<dyn Topology>,
+    portals: Vec<(Coords, Coords)>,
+
+    // Lower bound on the cost from each portal's exit to each portal's entry.
+    between_portals: Vec<Vec<i32>>,
+}
+
+impl Metric {
+    pub fn new(topology: Box<dyn Topology>, portals: Vec<(Coords, Coords)>) -> Metric {
+        let unit_cost = topology.get_unit_cost();
+
+        let mut between_portals: Vec<Vec<i32>> = portals.iter()
+            .map(|(_, exit_coords)| portals.iter().map(|(entry_coords, _)| topology.get_distance(*exit_coords, *entry_coords)).collect())
+            .collect();
+
+        for via in 0..portals.len() {
+            for from in 0..portals.len() {
+                for to in 0..portals.len() {
+                    let through = between_portals[from][via] + unit_cost + between_portals[via][to];
+
+                    if through < between_portals[from][to] {
+                        between_portals[from][to] = through;
+                    }
+                }
+            }
+        }
+
+        return Metric { topology, portals, between_portals };
+    }
+
+    pub fn get_step_cost(&self, from_coords: Coords, to_coords: Coords) -> i32 {
+        if self.portals.contains(&(from_coords, to_coords)) {
+            return self.topology.get_unit_cost();
+        }
+
+        return self.topology.get_distance(from_coords, to_coords);
+    }
+
+    pub fn get_distance(&self, from_coords: Coords, to_coords: Coords) -> i32 {
+        let mut distance = self.topology.get_distance(from_coords, to_coords);
+
+        if self.portals.is_empty() { return distance; }
+
+        let unit_cost = self.topology.get_unit_cost();
+
+        for (first, (first_entry, _)) in self.portals.iter().enumerate() {
+            let to_first = self.topology.get_distance(from_coords, *first_entry) + unit_cost;
+
+            if to_first >= distance { continue; }
+
+            for (last, (_, last_exit)) in self.portals.iter().enumerate() {
+                let between = if first == last { 0 } else { self.between_portals[first][last] + unit_cost };
+
+                let through = to_first + between + self.topology.get_distance(*last_exit, to_coords);
+
+                distance = i32::min(distance, through);
+            }
+        }
+
+        return distance;
+    }
+}
+
+impl Default for Metric {
+    fn default() -> Metric {
+        return Metric::new(Box::new(SquareTopology::default()), vec![]);
+    }
+}
+
+impl Clone for Metric {
+    fn clone(&self) -> Metric {
+        return Metric { topology: self.topology.clone_box(), portals: self.portals.clone(), between_portals: self.between_portals.clone() };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape};
+
+    const SHAPES: [Shape; 4] = [Shape::Square, Shape::Diagonal(CornerCutting::Allowed), Shape::Diagonal(CornerCutting::Disallowed), Shape::Brick];
+
+    #[test]
+    fn distances_through_portals_are_exact_on_open_grids_and_bounds_elsewhere() {
+        for seed in 0..80 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let shape = SHAPES[seed as usize % 4];
+            let open_chance = [1.0, 0.6][seed as usize / 4 % 2];
+
+            let size = rng.gen_range(2..12);
+
+            let mut graph = testing::random_grid(&mut rng, shape, size, open_chance);
+
+            for _ in 0..rng.gen_range(0..5) {
+                let (entry_coords, exit_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+                graph.add_portal(entry_coords, exit_coords);
+            }
+
+            let metric = graph.get_metric();
+
+            for (entry_coords, exit_coords) in graph.get_portals() {
+                assert_eq!(metric.get_step_cost(entry_coords, exit_coords), graph.get_topology().get_unit_cost());
+            }
+
+            for _ in 0..20 {
+                let from_coords = testing::random_coords(&mut rng, &graph);
+                let to_coords = testing::random_coords(&mut rng, &graph);
+
+                let distance = metric.get_distance(from_coords, to_coords);
+
+                match testing::dijkstra(&graph, from_coords, to_coords) {
+                    None => assert!(open_chance < 1.0, "seed {}: {:?} -> {:?} cut off on an open grid", seed, from_coords, to_coords),
+                    Some(cost) if open_chance == 1.0 => assert_eq!(distance, cost, "seed {} on {:?}: {:?} -> {:?}", seed, shape, from_coords, to_coords),
+                    Some(cost) => assert!(distance <= cost, "seed {} on {:?}: {:?} -> {:?} overestimated", seed, shape, from_coords, to_coords),
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,29 @@
 pub mod Graph;
 pub mod Node;
-pub mod astar;
\ No newline at end of file
+pub mod agent;
+pub mod astar;
+pub mod bidirectional;
+pub mod exploration;
+pub mod filling;
+pub mod hpa;
+pub mod ida_star;
+pub mod incremental;
+pub mod jps;
+pub mod keys;
+pub mod landmarks;
+pub mod mapf;
+pub mod metric;
+pub mod pursuit;
+pub mod replay;
+pub mod schedule;
+pub mod sma_star;
+pub mod solver;
+pub mod stats;
+#[cfg(test)]
+pub mod testing;
+pub mod timed;
+pub mod topology;
+pub mod tour;
+pub mod trace;
+pub mod tremaux;
+pub mod validation;
\ No newline at end of file
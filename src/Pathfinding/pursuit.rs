@@ -0,0 +1,484 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+use priority_queue::PriorityQueue;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use super::Graph;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+// The target only moves on every other tick so an equally fast chaser can corner it.
+const TARGET_PERIOD: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetPolicy {
+    RandomWalk,
+    Flee,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChaseStrategy {
+    Replan,
+    ReuseTree,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChaseReport {
+    pub ticks: usize,
+    pub expansions: usize,
+    pub is_caught: bool,
+}
+
+pub struct Pursuit {
+    target_policy: TargetPolicy,
+    strategy: ChaseStrategy,
+
+    start_coords: Coords,
+    target_coords: Coords,
+    current_coords: Coords,
+
+    // A* tree rooted at the chaser, kept between ticks when the strategy allows it.
+    open_set: PriorityQueue<Coords, (Reverse<i32>, i32)>,
+    closed_set: HashSet<Coords>,
+    came_from: HashMap<Coords, Coords>,
+    g_score: HashMap<Coords, i32>,
+
+    ticks: usize,
+    metric: Metric,
+    // Set when walls change, since the kept tree may then run through walls or around new passages.
+    is_tree_stale: bool,
+
+    // The target starts every chase from the same seed, so each strategy can be set after the same walk.
+    seed: u64,
+    rng: StdRng,
+
+    stats: SearchStats,
+
+    trail: Vec<Coords>,
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl Pursuit {
+    pub fn new(target_policy: TargetPolicy, strategy: ChaseStrategy) -> Pursuit {
+        let seed = rand::random();
+
+        return Pursuit {
+            target_policy,
+            strategy,
+            start_coords: (0, 0),
+            target_coords: (0, 0),
+            current_coords: (0, 0),
+            open_set: PriorityQueue::new(),
+            closed_set: HashSet::new(),
+            came_from: HashMap::new(),
+            g_score: HashMap::new(),
+            ticks: 0,
+            metric: Metric::default(),
+            is_tree_stale: false,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            stats: SearchStats::default(),
+            trail: vec![],
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn reset_tree(&mut self) {
+        self.open_set = PriorityQueue::new();
+        self.closed_set = HashSet::new();
+        self.came_from = HashMap::new();
+        self.g_score = HashMap::from([(self.current_coords, 0)]);
+
+        self.push(self.current_coords);
+    }
+
+    fn push(&mut self, node_coords: Coords) {
+        let g = self.g_score[&node_coords];
+
+        let f = g + self.metric.get_distance(node_coords, self.target_coords);
+
+        self.open_set.push(node_coords, (Reverse(f), g));
+    }
+
+    // Keeps the part of the tree hanging below the chaser's new cell, and reopens cells on its
+    // border so the search can grow back into everything that was dropped. Once shifted, a kept
+    // g is only the distance from the new cell when the old one was the distance from the old
+    // cell, so search treats them as upper bounds and keeps lowering them where it can.
+    fn reroot_tree(&mut self, graph: &Graph::Graph, root_coords: Coords) {
+        let mut children: HashMap<Coords, Vec<Coords>> = HashMap::new();
+
+        for (child_coords, parent_coords) in &self.came_from {
+            children.entry(*parent_coords).or_default().push(*child_coords);
+        }
+
+        let mut kept: HashSet<Coords> = HashSet::from([root_coords]);
+        let mut queue: VecDeque<Coords> = VecDeque::from([root_coords]);
+
+        while let Some(node_coords) = queue.pop_front() {
+            for child_coords in children.get(&node_coords).cloned().unwrap_or_default() {
+                if kept.insert(child_coords) {
+                    queue.push_back(child_coords);
+                }
+            }
+        }
+
+        let offset = self.g_score[&root_coords];
+
+        self.g_score = self.g_score.iter()
+            .filter(|(node_coords, _)| kept.contains(node_coords))
+            .map(|(node_coords, g)| (*node_coords, g - offset))
+            .collect();
+
+        self.came_from.retain(|node_coords, _| kept.contains(node_coords) && *node_coords != root_coords);
+        self.closed_set.retain(|node_coords| kept.contains(node_coords));
+
+        let open_coords: Vec<Coords> = self.open_set.iter().map(|(node_coords, _)| *node_coords).collect();
+
+        self.open_set = PriorityQueue::new();
+
+        for node_coords in open_coords {
+            if kept.contains(&node_coords) {
+                self.push(node_coords);
+            }
+        }
+
+        let closed_coords: Vec<Coords> = self.closed_set.iter().copied().collect();
+
+        for node_coords in closed_coords {
+            let is_border = graph.iter_connections(node_coords).any(|connection_coords| !kept.contains(&connection_coords));
+
+            if is_border {
+                self.closed_set.remove(&node_coords);
+
+                self.push(node_coords);
+            }
+        }
+    }
+
+    fn search(&mut self, graph: &Graph::Graph) -> Vec<Coords> {
+        // The target has moved since the open set was ordered.
+        let open_coords: Vec<Coords> = self.open_set.iter().map(|(node_coords, _)| *node_coords).collect();
+
+        for node_coords in open_coords {
+            self.push(node_coords);
+        }
+
+        // Reaching the target is not enough to stop, since a kept g can be more than the true distance
+        // and something still open could lead there more cheaply.
+        loop {
+            let opt_best_f = self.open_set.peek().map(|(_, priority)| priority.0.0);
+
+            if opt_best_f.is_none() { break; }
+
+            if self.closed_set.contains(&self.target_coords) && opt_best_f.unwrap() >= self.g_score[&self.target_coords] { break; }
+
+            let node_coords = self.open_set.pop().unwrap().0;
+
+            // Every closed cell is expanded, the target included, so a tree kept for later ticks has
+            // already looked past each of its cells.
+            self.closed_set.insert(node_coords);
+
+            self.stats.record_expansion();
+
+            let g = self.g_score[&node_coords];
+
+            for connection_coords in graph.iter_connections(node_coords) {
+                let tentative_g_score = g + self.metric.get_step_cost(node_coords, connection_coords);
+
+                if tentative_g_score >= *self.g_score.get(&connection_coords).unwrap_or(&i32::MAX) { continue; }
+
+                self.closed_set.remove(&connection_coords);
+                self.came_from.insert(connection_coords, node_coords);
+                self.g_score.insert(connection_coords, tentative_g_score);
+
+                self.push(connection_coords);
+
+                self.stats.record_generated(1);
+                self.stats.record_frontier(self.open_set.len());
+            }
+        }
+
+        if !self.closed_set.contains(&self.target_coords) { return vec![]; }
+
+        let mut plan = vec![self.target_coords];
+
+        let mut node_coords = self.target_coords;
+
+        while let Some(parent_coords) = self.came_from.get(&node_coords) {
+            node_coords = *parent_coords;
+
+            plan.push(node_coords);
+        }
+
+        return plan;
+    }
+
+    fn move_target(&mut self, graph: &Graph::Graph) {
+        let mut moves = graph.get_connections(self.target_coords);
+
+        moves.push(self.target_coords);
+
+        if self.target_policy == TargetPolicy::RandomWalk {
+            self.target_coords = moves[self.rng.gen_range(0..moves.len())];
+
+            return;
+        }
+
+        let distances = Pursuit::measure_distances(graph, self.current_coords);
+
+        let farthest = moves.iter().map(|coords| *distances.get(coords).unwrap_or(&i32::MAX)).max().unwrap();
+
+        let candidates: Vec<Coords> = moves.into_iter().filter(|coords| *distances.get(coords).unwrap_or(&i32::MAX) == farthest).collect();
+
+        self.target_coords = candidates[self.rng.gen_range(0..candidates.len())];
+    }
+
+    fn measure_distances(graph: &Graph::Graph, origin_coords: Coords) -> HashMap<Coords, i32> {
+        let mut distances: HashMap<Coords, i32> = HashMap::from([(origin_coords, 0)]);
+        let mut queue: VecDeque<Coords> = VecDeque::from([origin_coords]);
+
+        while let Some(node_coords) = queue.pop_front() {
+            for connection_coords in graph.iter_connections(node_coords) {
+                if distances.contains_key(&connection_coords) { continue; }
+
+                distances.insert(connection_coords, distances[&node_coords] + 1);
+
+                queue.push_back(connection_coords);
+            }
+        }
+
+        return distances;
+    }
+
+    fn finish(&mut self, is_caught: bool) {
+        self.path = if is_caught { self.trail.iter().rev().copied().collect() } else { vec![] };
+        self.is_finished = true;
+    }
+}
+
+impl Solver for Pursuit {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.target_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.ticks = 0;
+        self.stats = SearchStats::default();
+        self.trail = vec![start_coords];
+        self.path = vec![];
+        self.is_finished = false;
+        self.is_tree_stale = false;
+        self.rng = StdRng::seed_from_u64(self.seed);
+
+        self.reset_tree();
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        if self.current_coords == self.target_coords {
+            self.finish(true);
+
+            return;
+        }
+
+        if self.ticks > graph.get_topology().get_cell_count() * 4 {
+            self.finish(false);
+
+            return;
+        }
+
+        self.ticks += 1;
+
+        if self.strategy == ChaseStrategy::Replan || self.is_tree_stale {
+            self.reset_tree();
+
+            self.is_tree_stale = false;
+        }
+
+        let mut plan = self.search(graph);
+
+        if plan.len() < 2 {
+            self.finish(false);
+
+            return;
+        }
+
+        plan.pop();
+
+        self.current_coords = *plan.last().unwrap();
+        self.trail.push(self.current_coords);
+
+        self.path = plan;
+
+        if self.strategy == ChaseStrategy::ReuseTree {
+            self.reroot_tree(graph, self.current_coords);
+        }
+
+        if self.ticks.is_multiple_of(TARGET_PERIOD) && self.current_coords != self.target_coords {
+            self.move_target(graph);
+        }
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    fn notify_graph_changed(&mut self) {
+        self.is_tree_stale = true;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.target_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return self.open_set.iter().map(|(node_coords, _)| *node_coords).collect();
+    }
+
+    fn get_chase_report(&self) -> Option<ChaseReport> {
+        return Some(ChaseReport { ticks: self.ticks, expansions: self.stats.expansions, is_caught: self.current_coords == self.target_coords });
+    }
+
+    fn get_trail(&self) -> Vec<Coords> {
+        return self.trail.clone();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::{ChaseReport, ChaseStrategy, Pursuit, TargetPolicy};
+    use crate::Pathfinding::Node::Coords;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::Shape;
+
+    #[test]
+    fn reused_trees_still_plan_shortest_routes_as_walls_change() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let mut graph = testing::generate_maze(16).graph;
+
+            for _ in 0..3 {
+                let (entry_coords, exit_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+                graph.add_portal(entry_coords, exit_coords);
+            }
+
+            let mut pursuit = Pursuit::new(TargetPolicy::Flee, ChaseStrategy::ReuseTree);
+
+            pursuit.set_metric(graph.get_metric());
+            pursuit.initialize(testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+            while !pursuit.is_finished() {
+                // Now and then a passage opens or closes under the chase.
+                if rng.gen_bool(0.3) {
+                    let node_coords = testing::random_coords(&mut rng, &graph);
+
+                    let neighbors = graph.get_wall_neighbors(node_coords);
+
+                    let neighbor_coords = neighbors[rng.gen_range(0..neighbors.len())];
+
+                    if graph.is_connected(node_coords, neighbor_coords) {
+                        graph.remove_connection(node_coords, neighbor_coords);
+                        graph.remove_connection(neighbor_coords, node_coords);
+                    } else {
+                        graph.add_connection(node_coords, neighbor_coords);
+                        graph.add_connection(neighbor_coords, node_coords);
+                    }
+
+                    pursuit.notify_graph_changed();
+                }
+
+                let previous_coords = pursuit.get_current_coords();
+
+                pursuit.generate_path(&graph);
+
+                if pursuit.is_finished() { break; }
+
+                // The plan runs from the target it was made for back to the cell the chaser just stepped to.
+                let plan = pursuit.get_path();
+
+                let cost = testing::get_path_cost(&graph, &plan) + graph.get_step_cost(previous_coords, pursuit.get_current_coords());
+
+                assert_eq!(Some(cost), testing::dijkstra(&graph, previous_coords, plan[0]), "seed {} from {:?}", seed, previous_coords);
+            }
+        }
+    }
+
+    #[test]
+    fn both_strategies_chase_the_same_random_walk_from_the_same_seed() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let graph = testing::random_grid(&mut rng, Shape::Square, 12, 0.7);
+
+            let (start_coords, target_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+            let chases: Vec<(ChaseReport, Vec<Coords>)> = [ChaseStrategy::Replan, ChaseStrategy::ReuseTree].iter().map(|strategy| {
+                let mut pursuit = Pursuit::new(TargetPolicy::RandomWalk, *strategy);
+
+                pursuit.set_seed(seed);
+                pursuit.set_metric(graph.get_metric());
+                pursuit.initialize(start_coords, target_coords);
+
+                let mut walk = vec![target_coords];
+
+                while !pursuit.is_finished() {
+                    pursuit.generate_path(&graph);
+
+                    walk.push(pursuit.get_goal_coords());
+                }
+
+                return (pursuit.get_chase_report().unwrap(), walk);
+            }).collect();
+
+            let (replan, reuse) = (&chases[0], &chases[1]);
+
+            // The walk only depends on the seed, so it matches for as long as both chases last.
+            let shared = usize::min(replan.1.len(), reuse.1.len());
+
+            assert_eq!(replan.1[..shared], reuse.1[..shared], "seed {}", seed);
+
+            // Shortest routes can tie, so the chasers need not catch the target on the same tick, but either both do or neither.
+            assert_eq!(replan.0.is_caught, reuse.0.is_caught, "seed {}", seed);
+            assert!(reuse.0.expansions <= replan.0.expansions, "seed {}: {:?} against {:?}", seed, reuse.0, replan.0);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use super::Graph;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::{CellScores, SearchStats};
+use super::trace::{SearchTrace, TraceAction};
+
+// Plays a recorded search back without running it again. Positive speeds move forwards
+// through the events, negative ones backwards.
+pub struct TraceReplay {
+    trace: SearchTrace,
+    position: usize,
+    speed: i32,
+
+    start_coords: Coords,
+    goal_coords: Coords,
+
+    open_set: HashMap<Coords, i32>,
+    closed_set: Vec<Coords>,
+    came_from: HashMap<Coords, Coords>,
+    g_score: HashMap<Coords, i32>,
+
+    metric: Metric,
+
+    // The stats the search had reached at each event played so far, with the largest frontier
+    // kept per event so it can be wound back.
+    stats: SearchStats,
+    frontier_peaks: Vec<usize>,
+
+    path: Vec<Coords>,
+}
+
+impl TraceReplay {
+    pub fn new(trace: SearchTrace) -> TraceReplay {
+        let mut replay = TraceReplay {
+            trace,
+            position: 0,
+            speed: 1,
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            open_set: HashMap::new(),
+            closed_set: vec![],
+            came_from: HashMap::new(),
+            g_score: HashMap::new(),
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            frontier_peaks: vec![],
+            path: vec![]
+        };
+
+        let opt_first = replay.trace.get_events().first().map(|event| event.action.clone());
+
+        if let Some(TraceAction::Initialize { start, goal }) = opt_first {
+            replay.start_coords = start;
+            replay.goal_coords = goal;
+        }
+
+        return replay;
+    }
+
+    fn set_or_remove<T: Copy>(map: &mut HashMap<Coords, T>, coords: Coords, opt_value: Option<T>) {
+        match opt_value {
+            None => { map.remove(&coords); },
+            Some(value) => { map.insert(coords, value); },
+        }
+    }
+
+    fn apply(&mut self, action: &TraceAction) {
+        match action {
+            TraceAction::Initialize { .. } => {},
+            TraceAction::Push { coords, f, .. } => {
+                self.open_set.insert(*coords, *f);
+
+                self.stats.record_generated(1);
+            },
+            TraceAction::Pop { coords, .. } => {
+                self.open_set.remove(coords);
+                self.closed_set.push(*coords);
+
+                // Reaching the goal ends the search rather than expanding it.
+                if *coords != self.goal_coords {
+                    self.stats.record_expansion();
+                }
+            },
+            TraceAction::ScoreUpdate { coords, g, .. } => { self.g_score.insert(*coords, *g); },
+            TraceAction::ParentChange { coords, parent, .. } => { self.came_from.insert(*coords, *parent); },
+            TraceAction::PathFound { path } => self.path = path.clone(),
+        }
+
+        let peak = usize::max(*self.frontier_peaks.last().unwrap_or(&0), self.open_set.len());
+
+        self.frontier_peaks.push(peak);
+        self.stats.max_frontier = peak;
+    }
+
+    fn undo(&mut self, action: &TraceAction) {
+        match action {
+            TraceAction::Initialize { .. } => {},
+            TraceAction::Push { coords, previous_f, .. } => {
+                TraceReplay::set_or_remove(&mut self.open_set, *coords, *previous_f);
+
+                self.stats.generated -= 1;
+            },
+            TraceAction::Pop { coords, f } => {
+                self.closed_set.pop();
+                self.open_set.insert(*coords, *f);
+
+                if *coords != self.goal_coords {
+                    self.stats.expansions -= 1;
+                }
+            },
+            TraceAction::ScoreUpdate { coords, previous_g, .. } => TraceReplay::set_or_remove(&mut self.g_score, *coords, *previous_g),
+            TraceAction::ParentChange { coords, previous_parent, .. } => TraceReplay::set_or_remove(&mut self.came_from, *coords, *previous_parent),
+            TraceAction::PathFound { .. } => self.path = vec![],
+        }
+
+        self.frontier_peaks.pop();
+        self.stats.max_frontier = *self.frontier_peaks.last().unwrap_or(&0);
+    }
+}
+
+impl Solver for TraceReplay {
+    // The trace decides where the search runs, so a new query only rewinds it.
+    fn initialize(&mut self, _start_coords: Coords, _goal_coords: Coords) {
+        while self.position > 0 {
+            self.position -= 1;
+
+            let action = self.trace.get_events()[self.position].action.clone();
+
+            self.undo(&action);
+        }
+    }
+
+    fn generate_path(&mut self, _graph: &Graph::Graph) {
+        for _ in 0..self.speed.unsigned_abs() {
+            if self.speed > 0 {
+                if self.position == self.trace.get_events().len() { return; }
+
+                let action = self.trace.get_events()[self.position].action.clone();
+
+                self.apply(&action);
+
+                self.position += 1;
+            } else {
+                if self.position == 0 { return; }
+
+                self.position -= 1;
+
+                let action = self.trace.get_events()[self.position].action.clone();
+
+                self.undo(&action);
+            }
+        }
+    }
+
+    fn set_playback_speed(&mut self, speed: i32) {
+        self.speed = speed;
+    }
+
+    fn get_playback_position(&self) -> Option<(usize, usize)> {
+        return Some((self.position, self.trace.get_events().len()));
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return *self.closed_set.last().unwrap_or(&self.start_coords);
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return self.open_set.keys().copied().collect();
+    }
+
+    fn get_closed_set(&self) -> Vec<Coords> {
+        return self.closed_set.clone();
+    }
+
+    fn get_came_from(&self) -> Vec<(Coords, Coords)> {
+        return self.came_from.iter().map(|(node_coords, parent_coords)| (*node_coords, *parent_coords)).collect();
+    }
+
+    fn get_cell_scores(&self, coords: Coords) -> Option<CellScores> {
+        let h = self.metric.get_distance(coords, self.goal_coords);
+
+        return Some(CellScores { g: self.g_score.get(&coords).copied(), h, parent: self.came_from.get(&coords).copied() });
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    // A replay stays open so it can always be played in either direction.
+    fn is_finished(&self) -> bool {
+        return false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::TraceReplay;
+    use crate::Pathfinding::Node::Coords;
+    use crate::Pathfinding::astar::Astar;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::Shape;
+
+    type Snapshot = (Vec<Coords>, Vec<Coords>, Vec<(Coords, Coords)>, HashMap<Coords, Option<i32>>, Vec<Coords>);
+
+    fn take_snapshot(replay: &TraceReplay) -> Snapshot {
+        let mut open_set = replay.get_open_set();
+        let mut came_from = replay.get_came_from();
+
+        open_set.sort();
+        came_from.sort();
+
+        let g_score = replay.g_score.keys().map(|coords| (*coords, replay.get_cell_scores(*coords).unwrap().g)).collect();
+
+        return (open_set, replay.get_closed_set(), came_from, g_score, replay.get_path());
+    }
+
+    fn record_astar(seed: u64) -> (Astar, TraceReplay) {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let size = rng.gen_range(3..12);
+
+        let graph = testing::random_grid(&mut rng, Shape::Square, size, [0.6, 0.8, 1.0][seed as usize % 3]);
+
+        let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+        let mut astar = Astar::new();
+
+        testing::solve(&mut astar, &graph, start_coords, goal_coords);
+
+        let replay = TraceReplay::new(astar.get_trace().unwrap().clone());
+
+        return (astar, replay);
+    }
+
+    #[test]
+    fn playing_forwards_then_backwards_restores_every_event() {
+        // A replay never looks at the graph it is handed.
+        let graph = testing::random_grid(&mut StdRng::seed_from_u64(0), Shape::Square, 1, 1.0);
+
+        for seed in 0..30 {
+            let (astar, mut replay) = record_astar(seed);
+
+            let event_count = astar.get_trace().unwrap().get_events().len();
+
+            let mut snapshots: Vec<Snapshot> = vec![take_snapshot(&replay)];
+
+            for _ in 0..event_count {
+                replay.generate_path(&graph);
+
+                snapshots.push(take_snapshot(&replay));
+            }
+
+            // The last event played leaves the search exactly where the solver left it.
+            let (open_set, closed_set, came_from, _, path) = snapshots.last().unwrap().clone();
+
+            let mut astar_open_set = astar.get_open_set();
+            let mut astar_came_from = astar.get_came_from();
+
+            astar_open_set.sort();
+            astar_came_from.sort();
+
+            assert_eq!((open_set, came_from, path), (astar_open_set, astar_came_from, astar.get_path()), "seed {}", seed);
+            // The replay also shows the goal being taken off the frontier, which the solver stops at.
+            let expanded: Vec<Coords> = closed_set.into_iter().filter(|coords| *coords != astar.get_goal_coords()).collect();
+
+            assert_eq!(expanded, astar.get_closed_set(), "seed {}", seed);
+
+            let (replay_stats, astar_stats) = (replay.get_stats(), astar.get_stats());
+
+            assert_eq!((replay_stats.expansions, replay_stats.generated, replay_stats.max_frontier), (astar_stats.expansions, astar_stats.generated, astar_stats.max_frontier), "seed {}", seed);
+
+            replay.set_playback_speed(-1);
+
+            for position in (0..event_count).rev() {
+                replay.generate_path(&graph);
+
+                assert_eq!(replay.get_playback_position(), Some((position, event_count)), "seed {}", seed);
+                assert_eq!(take_snapshot(&replay), snapshots[position], "seed {}: event {} was not undone", seed, position);
+            }
+
+            assert_eq!(replay.get_stats(), Default::default(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn negative_speeds_step_back_that_many_events() {
+        let graph = testing::random_grid(&mut StdRng::seed_from_u64(0), Shape::Square, 1, 1.0);
+
+        let (astar, mut replay) = record_astar(1);
+
+        let event_count = astar.get_trace().unwrap().get_events().len();
+
+        replay.set_playback_speed(5);
+        replay.generate_path(&graph);
+
+        assert_eq!(replay.get_playback_position(), Some((5, event_count)));
+
+        replay.set_playback_speed(-3);
+        replay.generate_path(&graph);
+
+        assert_eq!(replay.get_playback_position(), Some((2, event_count)));
+
+        // Stepping back past the start stops there.
+        replay.generate_path(&graph);
+
+        assert_eq!(replay.get_playback_position(), Some((0, event_count)));
+        assert!(replay.get_open_set().is_empty() && replay.get_closed_set().is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+// When a gated passage can be crossed. A step is taken at one timestep and arrives at the next,
+// and the passage has to be open at the timestep the step starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    // Open for the first `open` timesteps of every `period`, shifted along by `offset`.
+    Periodic { period: usize, open: usize, offset: usize },
+    // Open from one timestep until just before another, and closed for good after that.
+    Window { from: usize, until: usize },
+}
+
+impl Schedule {
+    pub fn is_open(&self, time: usize) -> bool {
+        match *self {
+            Schedule::Periodic { period, open, offset } => return (time + offset) % period < open,
+            Schedule::Window { from, until } => return time >= from && time < until,
+        }
+    }
+
+    // How often the schedule repeats once it has settled.
+    pub fn get_period(&self) -> usize {
+        match *self {
+            Schedule::Periodic { period, .. } => return period,
+            Schedule::Window { .. } => return 1,
+        }
+    }
+
+    // The timestep from which the schedule only repeats.
+    pub fn get_settle_time(&self) -> usize {
+        match *self {
+            Schedule::Periodic { .. } => return 0,
+            Schedule::Window { until, .. } => return until,
+        }
+    }
+}
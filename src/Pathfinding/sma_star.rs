@@ -0,0 +1,458 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use priority_queue::PriorityQueue;
+
+use super::Graph;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+const INFINITY: i32 = i32::MAX;
+
+struct SearchNode {
+    coords: Coords,
+    parent: Option<usize>,
+    g: i32,
+    f: i32,
+    depth: usize,
+
+    // Successors that are not in memory, either never generated or forgotten.
+    pending: Option<Vec<Coords>>,
+    forgotten_f: HashMap<Coords, i32>,
+    children: Vec<usize>,
+}
+
+pub struct SmaStar {
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    max_nodes: usize,
+    next_id: usize,
+    nodes: HashMap<usize, SearchNode>,
+    // The nodes in memory for each cell, so a cell reached again along a worse route can be passed over.
+    cell_nodes: HashMap<Coords, Vec<usize>>,
+    open_set: PriorityQueue<usize, (Reverse<i32>, usize)>,
+    forgotten_set: Vec<Coords>,
+
+    metric: Metric,
+
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl SmaStar {
+    pub fn new(max_nodes: usize) -> SmaStar {
+        return SmaStar {
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            max_nodes: usize::max(max_nodes, 2),
+            next_id: 0,
+            nodes: HashMap::new(),
+            cell_nodes: HashMap::new(),
+            open_set: PriorityQueue::new(),
+            forgotten_set: vec![],
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn h(&self, node_coords: Coords) -> i32 {
+        return self.metric.get_distance(node_coords, self.goal_coords);
+    }
+
+    fn add_node(&mut self, coords: Coords, parent: Option<usize>, g: i32, f: i32, depth: usize) -> usize {
+        let id = self.next_id;
+
+        self.next_id += 1;
+
+        self.nodes.insert(id, SearchNode {
+            coords, parent, g, f, depth, pending: None, forgotten_f: HashMap::new(), children: vec![]
+        });
+
+        self.cell_nodes.entry(coords).or_default().push(id);
+
+        self.open_set.push(id, (Reverse(f), depth));
+
+        self.stats.record_generated(1);
+        self.stats.record_frontier(self.open_set.len());
+
+        return id;
+    }
+
+    fn is_ancestor(&self, id: usize, coords: Coords) -> bool {
+        let mut opt_id = Some(id);
+
+        while let Some(current_id) = opt_id {
+            let node = &self.nodes[&current_id];
+
+            if node.coords == coords { return true; }
+
+            opt_id = node.parent;
+        }
+
+        return false;
+    }
+
+    // Whether memory already holds the cell reached at least as cheaply and no deeper, in which case
+    // everything past it is already covered there.
+    fn is_duplicate(&self, coords: Coords, g: i32, depth: usize) -> bool {
+        match self.cell_nodes.get(&coords) {
+            None => return false,
+            Some(ids) => return ids.iter().any(|id| self.nodes[id].g <= g && self.nodes[id].depth <= depth),
+        }
+    }
+
+    fn expand_pending(&mut self, graph: &Graph::Graph, id: usize) {
+        if self.nodes[&id].pending.is_some() { return; }
+
+        let mut successors: Vec<Coords> = graph.get_connections(self.nodes[&id].coords)
+            .into_iter()
+            .filter(|coords| !self.is_ancestor(id, *coords))
+            .collect();
+
+        // Successors are popped from the back, so this generates them in ascending order.
+        successors.sort();
+        successors.reverse();
+
+        self.nodes.get_mut(&id).unwrap().pending = Some(successors);
+
+        self.stats.record_expansion();
+    }
+
+    // Once every successor has been generated at least once, a node's f can be raised
+    // to the best of its children, remembered or forgotten.
+    fn backup(&mut self, id: usize) {
+        let mut opt_id = Some(id);
+
+        while let Some(current_id) = opt_id {
+            let node = &self.nodes[&current_id];
+
+            let pending = node.pending.as_ref().unwrap();
+
+            if pending.iter().any(|coords| !node.forgotten_f.contains_key(coords)) { return; }
+
+            let mut best_f = INFINITY;
+
+            for child_id in &node.children {
+                best_f = i32::min(best_f, self.nodes[child_id].f);
+            }
+
+            for f in node.forgotten_f.values() {
+                best_f = i32::min(best_f, *f);
+            }
+
+            if best_f == node.f { return; }
+
+            let (parent, depth) = (node.parent, node.depth);
+
+            self.nodes.get_mut(&current_id).unwrap().f = best_f;
+
+            if self.open_set.get(&current_id).is_some() {
+                self.open_set.change_priority(&current_id, (Reverse(best_f), depth));
+            }
+
+            opt_id = parent;
+        }
+    }
+
+    // Drops the shallowest of the worst leaves and lets its parent remember its f value.
+    fn forget_worst_leaf(&mut self, keep_id: usize) {
+        let mut opt_worst: Option<(i32, Reverse<usize>, usize)> = None;
+
+        for (id, node) in &self.nodes {
+            if *id == keep_id || node.parent.is_none() || !node.children.is_empty() { continue; }
+
+            let candidate = (node.f, Reverse(node.depth), *id);
+
+            if opt_worst.is_none() || candidate > opt_worst.unwrap() {
+                opt_worst = Some(candidate);
+            }
+        }
+
+        if opt_worst.is_none() { return; }
+
+        let id = opt_worst.unwrap().2;
+
+        let node = self.nodes.remove(&id).unwrap();
+
+        self.open_set.remove(&id);
+        self.forgotten_set.push(node.coords);
+
+        self.cell_nodes.get_mut(&node.coords).unwrap().retain(|other_id| *other_id != id);
+
+        let parent_id = node.parent.unwrap();
+
+        let parent = self.nodes.get_mut(&parent_id).unwrap();
+
+        parent.children.retain(|child_id| *child_id != id);
+
+        let forgotten_f = parent.forgotten_f.entry(node.coords).or_insert(node.f);
+
+        *forgotten_f = i32::min(*forgotten_f, node.f);
+
+        // Only children that could still lead somewhere are worth regenerating, and only
+        // once the successors that were never generated have had their turn.
+        if node.f != INFINITY {
+            parent.pending.as_mut().unwrap().insert(0, node.coords);
+
+            let priority = (Reverse(parent.f), parent.depth);
+
+            self.open_set.push(parent_id, priority);
+        }
+
+        self.backup(parent_id);
+    }
+
+    fn reconstruct_path(&mut self, id: usize) {
+        let mut opt_id = Some(id);
+
+        while let Some(current_id) = opt_id {
+            let node = &self.nodes[&current_id];
+
+            self.path.push(node.coords);
+
+            opt_id = node.parent;
+        }
+    }
+}
+
+impl Solver for SmaStar {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.next_id = 0;
+        self.nodes = HashMap::new();
+        self.cell_nodes = HashMap::new();
+        self.open_set = PriorityQueue::new();
+        self.forgotten_set = vec![];
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+
+        let f = self.h(start_coords);
+
+        self.add_node(start_coords, None, 0, f, 0);
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        let opt_best = self.open_set.peek();
+
+        if opt_best.is_none() || (opt_best.unwrap().1).0.0 == INFINITY {
+            self.is_finished = true;
+
+            return;
+        }
+
+        let id = *opt_best.unwrap().0;
+
+        self.current_coords = self.nodes[&id].coords;
+
+        if self.current_coords == self.goal_coords {
+            self.reconstruct_path(id);
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        self.expand_pending(graph, id);
+
+        let node = self.nodes.get_mut(&id).unwrap();
+
+        let opt_successor_coords = node.pending.as_mut().unwrap().pop();
+
+        if opt_successor_coords.is_none() {
+            // A dead end with nothing left to generate can never lead to the goal.
+            node.f = INFINITY;
+
+            let opt_parent_id = node.parent;
+
+            self.open_set.remove(&id);
+
+            if let Some(parent_id) = opt_parent_id {
+                self.backup(parent_id);
+            }
+
+            return;
+        }
+
+        let successor_coords = opt_successor_coords.unwrap();
+
+        let opt_forgotten_f = node.forgotten_f.remove(&successor_coords);
+
+        let (g, f, depth) = (node.g, node.f, node.depth);
+
+        if node.pending.as_ref().unwrap().is_empty() {
+            self.open_set.remove(&id);
+        }
+
+        let successor_g = g + self.metric.get_step_cost(self.current_coords, successor_coords);
+
+        if self.is_duplicate(successor_coords, successor_g, depth + 1) {
+            self.backup(id);
+
+            return;
+        }
+
+        // Paths longer than the budget cannot be held in memory, so they are worthless.
+        let successor_f = if successor_coords != self.goal_coords && depth + 2 >= self.max_nodes {
+            INFINITY
+        } else {
+            i32::max(f, successor_g + self.h(successor_coords))
+        };
+
+        // A regenerated child starts from what was learned about it before it was forgotten.
+        let successor_f = i32::max(successor_f, opt_forgotten_f.unwrap_or(successor_f));
+
+        let successor_id = self.add_node(successor_coords, Some(id), successor_g, successor_f, depth + 1);
+
+        self.nodes.get_mut(&id).unwrap().children.push(successor_id);
+
+        self.backup(id);
+
+        while self.nodes.len() > self.max_nodes {
+            let count = self.nodes.len();
+
+            self.forget_worst_leaf(successor_id);
+
+            if self.nodes.len() == count { break; }
+        }
+    }
+
+    fn set_node_budget(&mut self, max_nodes: usize) {
+        self.max_nodes = usize::max(max_nodes, 2);
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        let mut nodes: Vec<Coords> = vec![];
+
+        for (id, _) in &self.open_set {
+            nodes.push(self.nodes[id].coords);
+        }
+
+        return nodes;
+    }
+
+    fn get_forgotten_set(&self) -> Vec<Coords> {
+        return self.forgotten_set.clone();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::SmaStar;
+    use crate::Pathfinding::astar::Astar;
+    use crate::Pathfinding::solver::{Solver, DEFAULT_NODE_BUDGET};
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape, SquareTopology};
+
+    #[test]
+    fn stays_within_its_budget_and_matches_astar_when_the_route_fits() {
+        for seed in 0..60 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let shape = [Shape::Square, Shape::Diagonal(CornerCutting::Disallowed)][seed as usize % 2];
+
+            let size = rng.gen_range(3..12);
+
+            let graph = testing::random_grid(&mut rng, shape, size, [0.6, 0.8, 1.0][seed as usize / 2 % 3]);
+
+            let start_coords = testing::random_coords(&mut rng, &graph);
+            let goal_coords = testing::random_coords(&mut rng, &graph);
+
+            let astar_path = testing::solve(&mut Astar::new(), &graph, start_coords, goal_coords);
+
+            let max_nodes = rng.gen_range(2..40);
+
+            let mut sma_star = SmaStar::new(max_nodes);
+
+            sma_star.set_metric(graph.get_metric());
+            sma_star.initialize(start_coords, goal_coords);
+
+            while !sma_star.is_finished() {
+                sma_star.generate_path(&graph);
+
+                assert!(sma_star.nodes.len() <= max_nodes, "seed {}: {} nodes held with a budget of {}", seed, sma_star.nodes.len(), max_nodes);
+            }
+
+            let path = sma_star.get_path();
+
+            if astar_path.is_empty() {
+                assert!(path.is_empty(), "seed {}", seed);
+
+                continue;
+            }
+
+            // Routes that need more cells than the budget holds may be missed, but never beaten.
+            if astar_path.len() > max_nodes {
+                assert!(path.is_empty() || testing::get_path_cost(&graph, &path) >= testing::get_path_cost(&graph, &astar_path), "seed {}", seed);
+
+                continue;
+            }
+
+            assert_eq!(testing::get_path_cost(&graph, &path), testing::get_path_cost(&graph, &astar_path), "seed {}: budget {}", seed, max_nodes);
+        }
+    }
+
+    #[test]
+    fn finishes_on_diagonal_mazes_with_corner_cutting() {
+        for seed in 0..5 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let mut maze = testing::generate_maze(14);
+
+            maze.graph.set_topology(Box::new(SquareTopology::with_diagonals(14, CornerCutting::Allowed)));
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &maze.graph), testing::random_coords(&mut rng, &maze.graph));
+
+            let path = testing::solve(&mut SmaStar::new(DEFAULT_NODE_BUDGET), &maze.graph, start_coords, goal_coords);
+
+            assert_eq!(Some(testing::get_path_cost(&maze.graph, &path)), testing::dijkstra(&maze.graph, start_coords, goal_coords), "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use std::time::Instant;
+
+use super::Graph;
+use super::Node::Coords;
+use super::agent::{Agent, AgentStrategy};
+use super::astar::{Astar, TieBreak};
+use super::bidirectional::Bidirectional;
+use super::exploration::Explorer;
+use super::filling::{FillStrategy, Filler};
+use super::hpa::{HierarchicalPlanner, DEFAULT_CLUSTER_SIZE};
+use super::ida_star::IdaStar;
+use super::incremental::IncrementalPlanner;
+use super::jps::JumpPointSearch;
+use super::keys::KeySearch;
+use super::landmarks::LandmarkSelection;
+use super::mapf::ConflictBasedSearch;
+use super::metric::Metric;
+use super::pursuit::{ChaseReport, ChaseStrategy, Pursuit, TargetPolicy};
+use super::sma_star::SmaStar;
+use super::stats::{CellScores, SearchResult, SearchStats};
+use super::timed::TimedSearch;
+use super::topology::Shape;
+use super::tour::TourPlanner;
+use super::trace::SearchTrace;
+use super::tremaux::Tremaux;
+
+pub const DEFAULT_NODE_BUDGET: usize = 1000;
+
+pub trait Solver {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords);
+
+    fn generate_path(&mut self, graph: &Graph::Graph);
+
+    // Runs one generate_path call and charges the time it took to the solver's stats.
+    fn step(&mut self, graph: &Graph::Graph) {
+        let started = Instant::now();
+
+        self.generate_path(graph);
+
+        self.get_stats_mut().elapsed += started.elapsed();
+    }
+
+    fn set_tie_break(&mut self, _tie_break: TieBreak) {}
+
+    fn set_node_budget(&mut self, _max_nodes: usize) {}
+
+    // Extra cells that a route has to pass through on its way from the start.
+    fn set_waypoints(&mut self, _waypoints: Vec<Coords>) {}
+
+    // Start and goal pairs for agents that share the maze with the one given to initialize.
+    fn set_agents(&mut self, _agents: Vec<(Coords, Coords)>) {}
+
+    // Seeds the random choices of solvers that make any, so a run can be repeated.
+    fn set_seed(&mut self, _seed: u64) {}
+
+    // The step costs and distance bounds the solver searches with.
+    fn set_metric(&mut self, _metric: Metric) {}
+
+    // Events per generate_path call for solvers that play something back, negative to go backwards.
+    fn set_playback_speed(&mut self, _speed: i32) {}
+
+    // Called after walls change so a finished solver can pick the changes up from the graph.
+    fn notify_graph_changed(&mut self) {}
+
+    // Runs the search again without its extra guidance and records the expansions that took,
+    // for solvers that have something to compare against.
+    fn compare_with_baseline(&mut self, _graph: &Graph::Graph) {}
+
+    fn get_start_coords(&self) -> Coords;
+
+    fn get_current_coords(&self) -> Coords;
+
+    fn get_goal_coords(&self) -> Coords;
+
+    fn get_open_set(&self) -> Vec<Coords>;
+
+    // Frontier grown from the goal by solvers that search from both ends.
+    fn get_backward_open_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Cells already expanded, in the order they were expanded.
+    fn get_closed_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Each reached cell paired with the cell it was reached from.
+    fn get_came_from(&self) -> Vec<(Coords, Coords)> {
+        return vec![];
+    }
+
+    // Everything the solver has done so far, for solvers that keep a record of it.
+    fn get_trace(&self) -> Option<&SearchTrace> {
+        return None;
+    }
+
+    // How far through its recording a playback is, and how long the recording is.
+    fn get_playback_position(&self) -> Option<(usize, usize)> {
+        return None;
+    }
+
+    fn get_cell_scores(&self, _coords: Coords) -> Option<CellScores> {
+        return None;
+    }
+
+    fn get_meeting_coords(&self) -> Option<Coords> {
+        return None;
+    }
+
+    // The f bound of the current iteration for iterative deepening solvers.
+    fn get_threshold(&self) -> Option<i32> {
+        return None;
+    }
+
+    // Cells dropped from memory by solvers that run under a node budget.
+    fn get_forgotten_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Cells walked so far by solvers that move a single agent through the maze.
+    fn get_trail(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Cells ruled out so far, in the order they were filled in.
+    fn get_filled_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Cells the solver has not sensed yet when it only learns the maze by moving through it.
+    fn get_unknown_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Where every agent is right now when several move at once.
+    fn get_agents(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Cells where agents got in each other's way and had to be kept apart.
+    fn get_conflicts(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Waypoints in the order they are visited once a route has been found.
+    fn get_waypoints(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    fn get_legs(&self) -> Vec<Vec<Coords>> {
+        return vec![];
+    }
+
+    // Side length of the square clusters a hierarchical solver splits the maze into.
+    fn get_cluster_size(&self) -> Option<i32> {
+        return None;
+    }
+
+    // Entrance cells the route was planned through before it was refined cell by cell.
+    fn get_abstract_path(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Cells whose keys the route picks up, in the order it picks them up.
+    fn get_collected_keys(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    // Catch time and search effort for solvers whose goal runs away from them.
+    fn get_chase_report(&self) -> Option<ChaseReport> {
+        return None;
+    }
+
+    fn get_stats(&self) -> SearchStats;
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats;
+
+    fn get_result(&self, graph: &Graph::Graph) -> SearchResult {
+        return SearchResult::new(graph, self.is_finished(), &self.get_path(), self.get_stats());
+    }
+
+    fn get_path(&self) -> Vec<Coords>;
+
+    fn is_finished(&self) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolverKind {
+    Astar,
+    AltFarthestPoint,
+    AltRandom,
+    AltCorner,
+    BidirectionalBfs,
+    BidirectionalAstar,
+    JumpPoint,
+    IdaStar,
+    SmaStar,
+    LeftWallFollower,
+    RightWallFollower,
+    Pledge,
+    RandomMouse,
+    Tremaux,
+    DeadEndFilling,
+    CulDeSacFilling,
+    LpaStar,
+    DstarLite,
+    Explorer,
+    Tour,
+    ConflictBased,
+    ChaseRandomReplan,
+    ChaseRandomReuse,
+    ChaseFleeReplan,
+    ChaseFleeReuse,
+    Hierarchical,
+    Keys,
+    Timed,
+}
+
+impl SolverKind {
+    pub fn next(&self) -> SolverKind {
+        match self {
+            SolverKind::Astar => return SolverKind::AltFarthestPoint,
+            SolverKind::AltFarthestPoint => return SolverKind::AltRandom,
+            SolverKind::AltRandom => return SolverKind::AltCorner,
+            SolverKind::AltCorner => return SolverKind::BidirectionalBfs,
+            SolverKind::BidirectionalBfs => return SolverKind::BidirectionalAstar,
+            SolverKind::BidirectionalAstar => return SolverKind::JumpPoint,
+            SolverKind::JumpPoint => return SolverKind::IdaStar,
+            SolverKind::IdaStar => return SolverKind::SmaStar,
+            SolverKind::SmaStar => return SolverKind::LeftWallFollower,
+            SolverKind::LeftWallFollower => return SolverKind::RightWallFollower,
+            SolverKind::RightWallFollower => return SolverKind::Pledge,
+            SolverKind::Pledge => return SolverKind::RandomMouse,
+            SolverKind::RandomMouse => return SolverKind::Tremaux,
+            SolverKind::Tremaux => return SolverKind::DeadEndFilling,
+            SolverKind::DeadEndFilling => return SolverKind::CulDeSacFilling,
+            SolverKind::CulDeSacFilling => return SolverKind::LpaStar,
+            SolverKind::LpaStar => return SolverKind::DstarLite,
+            SolverKind::DstarLite => return SolverKind::Explorer,
+            SolverKind::Explorer => return SolverKind::Tour,
+            SolverKind::Tour => return SolverKind::ConflictBased,
+            SolverKind::ConflictBased => return SolverKind::ChaseRandomReplan,
+            SolverKind::ChaseRandomReplan => return SolverKind::ChaseRandomReuse,
+            SolverKind::ChaseRandomReuse => return SolverKind::ChaseFleeReplan,
+            SolverKind::ChaseFleeReplan => return SolverKind::ChaseFleeReuse,
+            SolverKind::ChaseFleeReuse => return SolverKind::Hierarchical,
+            SolverKind::Hierarchical => return SolverKind::Keys,
+            SolverKind::Keys => return SolverKind::Timed,
+            SolverKind::Timed => return SolverKind::Astar,
+        }
+    }
+
+    // Whether the solver keeps to the locked doors and gates the graph has. The others plan
+    // straight through them. Walkers steer by the sides of square cells, so they cannot find
+    // their way on other grids or through portals.
+    pub fn handles(&self, graph: &Graph::Graph) -> bool {
+        if !graph.get_doors().is_empty() && *self != SolverKind::Keys { return false; }
+
+        if !graph.get_gates().is_empty() && *self != SolverKind::Timed { return false; }
+
+        let is_walker = matches!(self, SolverKind::LeftWallFollower | SolverKind::RightWallFollower | SolverKind::Pledge | SolverKind::RandomMouse);
+
+        if is_walker && (graph.get_topology().get_shape() != Shape::Square || !graph.get_portals().is_empty()) { return false; }
+
+        return true;
+    }
+
+    pub fn create(&self) -> Box<dyn Solver> {
+        match self {
+            SolverKind::Astar => return Box::new(Astar::new()),
+            SolverKind::AltFarthestPoint => return Box::new(Astar::with_landmarks(LandmarkSelection::FarthestPoint)),
+            SolverKind::AltRandom => return Box::new(Astar::with_landmarks(LandmarkSelection::Random)),
+            SolverKind::AltCorner => return Box::new(Astar::with_landmarks(LandmarkSelection::Corner)),
+            SolverKind::BidirectionalBfs => return Box::new(Bidirectional::new(false)),
+            SolverKind::BidirectionalAstar => return Box::new(Bidirectional::new(true)),
+            SolverKind::JumpPoint => return Box::new(JumpPointSearch::new()),
+            SolverKind::IdaStar => return Box::new(IdaStar::new()),
+            SolverKind::SmaStar => return Box::new(SmaStar::new(DEFAULT_NODE_BUDGET)),
+            SolverKind::LeftWallFollower => return Box::new(Agent::new(AgentStrategy::LeftHand)),
+            SolverKind::RightWallFollower => return Box::new(Agent::new(AgentStrategy::RightHand)),
+            SolverKind::Pledge => return Box::new(Agent::new(AgentStrategy::Pledge)),
+            SolverKind::RandomMouse => return Box::new(Agent::new(AgentStrategy::RandomMouse)),
+            SolverKind::Tremaux => return Box::new(Tremaux::new()),
+            SolverKind::DeadEndFilling => return Box::new(Filler::new(FillStrategy::DeadEnd)),
+            SolverKind::CulDeSacFilling => return Box::new(Filler::new(FillStrategy::CulDeSac)),
+            SolverKind::LpaStar => return Box::new(IncrementalPlanner::new(false)),
+            SolverKind::DstarLite => return Box::new(IncrementalPlanner::new(true)),
+            SolverKind::Explorer => return Box::new(Explorer::new()),
+            SolverKind::Tour => return Box::new(TourPlanner::new()),
+            SolverKind::ConflictBased => return Box::new(ConflictBasedSearch::new()),
+            SolverKind::ChaseRandomReplan => return Box::new(Pursuit::new(TargetPolicy::RandomWalk, ChaseStrategy::Replan)),
+            SolverKind::ChaseRandomReuse => return Box::new(Pursuit::new(TargetPolicy::RandomWalk, ChaseStrategy::ReuseTree)),
+            SolverKind::ChaseFleeReplan => return Box::new(Pursuit::new(TargetPolicy::Flee, ChaseStrategy::Replan)),
+            SolverKind::ChaseFleeReuse => return Box::new(Pursuit::new(TargetPolicy::Flee, ChaseStrategy::ReuseTree)),
+            SolverKind::Hierarchical => return Box::new(HierarchicalPlanner::new(DEFAULT_CLUSTER_SIZE)),
+            SolverKind::Keys => return Box::new(KeySearch::new()),
+            SolverKind::Timed => return Box::new(TimedSearch::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::SolverKind;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape};
+
+    const OPTIMAL_KINDS: [SolverKind; 13] = [
+        SolverKind::Astar,
+        SolverKind::AltFarthestPoint,
+        SolverKind::AltRandom,
+        SolverKind::AltCorner,
+        SolverKind::BidirectionalBfs,
+        SolverKind::BidirectionalAstar,
+        SolverKind::JumpPoint,
+        SolverKind::IdaStar,
+        SolverKind::SmaStar,
+        SolverKind::LpaStar,
+        SolverKind::DstarLite,
+        SolverKind::Keys,
+        SolverKind::Timed,
+    ];
+
+    #[test]
+    fn optimal_solvers_match_dijkstra_through_portals_and_one_way_passages() {
+        for seed in 0..120 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let size = rng.gen_range(3..16);
+            let open_chance = [0.5, 0.7, 1.0][seed as usize % 3];
+
+            let mut graph = testing::random_grid(&mut rng, Shape::Square, size, open_chance);
+
+            for _ in 0..rng.gen_range(0..4) {
+                let (entry_coords, exit_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+                graph.add_portal(entry_coords, exit_coords);
+            }
+
+            for _ in 0..rng.gen_range(0..size * 2) {
+                let node_coords = testing::random_coords(&mut rng, &graph);
+
+                let neighbors: Vec<_> = graph.iter_connections(node_coords).collect();
+
+                if neighbors.is_empty() { continue; }
+
+                graph.set_one_way(neighbors[rng.gen_range(0..neighbors.len())], node_coords);
+            }
+
+            let start_coords = testing::random_coords(&mut rng, &graph);
+            let goal_coords = testing::random_coords(&mut rng, &graph);
+
+            let opt_cost = testing::dijkstra(&graph, start_coords, goal_coords);
+
+            for kind in OPTIMAL_KINDS {
+                let path = testing::solve(kind.create().as_mut(), &graph, start_coords, goal_coords);
+
+                if opt_cost.is_none() {
+                    assert!(path.is_empty(), "seed {}: {:?} found a route that is not there", seed, kind);
+
+                    continue;
+                }
+
+                assert_eq!((path.last(), path.first()), (Some(&start_coords), Some(&goal_coords)), "seed {}: {:?}", seed, kind);
+
+                assert_eq!(testing::get_path_cost(&graph, &path), opt_cost.unwrap(), "seed {}: {:?} {:?} -> {:?}", seed, kind, start_coords, goal_coords);
+            }
+        }
+    }
+
+    #[test]
+    fn walkers_are_passed_over_off_square_grids_and_with_portals() {
+        let walkers = [SolverKind::LeftWallFollower, SolverKind::RightWallFollower, SolverKind::Pledge, SolverKind::RandomMouse];
+
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for shape in [Shape::Square, Shape::Diagonal(CornerCutting::Allowed), Shape::Diagonal(CornerCutting::Disallowed), Shape::Brick] {
+            let mut graph = testing::random_grid(&mut rng, shape, 6, 1.0);
+
+            for kind in walkers {
+                assert_eq!(kind.handles(&graph), shape == Shape::Square, "{:?} on {:?}", kind, shape);
+            }
+
+            assert!(SolverKind::Tremaux.handles(&graph));
+
+            graph.add_portal((0, 0), (5, 5));
+
+            for kind in walkers {
+                assert!(!kind.handles(&graph), "{:?} on {:?} with a portal", kind, shape);
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use super::Graph;
+use super::Node::Coords;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchStatus {
+    Found,
+    Unreachable,
+    InProgress,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    // Cells taken off the frontier and looked past.
+    pub expansions: usize,
+    // Cells put onto the frontier, counting every time one is put back.
+    pub generated: usize,
+    pub max_frontier: usize,
+    // Time spent inside generate_path, which is spread across many frames.
+    pub elapsed: Duration,
+    // Expansions the same search took without the extra guidance, once it has been compared.
+    pub opt_baseline_expansions: Option<usize>,
+}
+
+impl SearchStats {
+    pub fn record_expansion(&mut self) {
+        self.expansions += 1;
+    }
+
+    pub fn record_generated(&mut self, count: usize) {
+        self.generated += count;
+    }
+
+    pub fn record_frontier(&mut self, size: usize) {
+        self.max_frontier = usize::max(self.max_frontier, size);
+    }
+
+    // Folds in the work of a search run on this one's behalf.
+    pub fn absorb(&mut self, other: &SearchStats) {
+        self.expansions += other.expansions;
+        self.generated += other.generated;
+        self.max_frontier = usize::max(self.max_frontier, other.max_frontier);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchResult {
+    pub status: SearchStatus,
+    pub stats: SearchStats,
+    pub path_length: usize,
+    pub path_cost: i32,
+}
+
+impl SearchResult {
+    pub fn new(graph: &Graph::Graph, is_finished: bool, path: &[Coords], stats: SearchStats) -> SearchResult {
+        let status = if !is_finished {
+            SearchStatus::InProgress
+        } else if path.is_empty() {
+            SearchStatus::Unreachable
+        } else {
+            SearchStatus::Found
+        };
+
+        // Paths run from the goal back to the start, so each step is taken from the later cell to the earlier one.
+        let path_cost = path.windows(2).map(|pair| graph.get_step_cost(pair[1], pair[0])).sum();
+
+        return SearchResult { status, stats, path_length: path.len(), path_cost };
+    }
+}
+
+// What a solver currently knows about a single cell, for inspecting a search while it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellScores {
+    pub g: Option<i32>,
+    pub h: i32,
+    pub parent: Option<Coords>,
+}
+
+impl CellScores {
+    pub fn get_f(&self) -> Option<i32> {
+        return self.g.map(|g| g + self.h);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{SearchResult, SearchStats, SearchStatus};
+    use crate::Pathfinding::Graph;
+    use crate::Pathfinding::topology::{CornerCutting, Shape};
+
+    #[test]
+    fn status_follows_whether_the_search_is_done_and_found_a_path() {
+        let graph = Graph::Graph::new(4);
+
+        let stats = SearchStats { expansions: 3, generated: 5, max_frontier: 2, elapsed: Duration::from_millis(7), opt_baseline_expansions: None };
+
+        let cases = [
+            (false, vec![], SearchStatus::InProgress),
+            (false, vec![(1, 0), (0, 0)], SearchStatus::InProgress),
+            (true, vec![], SearchStatus::Unreachable),
+            (true, vec![(0, 0)], SearchStatus::Found),
+            (true, vec![(1, 0), (0, 0)], SearchStatus::Found),
+        ];
+
+        for (is_finished, path, status) in cases {
+            let result = SearchResult::new(&graph, is_finished, &path, stats);
+
+            assert_eq!(result.status, status, "{} with {:?}", is_finished, path);
+            assert_eq!((result.stats, result.path_length), (stats, path.len()), "{} with {:?}", is_finished, path);
+        }
+    }
+
+    #[test]
+    fn path_cost_adds_each_step_from_the_start_towards_the_goal() {
+        let mut graph = Graph::Graph::with_topology(Shape::Diagonal(CornerCutting::Allowed).create(6));
+
+        graph.add_portal((0, 0), (5, 5));
+
+        let get_cost = |path: &[(i32, i32)]| SearchResult::new(&graph, true, path, SearchStats::default()).path_cost;
+
+        assert_eq!(get_cost(&[]), 0);
+        assert_eq!(get_cost(&[(3, 3)]), 0);
+
+        // Two sides and a corner, from (0, 0) to (2, 2).
+        assert_eq!(get_cost(&[(2, 2), (1, 1), (1, 0), (0, 0)]), 34);
+
+        // The portal only leads from (0, 0), so only that way round costs a single step.
+        assert_eq!(get_cost(&[(5, 5), (0, 0)]), 10);
+        assert_eq!(get_cost(&[(0, 0), (5, 5)]), 70);
+    }
+
+    #[test]
+    fn absorbing_adds_up_the_work_and_keeps_the_larger_frontier() {
+        let mut stats = SearchStats { expansions: 3, generated: 5, max_frontier: 9, ..Default::default() };
+
+        stats.absorb(&SearchStats { expansions: 4, generated: 6, max_frontier: 7, ..Default::default() });
+
+        assert_eq!((stats.expansions, stats.generated, stats.max_frontier), (7, 11, 9));
+
+        stats.absorb(&SearchStats { max_frontier: 12, ..Default::default() });
+
+        assert_eq!(stats.max_frontier, 12);
+    }
+}
@@ -0,0 +1,90 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use priority_queue::PriorityQueue;
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::Maze;
+
+use super::Graph;
+use super::Node::Coords;
+use super::solver::Solver;
+use super::topology::Shape;
+
+// A grid with each wall knocked through at random, both ways.
+pub fn random_grid(rng: &mut StdRng, shape: Shape, size: i32, open_chance: f64) -> Graph::Graph {
+    let mut graph = Graph::Graph::with_topology(shape.create(size));
+
+    let topology = shape.create(size);
+
+    for node_coords in graph.get_nodes() {
+        for neighbor_coords in graph.get_wall_neighbors(node_coords) {
+            if topology.get_index(neighbor_coords) < topology.get_index(node_coords) || !rng.gen_bool(open_chance) { continue; }
+
+            graph.add_connection(node_coords, neighbor_coords);
+            graph.add_connection(neighbor_coords, node_coords);
+        }
+    }
+
+    return graph;
+}
+
+pub fn generate_maze(size: i32) -> Maze::Maze {
+    let mut maze = Maze::Maze::new(size);
+
+    while !maze.is_finished() {
+        maze.generate_maze();
+    }
+
+    return maze;
+}
+
+pub fn random_coords(rng: &mut StdRng, graph: &Graph::Graph) -> Coords {
+    let topology = graph.get_topology();
+
+    return topology.get_coords(rng.gen_range(0..topology.get_cell_count()));
+}
+
+pub fn solve(solver: &mut dyn Solver, graph: &Graph::Graph, start_coords: Coords, goal_coords: Coords) -> Vec<Coords> {
+    solver.set_metric(graph.get_metric());
+    solver.initialize(start_coords, goal_coords);
+
+    while !solver.is_finished() {
+        solver.generate_path(graph);
+    }
+
+    return solver.get_path();
+}
+
+// The cost of a path running from the goal back to the start, after checking every step is open.
+pub fn get_path_cost(graph: &Graph::Graph, path: &[Coords]) -> i32 {
+    for step in path.windows(2) {
+        assert!(graph.get_connections(step[1]).contains(&step[0]), "{:?} to {:?} is not open", step[1], step[0]);
+    }
+
+    return path.windows(2).map(|step| graph.get_step_cost(step[1], step[0])).sum();
+}
+
+// The cheapest cost from the start to the goal, found the plainest way there is.
+pub fn dijkstra(graph: &Graph::Graph, start_coords: Coords, goal_coords: Coords) -> Option<i32> {
+    let mut open_set: PriorityQueue<Coords, Reverse<i32>> = PriorityQueue::new();
+    let mut g_score: HashMap<Coords, i32> = HashMap::from([(start_coords, 0)]);
+
+    open_set.push(start_coords, Reverse(0));
+
+    while let Some((node_coords, Reverse(g))) = open_set.pop() {
+        if node_coords == goal_coords { return Some(g); }
+
+        for connection_coords in graph.get_connections(node_coords) {
+            let tentative_g_score = g + graph.get_step_cost(node_coords, connection_coords);
+
+            if tentative_g_score >= *g_score.get(&connection_coords).unwrap_or(&i32::MAX) { continue; }
+
+            g_score.insert(connection_coords, tentative_g_score);
+
+            open_set.push(connection_coords, Reverse(tentative_g_score));
+        }
+    }
+
+    return None;
+}
@@ -0,0 +1,403 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use priority_queue::PriorityQueue;
+
+use super::Graph;
+use super::Node::Coords;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+// A cell and where the gates' schedules stand when the search is there. Once every schedule
+// has settled they repeat, so later timesteps fold onto the ones a period earlier.
+type State = (Coords, usize);
+
+// A* over cells and timesteps where a move can also be a wait in place, so a route can hold
+// back until a gate opens.
+pub struct TimedSearch {
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    period: usize,
+    settle_time: usize,
+
+    open_set: PriorityQueue<State, (Reverse<i32>, i32, i64)>,
+    came_from: HashMap<State, State>,
+    closed_set: Vec<Coords>,
+    g_score: HashMap<State, i32>,
+    // The actual timestep each state was reached at, which folding leaves out.
+    times: HashMap<State, usize>,
+
+    push_count: i64,
+
+    metric: Metric,
+
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl TimedSearch {
+    pub fn new() -> TimedSearch {
+        return TimedSearch {
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            period: 1,
+            settle_time: 0,
+            open_set: PriorityQueue::new(),
+            came_from: HashMap::new(),
+            closed_set: vec![],
+            g_score: HashMap::new(),
+            times: HashMap::new(),
+            push_count: 0,
+            metric: Metric::default(),
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn get_greatest_common_divisor(a: usize, b: usize) -> usize {
+        if b == 0 { return a; }
+
+        return TimedSearch::get_greatest_common_divisor(b, a % b);
+    }
+
+    fn fold(&self, time: usize) -> usize {
+        if time < self.settle_time { return time; }
+
+        return self.settle_time + (time - self.settle_time) % self.period;
+    }
+
+    fn add_to_open_set(&mut self, state: State, g: i32) {
+        let f = g + self.metric.get_distance(state.0, self.goal_coords);
+
+        self.push_count += 1;
+
+        self.open_set.push(state, (Reverse(f), g, -self.push_count));
+
+        self.stats.record_generated(1);
+        self.stats.record_frontier(self.open_set.len());
+    }
+
+    fn reconstruct_path(&mut self, goal_state: State) {
+        let mut state = goal_state;
+
+        self.path.push(state.0);
+
+        while let Some(parent_state) = self.came_from.get(&state) {
+            state = *parent_state;
+
+            self.path.push(state.0);
+        }
+    }
+}
+
+impl Default for TimedSearch {
+    fn default() -> TimedSearch {
+        return TimedSearch::new();
+    }
+}
+
+impl Solver for TimedSearch {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.open_set = PriorityQueue::new();
+        self.came_from = HashMap::new();
+        self.closed_set = vec![];
+        self.g_score = HashMap::new();
+        self.times = HashMap::new();
+        self.push_count = 0;
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        // The schedules are only known once the graph is, so the search is set up on the first call.
+        if self.g_score.is_empty() {
+            let gates = graph.get_gates();
+
+            self.period = gates.iter().fold(1, |period, (_, _, schedule)| {
+                let gate_period = schedule.get_period();
+
+                return (period / TimedSearch::get_greatest_common_divisor(period, gate_period)).saturating_mul(gate_period);
+            });
+
+            self.settle_time = gates.iter().map(|(_, _, schedule)| schedule.get_settle_time()).max().unwrap_or(0);
+
+            let start_state = (self.start_coords, 0);
+
+            self.g_score.insert(start_state, 0);
+            self.times.insert(start_state, 0);
+            self.add_to_open_set(start_state, 0);
+        }
+
+        let opt_state = self.open_set.pop();
+
+        if opt_state.is_none() {
+            self.is_finished = true;
+
+            return;
+        }
+
+        let state = opt_state.unwrap().0;
+
+        self.current_coords = state.0;
+
+        if state.0 == self.goal_coords {
+            self.reconstruct_path(state);
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        self.stats.record_expansion();
+        self.closed_set.push(state.0);
+
+        let (g, time) = (self.g_score[&state], self.times[&state]);
+
+        let mut moves: Vec<(Coords, i32)> = vec![(state.0, graph.get_topology().get_unit_cost())];
+
+        for connection_coords in graph.get_connections(state.0) {
+            if graph.is_open_at(state.0, connection_coords, time) {
+                moves.push((connection_coords, graph.get_step_cost(state.0, connection_coords)));
+            }
+        }
+
+        for (next_coords, cost) in moves {
+            let next_state = (next_coords, self.fold(time + 1));
+
+            let tentative_g_score = g + cost;
+
+            if tentative_g_score >= *self.g_score.get(&next_state).unwrap_or(&i32::MAX) { continue; }
+
+            self.came_from.insert(next_state, state);
+            self.g_score.insert(next_state, tentative_g_score);
+            self.times.insert(next_state, time + 1);
+
+            self.add_to_open_set(next_state, tentative_g_score);
+        }
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        let mut nodes: Vec<Coords> = vec![];
+
+        for (state, _) in &self.open_set {
+            nodes.push(state.0);
+        }
+
+        return nodes;
+    }
+
+    fn get_closed_set(&self) -> Vec<Coords> {
+        return self.closed_set.clone();
+    }
+
+    fn get_came_from(&self) -> Vec<(Coords, Coords)> {
+        return self.came_from.iter()
+            .filter(|(state, parent_state)| state.0 != parent_state.0)
+            .map(|(state, parent_state)| (state.0, parent_state.0))
+            .collect();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::TimedSearch;
+    use crate::Pathfinding::Graph::Graph;
+    use crate::Pathfinding::Node::Coords;
+    use crate::Pathfinding::schedule::Schedule;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::Shape;
+
+    fn open_corridor(graph: &mut Graph, cells: &[Coords]) {
+        for step in cells.windows(2) {
+            graph.add_connection(step[0], step[1]);
+            graph.add_connection(step[1], step[0]);
+        }
+    }
+
+    // The earliest timestep the goal can be stood on, found by stepping every cell reachable at each timestep.
+    fn get_arrival_time(graph: &Graph, start_coords: Coords, goal_coords: Coords, horizon: usize) -> Option<usize> {
+        let mut reachable: HashSet<Coords> = HashSet::from([start_coords]);
+
+        for time in 0..=horizon {
+            if reachable.contains(&goal_coords) { return Some(time); }
+
+            let mut next_reachable = reachable.clone();
+
+            for node_coords in &reachable {
+                for connection_coords in graph.get_connections(*node_coords) {
+                    if graph.is_open_at(*node_coords, connection_coords, time) {
+                        next_reachable.insert(connection_coords);
+                    }
+                }
+            }
+
+            reachable = next_reachable;
+        }
+
+        return None;
+    }
+
+    #[test]
+    fn waits_at_a_closed_periodic_gate() {
+        let mut graph = Graph::new(3);
+
+        open_corridor(&mut graph, &[(0, 0), (1, 0), (2, 0)]);
+
+        // Open only at timesteps 3, 8, 13 and so on.
+        graph.add_gate((0, 0), (1, 0), Schedule::Periodic { period: 5, open: 1, offset: 2 });
+
+        let path = testing::solve(&mut TimedSearch::new(), &graph, (0, 0), (2, 0));
+
+        assert_eq!(path, vec![(2, 0), (1, 0), (0, 0), (0, 0), (0, 0), (0, 0)]);
+    }
+
+    #[test]
+    fn bridges_that_close_for_good_are_unreachable_once_shut() {
+        let mut graph = Graph::new(3);
+
+        open_corridor(&mut graph, &[(0, 2), (0, 1), (0, 0), (1, 0)]);
+
+        graph.add_gate((0, 0), (1, 0), Schedule::Window { from: 0, until: 2 });
+
+        let path = testing::solve(&mut TimedSearch::new(), &graph, (0, 1), (1, 0));
+
+        assert_eq!(path, vec![(1, 0), (0, 0), (0, 1)]);
+
+        // Two steps away the bridge has already closed by the time it is reached.
+        let mut solver = TimedSearch::new();
+
+        let path = testing::solve(&mut solver, &graph, (0, 2), (1, 0));
+
+        assert!(path.is_empty());
+        assert!(solver.is_finished());
+    }
+
+    #[test]
+    fn folds_timesteps_onto_the_same_gate_states() {
+        let mut graph = Graph::new(4);
+
+        let schedules = [
+            Schedule::Periodic { period: 4, open: 1, offset: 3 },
+            Schedule::Periodic { period: 6, open: 2, offset: 0 },
+            Schedule::Window { from: 2, until: 7 },
+        ];
+
+        for (index, schedule) in schedules.iter().enumerate() {
+            graph.add_gate((index as i32, 0), (index as i32, 1), *schedule);
+        }
+
+        assert_eq!(schedules.map(|schedule| (schedule.get_period(), schedule.get_settle_time())), [(4, 0), (6, 0), (1, 7)]);
+
+        let mut solver = TimedSearch::new();
+
+        solver.initialize((0, 0), (3, 3));
+        solver.generate_path(&graph);
+
+        assert_eq!((solver.period, solver.settle_time), (12, 7));
+
+        assert_eq!([0, 6, 7, 18, 19, 31].map(|time| solver.fold(time)), [0, 6, 7, 18, 7, 7]);
+
+        for time in 0..100 {
+            for schedule in schedules {
+                assert_eq!(schedule.is_open(time), schedule.is_open(solver.fold(time)), "{:?} at {}", schedule, time);
+            }
+        }
+    }
+
+    #[test]
+    fn arrives_as_early_as_stepping_through_every_timestep_does() {
+        for seed in 0..80 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let size = rng.gen_range(2..7);
+
+            let mut graph = testing::random_grid(&mut rng, Shape::Square, size, 0.7);
+
+            let mut horizon = 1;
+
+            for _ in 0..rng.gen_range(1..6) {
+                let node_coords = testing::random_coords(&mut rng, &graph);
+                let neighbors = graph.get_wall_neighbors(node_coords);
+                let neighbor_coords = neighbors[rng.gen_range(0..neighbors.len())];
+
+                let schedule = if rng.gen_bool(0.3) {
+                    Schedule::Window { from: rng.gen_range(0..6), until: rng.gen_range(6..12) }
+                } else {
+                    let period = rng.gen_range(2..5);
+
+                    Schedule::Periodic { period, open: rng.gen_range(1..period), offset: rng.gen_range(0..period) }
+                };
+
+                graph.add_gate(node_coords, neighbor_coords, schedule);
+
+                horizon = horizon * schedule.get_period() + schedule.get_settle_time();
+            }
+
+            // Every cell at every point in the cycle, which no earliest route has to pass twice.
+            horizon *= (size * size) as usize;
+
+            let start_coords = testing::random_coords(&mut rng, &graph);
+            let goal_coords = testing::random_coords(&mut rng, &graph);
+
+            let path = testing::solve(&mut TimedSearch::new(), &graph, start_coords, goal_coords);
+
+            let opt_arrival_time = get_arrival_time(&graph, start_coords, goal_coords, horizon);
+
+            assert_eq!(path.len().checked_sub(1), opt_arrival_time, "seed {}: {:?} -> {:?} with {:?}", seed, start_coords, goal_coords, graph.get_gates());
+
+            // Run from the start, each step is a wait or a crossing open at the timestep it starts.
+            for (time, step) in path.iter().rev().collect::<Vec<_>>().windows(2).enumerate() {
+                assert!(step[0] == step[1] || graph.is_open_at(*step[0], *step[1], time) && graph.is_connected(*step[0], *step[1]), "seed {}: {:?} at {}", seed, step, time);
+            }
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use std::fmt;
+
+use super::Node;
+use super::Node::Coords;
+
+// The four sides of a square cell followed by its four corners.
+const SQUARE_DIRECTIONS: [Coords; 8] = [(1, 0), (-1, 0), (0, -1), (0, 1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+// The two sides of a brick followed by the two bricks above it and the two below. Odd rows sit half
+// a brick to the right, so which columns are above and below depends on the row.
+const EVEN_BRICK_DIRECTIONS: [Coords; 6] = [(1, 0), (-1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)];
+const ODD_BRICK_DIRECTIONS: [Coords; 6] = [(1, 0), (-1, 0), (0, -1), (1, -1), (0, 1), (1, 1)];
+
+// Step costs on grids with diagonals, scaled so a diagonal step costs close to sqrt(2) orthogonal ones.
+pub const ORTHOGONAL_COST: i32 = 10;
+pub const DIAGONAL_COST: i32 = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CornerCutting {
+    // A diagonal step may slip past a corner as long as one of the two orthogonal routes around it is open.
+    Allowed,
+    // A diagonal step is blocked if either orthogonal route around the corner is.
+    Disallowed,
+}
+
+// The grids a maze can be laid out on, in the order the app cycles through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Square,
+    Diagonal(CornerCutting),
+    Brick,
+}
+
+impl Shape {
+    pub fn next(&self) -> Shape {
+        match self {
+            Shape::Square => return Shape::Diagonal(CornerCutting::Allowed),
+            Shape::Diagonal(CornerCutting::Allowed) => return Shape::Diagonal(CornerCutting::Disallowed),
+            Shape::Diagonal(CornerCutting::Disallowed) => return Shape::Brick,
+            Shape::Brick => return Shape::Square,
+        }
+    }
+
+    // Every shape labels its cells by column and row, so any of them can take over another's walls.
+    pub fn create(&self, size: i32) -> Box<dyn Topology> {
+        match self {
+            Shape::Square => return Box::new(SquareTopology::new(size)),
+            Shape::Diagonal(corner_cutting) => return Box::new(SquareTopology::with_diagonals(size, *corner_cutting)),
+            Shape::Brick => return Box::new(BrickTopology::new(size)),
+        }
+    }
+}
+
+// The shape of a grid: which cells exist, which cells border each other, how far apart
+// they are and where the walls between them are drawn. Drawing positions are in cell
+// units, so a square cell is one unit wide.
+pub trait Topology: fmt::Debug {
+    fn get_shape(&self) -> Shape;
+
+    fn get_cell_count(&self) -> usize;
+
+    // Position of a cell in dense storage, or None when the cell is not part of the grid.
+    fn get_index(&self, coords: Coords) -> Option<usize>;
+
+    fn get_coords(&self, index: usize) -> Coords;
+
+    // Offsets to the cells a step can reach. A cell stores its passages as one bit per
+    // direction, so there can be at most eight.
+    fn get_directions(&self, coords: Coords) -> &[Coords];
+
+    // How many of the directions, from the front, cross a wall. The rest cross a corner and
+    // are opened or closed by the graph from the walls around that corner.
+    fn get_wall_count(&self, coords: Coords) -> usize {
+        return self.get_directions(coords).len();
+    }
+
+    // The two cells a step across a corner passes between.
+    fn get_corner_cells(&self, coords: Coords, direction: usize) -> [Coords; 2];
+
+    fn get_corner_cutting(&self) -> Option<CornerCutting> {
+        return None;
+    }
+
+    // The cost of a step between neighbouring cells, and a lower bound on it between any two.
+    fn get_distance(&self, coords: Coords, other_coords: Coords) -> i32;
+
+    // The cost of the cheapest single step, which is also what going through a portal costs.
+    fn get_unit_cost(&self) -> i32 {
+        return 1;
+    }
+
+    fn get_extent(&self) -> (f64, f64);
+
+    // The box a cell is drawn in, as [x, y, width, height].
+    fn get_cell_bounds(&self, coords: Coords) -> [f64; 4];
+
+    // The wall on the side of a cell facing one of its directions, as [x1, y1, x2, y2].
+    fn get_wall(&self, coords: Coords, direction: usize) -> [f64; 4];
+
+    fn locate(&self, position: (f64, f64)) -> Option<Coords>;
+
+    fn clone_box(&self) -> Box<dyn Topology>;
+
+    fn get_direction(&self, coords: Coords, neighbor_coords: Coords) -> Option<usize> {
+        let offset = (neighbor_coords.0 - coords.0, neighbor_coords.1 - coords.1);
+
+        return self.get_directions(coords).iter().position(|direction| *direction == offset);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SquareTopology {
+    size: i32,
+
+    // Some when steps across corners are allowed, with the rule for when they are open.
+    opt_corner_cutting: Option<CornerCutting>,
+}
+
+impl SquareTopology {
+    pub fn new(size: i32) -> SquareTopology {
+        return SquareTopology { size, opt_corner_cutting: None };
+    }
+
+    pub fn with_diagonals(size: i32, corner_cutting: CornerCutting) -> SquareTopology {
+        return SquareTopology { size, opt_corner_cutting: Some(corner_cutting) };
+    }
+}
+
+impl Topology for SquareTopology {
+    fn get_shape(&self) -> Shape {
+        match self.opt_corner_cutting {
+            None => return Shape::Square,
+            Some(corner_cutting) => return Shape::Diagonal(corner_cutting),
+        }
+    }
+
+    fn get_cell_count(&self) -> usize {
+        return (self.size * self.size) as usize;
+    }
+
+    fn get_index(&self, coords: Coords) -> Option<usize> {
+        if coords.0 < 0 || coords.0 >= self.size || coords.1 < 0 || coords.1 >= self.size { return None; }
+
+        return Some((coords.1 * self.size + coords.0) as usize);
+    }
+
+    fn get_coords(&self, index: usize) -> Coords {
+        let index = index as i32;
+
+        return (index % self.size, index / self.size);
+    }
+
+    fn get_directions(&self, _coords: Coords) -> &[Coords] {
+        match self.opt_corner_cutting {
+            None => return &SQUARE_DIRECTIONS[..4],
+            Some(_) => return &SQUARE_DIRECTIONS,
+        }
+    }
+
+    fn get_wall_count(&self, _coords: Coords) -> usize {
+        return 4;
+    }
+
+    fn get_corner_cells(&self, coords: Coords, direction: usize) -> [Coords; 2] {
+        let offset = SQUARE_DIRECTIONS[direction];
+
+        return [(coords.0 + offset.0, coords.1), (coords.0, coords.1 + offset.1)];
+    }
+
+    fn get_corner_cutting(&self) -> Option<CornerCutting> {
+        return self.opt_corner_cutting;
+    }
+
+    fn get_unit_cost(&self) -> i32 {
+        match self.opt_corner_cutting {
+            None => return 1,
+            Some(_) => return ORTHOGONAL_COST,
+        }
+    }
+
+    // Octile distance when diagonal steps are allowed, Manhattan distance otherwise.
+    fn get_distance(&self, coords: Coords, other_coords: Coords) -> i32 {
+        if self.opt_corner_cutting.is_none() {
+            return Node::Node::get_distance_between(coords, other_coords);
+        }
+
+        let (dx, dy) = (i32::abs(coords.0 - other_coords.0), i32::abs(coords.1 - other_coords.1));
+
+        return ORTHOGONAL_COST * i32::max(dx, dy) + (DIAGONAL_COST - ORTHOGONAL_COST) * i32::min(dx, dy);
+    }
+
+    fn get_extent(&self) -> (f64, f64) {
+        return (f64::from(self.size), f64::from(self.size));
+    }
+
+    fn get_cell_bounds(&self, coords: Coords) -> [f64; 4] {
+        return [f64::from(coords.0), f64::from(coords.1), 1.0, 1.0];
+    }
+
+    fn get_wall(&self, coords: Coords, direction: usize) -> [f64; 4] {
+        let (x, y) = (f64::from(coords.0), f64::from(coords.1));
+
+        match SQUARE_DIRECTIONS[direction] {
+            (1, 0) => return [x + 1.0, y, x + 1.0, y + 1.0],
+            (-1, 0) => return [x, y, x, y + 1.0],
+            (0, -1) => return [x, y, x + 1.0, y],
+            _ => return [x, y + 1.0, x + 1.0, y + 1.0],
+        }
+    }
+
+    fn locate(&self, position: (f64, f64)) -> Option<Coords> {
+        let coords = (position.0.floor() as i32, position.1.floor() as i32);
+
+        return self.get_index(coords).map(|_| coords);
+    }
+
+    fn clone_box(&self) -> Box<dyn Topology> {
+        return Box::new(*self);
+    }
+}
+
+// Rows of bricks laid in a running bond, so every cell borders six others like a hexagon does
+// while its walls stay on a square grid's lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BrickTopology {
+    size: i32,
+}
+
+impl BrickTopology {
+    pub fn new(size: i32) -> BrickTopology {
+        return BrickTopology { size };
+    }
+
+    fn get_shift(row: i32) -> f64 {
+        if row.rem_euclid(2) == 1 { return 0.5; }
+
+        return 0.0;
+    }
+
+    // Cube coordinates of the hexagon the brick stands in for.
+    fn to_cube(coords: Coords) -> (i32, i32) {
+        return (coords.0 - (coords.1 - coords.1.rem_euclid(2)) / 2, coords.1);
+    }
+}
+
+impl Topology for BrickTopology {
+    fn get_shape(&self) -> Shape {
+        return Shape::Brick;
+    }
+
+    fn get_cell_count(&self) -> usize {
+        return (self.size * self.size) as usize;
+    }
+
+    fn get_index(&self, coords: Coords) -> Option<usize> {
+        if coords.0 < 0 || coords.0 >= self.size || coords.1 < 0 || coords.1 >= self.size { return None; }
+
+        return Some((coords.1 * self.size + coords.0) as usize);
+    }
+
+    fn get_coords(&self, index: usize) -> Coords {
+        let index = index as i32;
+
+        return (index % self.size, index / self.size);
+    }
+
+    fn get_directions(&self, coords: Coords) -> &[Coords] {
+        if coords.1.rem_euclid(2) == 1 { return &ODD_BRICK_DIRECTIONS; }
+
+        return &EVEN_BRICK_DIRECTIONS;
+    }
+
+    // Every step crosses a wall, so no corner is ever asked for.
+    fn get_corner_cells(&self, coords: Coords, _direction: usize) -> [Coords; 2] {
+        return [coords, coords];
+    }
+
+    // Steps on a hexagonal grid, which is exact between any two cells.
+    fn get_distance(&self, coords: Coords, other_coords: Coords) -> i32 {
+        let (q, r) = BrickTopology::to_cube(coords);
+        let (other_q, other_r) = BrickTopology::to_cube(other_coords);
+
+        let (dq, dr) = (q - other_q, r - other_r);
+
+        return (i32::abs(dq) + i32::abs(dr) + i32::abs(dq + dr)) / 2;
+    }
+
+    fn get_extent(&self) -> (f64, f64) {
+        return (f64::from(self.size) + 0.5, f64::from(self.size));
+    }
+
+    fn get_cell_bounds(&self, coords: Coords) -> [f64; 4] {
+        return [f64::from(coords.0) + BrickTopology::get_shift(coords.1), f64::from(coords.1), 1.0, 1.0];
+    }
+
+    // Side walls are whole, while the ones above and below are the half a brick shared with that neighbour.
+    fn get_wall(&self, coords: Coords, direction: usize) -> [f64; 4] {
+        let offset = self.get_directions(coords)[direction];
+
+        let left = f64::from(coords.0) + BrickTopology::get_shift(coords.1);
+        let y = f64::from(coords.1);
+
+        match offset.1 {
+            0 if offset.0 > 0 => return [left + 1.0, y, left + 1.0, y + 1.0],
+            0 => return [left, y, left, y + 1.0],
+            _ => {
+                let neighbor_left = f64::from(coords.0 + offset.0) + BrickTopology::get_shift(coords.1 + offset.1);
+
+                let wall_y = if offset.1 > 0 { y + 1.0 } else { y };
+
+                return [f64::max(left, neighbor_left), wall_y, f64::min(left, neighbor_left) + 1.0, wall_y];
+            },
+        }
+    }
+
+    fn locate(&self, position: (f64, f64)) -> Option<Coords> {
+        let row = position.1.floor() as i32;
+
+        let coords = ((position.0 - BrickTopology::get_shift(row)).floor() as i32, row);
+
+        return self.get_index(coords).map(|_| coords);
+    }
+
+    fn clone_box(&self) -> Box<dyn Topology> {
+        return Box::new(*self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, VecDeque};
+
+    use super::{CornerCutting, Shape, Topology};
+    use crate::Pathfinding::Node::Coords;
+
+    const SHAPES: [Shape; 4] = [Shape::Square, Shape::Diagonal(CornerCutting::Allowed), Shape::Diagonal(CornerCutting::Disallowed), Shape::Brick];
+
+    fn get_cells(topology: &dyn Topology) -> Vec<Coords> {
+        return (0..topology.get_cell_count()).map(|index| topology.get_coords(index)).collect();
+    }
+
+    fn get_neighbor(coords: Coords, offset: Coords) -> Coords {
+        return (coords.0 + offset.0, coords.1 + offset.1);
+    }
+
+    #[test]
+    fn indices_and_coordinates_agree() {
+        for shape in SHAPES {
+            let topology = shape.create(7);
+
+            for (index, coords) in get_cells(topology.as_ref()).into_iter().enumerate() {
+                assert_eq!(topology.get_index(coords), Some(index), "{:?}", shape);
+            }
+
+            assert_eq!(topology.get_index((-1, 0)), None);
+            assert_eq!(topology.get_index((0, 7)), None);
+            assert_eq!(topology.get_shape(), shape);
+        }
+    }
+
+    #[test]
+    fn neighbours_see_each_other_across_the_same_wall() {
+        for shape in SHAPES {
+            let topology = shape.create(6);
+
+            for coords in get_cells(topology.as_ref()) {
+                for (direction, offset) in topology.get_directions(coords).iter().enumerate() {
+                    let neighbor_coords = get_neighbor(coords, *offset);
+
+                    if topology.get_index(neighbor_coords).is_none() { continue; }
+
+                    let opt_back = topology.get_direction(neighbor_coords, coords);
+
+                    assert!(opt_back.is_some(), "{:?}: {:?} to {:?} has no way back", shape, coords, neighbor_coords);
+
+                    assert_eq!(topology.get_distance(coords, neighbor_coords), topology.get_distance(neighbor_coords, coords));
+
+                    if direction >= topology.get_wall_count(coords) { continue; }
+
+                    let wall = topology.get_wall(coords, direction);
+                    let other_wall = topology.get_wall(neighbor_coords, opt_back.unwrap());
+
+                    assert_eq!(wall, other_wall, "{:?}: {:?} and {:?} draw their wall apart", shape, coords, neighbor_coords);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_cell_is_found_where_it_is_drawn() {
+        for shape in SHAPES {
+            let topology = shape.create(5);
+
+            for coords in get_cells(topology.as_ref()) {
+                let [x, y, width, height] = topology.get_cell_bounds(coords);
+
+                assert_eq!(topology.locate((x + width / 2.0, y + height / 2.0)), Some(coords), "{:?}", shape);
+            }
+        }
+    }
+
+    #[test]
+    fn brick_distances_count_the_steps_between_cells() {
+        let topology = Shape::Brick.create(9);
+
+        for origin_coords in [(0, 0), (4, 4), (8, 3), (3, 8)] {
+            let mut steps: HashMap<Coords, i32> = HashMap::from([(origin_coords, 0)]);
+            let mut queue: VecDeque<Coords> = VecDeque::from([origin_coords]);
+
+            while let Some(coords) = queue.pop_front() {
+                for offset in topology.get_directions(coords) {
+                    let neighbor_coords = get_neighbor(coords, *offset);
+
+                    if topology.get_index(neighbor_coords).is_none() || steps.contains_key(&neighbor_coords) { continue; }
+
+                    steps.insert(neighbor_coords, steps[&coords] + 1);
+
+                    queue.push_back(neighbor_coords);
+                }
+            }
+
+            for (coords, count) in steps {
+                assert_eq!(topology.get_distance(origin_coords, coords), count, "{:?} to {:?}", origin_coords, coords);
+            }
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use super::Graph;
+use super::Node::Coords;
+use super::astar::Astar;
+use super::metric::Metric;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+const INFINITY: i32 = i32::MAX / 4;
+
+// Above this many waypoints the exact search gets too slow and the order is improved
+// heuristically instead.
+const EXACT_LIMIT: usize = 10;
+
+pub struct TourPlanner {
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+
+    // The start comes first, then the goal and the extra waypoints in any order.
+    points: Vec<Coords>,
+    extra_waypoints: Vec<Coords>,
+    distances: Vec<Vec<i32>>,
+    legs: Vec<Vec<Vec<Coords>>>,
+    next_pair: usize,
+
+    order: Vec<usize>,
+    planner: Astar,
+
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl TourPlanner {
+    pub fn new() -> TourPlanner {
+        return TourPlanner {
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            points: vec![],
+            extra_waypoints: vec![],
+            distances: vec![],
+            legs: vec![],
+            next_pair: 0,
+            order: vec![],
+            planner: Astar::new(),
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn measure_next_pair(&mut self, graph: &Graph::Graph) {
+        let count = self.points.len();
+
+        let (from, to) = (self.next_pair / count, self.next_pair % count);
+
+        self.next_pair += 1;
+
+        if from == to { return; }
+
+        self.current_coords = self.points[to];
+
+        self.planner.initialize(self.points[from], self.points[to]);
+
+        while !self.planner.is_finished() {
+            self.planner.generate_path(graph);
+        }
+
+        self.stats.absorb(&self.planner.get_stats());
+
+        let mut leg = self.planner.get_path();
+
+        leg.reverse();
+
+        self.distances[from][to] = if leg.is_empty() { INFINITY } else { leg.windows(2).map(|step| graph.get_step_cost(step[0], step[1])).sum() };
+        self.legs[from][to] = leg;
+    }
+
+    fn get_cost(&self, order: &[usize]) -> i32 {
+        let mut cost = 0;
+
+        let mut previous = 0;
+
+        for point in order {
+            cost = i32::min(cost + self.distances[previous][*point], INFINITY);
+
+            previous = *point;
+        }
+
+        return cost;
+    }
+
+    // Held-Karp over subsets of waypoints, always leaving from the start.
+    fn solve_exact(&self) -> Vec<usize> {
+        let waypoint_count = self.points.len() - 1;
+
+        let subset_count = 1 << waypoint_count;
+
+        let mut best = vec![vec![INFINITY; waypoint_count]; subset_count];
+        let mut parent = vec![vec![usize::MAX; waypoint_count]; subset_count];
+
+        for last in 0..waypoint_count {
+            best[1 << last][last] = self.distances[0][last + 1];
+        }
+
+        for subset in 1..subset_count {
+            for last in 0..waypoint_count {
+                if subset & (1 << last) == 0 || best[subset][last] >= INFINITY { continue; }
+
+                for next in 0..waypoint_count {
+                    if subset & (1 << next) != 0 { continue; }
+
+                    let cost = best[subset][last] + self.distances[last + 1][next + 1];
+
+                    let next_subset = subset | (1 << next);
+
+                    if cost < best[next_subset][next] {
+                        best[next_subset][next] = cost;
+                        parent[next_subset][next] = last;
+                    }
+                }
+            }
+        }
+
+        let full = subset_count - 1;
+
+        let mut last = (0..waypoint_count).min_by_key(|last| best[full][*last]).unwrap();
+
+        if best[full][last] >= INFINITY { return vec![]; }
+
+        let mut order: Vec<usize> = vec![];
+        let mut subset = full;
+
+        loop {
+            order.push(last + 1);
+
+            let previous = parent[subset][last];
+
+            subset &= !(1 << last);
+
+            if previous == usize::MAX { break; }
+
+            last = previous;
+        }
+
+        order.reverse();
+
+        return order;
+    }
+
+    // Nearest neighbour for a first tour, then 2-opt reversals until none of them help.
+    fn solve_heuristic(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = vec![];
+        let mut remaining: Vec<usize> = (1..self.points.len()).collect();
+
+        let mut previous = 0;
+
+        while !remaining.is_empty() {
+            let index = (0..remaining.len()).min_by_key(|index| self.distances[previous][remaining[*index]]).unwrap();
+
+            previous = remaining.remove(index);
+
+            order.push(previous);
+        }
+
+        let mut cost = self.get_cost(&order);
+
+        let mut is_improved = true;
+
+        while is_improved {
+            is_improved = false;
+
+            for i in 0..order.len() {
+                for j in i + 1..order.len() {
+                    order[i..=j].reverse();
+
+                    let candidate_cost = self.get_cost(&order);
+
+                    if candidate_cost < cost {
+                        cost = candidate_cost;
+                        is_improved = true;
+                    } else {
+                        order[i..=j].reverse();
+                    }
+                }
+            }
+        }
+
+        if cost >= INFINITY { return vec![]; }
+
+        return order;
+    }
+
+    fn build_path(&mut self) {
+        // Some waypoint could not be reached, so there is no tour.
+        if self.order.is_empty() { return; }
+
+        let mut route: Vec<Coords> = vec![self.start_coords];
+
+        for leg in self.get_legs() {
+            route.extend(leg.into_iter().skip(1));
+        }
+
+        route.reverse();
+
+        self.path = route;
+    }
+}
+
+impl Default for TourPlanner {
+    fn default() -> TourPlanner {
+        return TourPlanner::new();
+    }
+}
+
+impl Solver for TourPlanner {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+
+        self.points = vec![start_coords, goal_coords];
+
+        for waypoint_coords in &self.extra_waypoints {
+            if !self.points.contains(waypoint_coords) {
+                self.points.push(*waypoint_coords);
+            }
+        }
+
+        let count = self.points.len();
+
+        self.distances = vec![vec![0; count]; count];
+        self.legs = vec![vec![vec![]; count]; count];
+        self.next_pair = 0;
+        self.order = vec![];
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        let count = self.points.len();
+
+        if self.next_pair < count * count {
+            self.measure_next_pair(graph);
+
+            return;
+        }
+
+        self.order = if count - 1 <= EXACT_LIMIT { self.solve_exact() } else { self.solve_heuristic() };
+
+        self.build_path();
+
+        self.is_finished = true;
+    }
+
+    fn set_waypoints(&mut self, waypoints: Vec<Coords>) {
+        self.extra_waypoints = waypoints;
+    }
+
+    fn get_waypoints(&self) -> Vec<Coords> {
+        if self.order.is_empty() {
+            return self.points.iter().skip(1).copied().collect();
+        }
+
+        return self.order.iter().map(|point| self.points[*point]).collect();
+    }
+
+    fn get_legs(&self) -> Vec<Vec<Coords>> {
+        let mut legs: Vec<Vec<Coords>> = vec![];
+
+        let mut previous = 0;
+
+        for point in &self.order {
+            legs.push(self.legs[previous][*point].clone());
+
+            previous = *point;
+        }
+
+        return legs;
+    }
+
+    fn set_metric(&mut self, metric: Metric) {
+        self.planner.set_metric(metric);
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::{INFINITY, TourPlanner};
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::{CornerCutting, Shape};
+
+    // Tries every order of the remaining points, leaving from the previous one.
+    fn get_cheapest_cost(table: &[Vec<Option<i32>>], previous: usize, remaining: &mut Vec<usize>) -> Option<i32> {
+        if remaining.is_empty() { return Some(0); }
+
+        let mut opt_best: Option<i32> = None;
+
+        for index in 0..remaining.len() {
+            let point = remaining.remove(index);
+
+            if let (Some(step), Some(rest)) = (table[previous][point], get_cheapest_cost(table, point, remaining)) {
+                opt_best = Some(i32::min(opt_best.unwrap_or(i32::MAX), step + rest));
+            }
+
+            remaining.insert(index, point);
+        }
+
+        return opt_best;
+    }
+
+    #[test]
+    fn exact_tours_match_every_order_and_heuristic_tours_are_never_cheaper() {
+        let shapes = [Shape::Square, Shape::Diagonal(CornerCutting::Allowed), Shape::Diagonal(CornerCutting::Disallowed), Shape::Brick];
+
+        for seed in 0..60 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let size = rng.gen_range(3..9);
+
+            let graph = testing::random_grid(&mut rng, shapes[seed as usize % 4], size, [0.7, 0.9][seed as usize % 2]);
+
+            let (start_coords, goal_coords) = (testing::random_coords(&mut rng, &graph), testing::random_coords(&mut rng, &graph));
+
+            let waypoint_count = rng.gen_range(0..6);
+            let waypoints: Vec<_> = (0..waypoint_count).map(|_| testing::random_coords(&mut rng, &graph)).collect();
+
+            let mut tour = TourPlanner::new();
+
+            tour.set_waypoints(waypoints);
+
+            let path = testing::solve(&mut tour, &graph, start_coords, goal_coords);
+
+            let points = tour.points.clone();
+
+            let table: Vec<Vec<Option<i32>>> = points.iter()
+                .map(|from| points.iter().map(|to| testing::dijkstra(&graph, *from, *to)).collect())
+                .collect();
+
+            for (from, row) in table.iter().enumerate() {
+                for (to, opt_cost) in row.iter().enumerate() {
+                    assert_eq!(tour.distances[from][to], opt_cost.unwrap_or(INFINITY), "seed {}: {:?} to {:?}", seed, points[from], points[to]);
+                }
+            }
+
+            let opt_cheapest_cost = get_cheapest_cost(&table, 0, &mut (1..points.len()).collect());
+
+            match opt_cheapest_cost {
+                None => assert!(path.is_empty(), "seed {}: a tour was found through an unreachable waypoint", seed),
+                Some(cheapest_cost) => {
+                    assert_eq!(testing::get_path_cost(&graph, &path), cheapest_cost, "seed {}", seed);
+
+                    for point in &points {
+                        assert!(path.contains(point), "seed {}: the tour skips {:?}", seed, point);
+                    }
+
+                    let heuristic_order = tour.solve_heuristic();
+
+                    assert_eq!(heuristic_order.len(), points.len() - 1, "seed {}", seed);
+                    assert!(tour.get_cost(&heuristic_order) >= cheapest_cost, "seed {}", seed);
+                },
+            }
+        }
+    }
+}
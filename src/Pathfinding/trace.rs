@@ -0,0 +1,427 @@
+use std::fs;
+use std::io;
+
+use super::Node::Coords;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceAction {
+    Initialize { start: Coords, goal: Coords },
+    // Each change keeps the value it replaced so a replay can step back through it.
+    Push { coords: Coords, f: i32, previous_f: Option<i32> },
+    Pop { coords: Coords, f: i32 },
+    ScoreUpdate { coords: Coords, g: i32, previous_g: Option<i32> },
+    ParentChange { coords: Coords, parent: Coords, previous_parent: Option<Coords> },
+    PathFound { path: Vec<Coords> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub step: usize,
+    pub action: TraceAction,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchTrace {
+    events: Vec<TraceEvent>,
+    step: usize,
+}
+
+impl SearchTrace {
+    pub fn new() -> SearchTrace {
+        return SearchTrace { events: vec![], step: 0 };
+    }
+
+    pub fn record(&mut self, action: TraceAction) {
+        self.events.push(TraceEvent { step: self.step, action });
+    }
+
+    // Events recorded after this share the next step number.
+    pub fn next_step(&mut self) {
+        self.step += 1;
+    }
+
+    pub fn get_events(&self) -> &[TraceEvent] {
+        return &self.events;
+    }
+
+    pub fn to_json_lines(&self) -> String {
+        let mut lines = String::new();
+
+        for event in &self.events {
+            lines.push_str(&SearchTrace::event_to_json(event));
+            lines.push('\n');
+        }
+
+        return lines;
+    }
+
+    pub fn from_json_lines(text: &str) -> Result<SearchTrace, String> {
+        let mut trace = SearchTrace::new();
+
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() { continue; }
+
+            let event = SearchTrace::event_from_json(line).map_err(|error| format!("line {}: {}", index + 1, error))?;
+
+            trace.step = event.step;
+            trace.events.push(event);
+        }
+
+        return Ok(trace);
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        return fs::write(path, self.to_json_lines());
+    }
+
+    pub fn load(path: &str) -> Result<SearchTrace, String> {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+
+        return SearchTrace::from_json_lines(&text);
+    }
+
+    fn event_to_json(event: &TraceEvent) -> String {
+        let coords = |coords: Coords| format!("[{},{}]", coords.0, coords.1);
+        let optional = |opt_value: Option<String>| opt_value.unwrap_or("null".to_string());
+
+        let fields = match &event.action {
+            TraceAction::Initialize { start, goal } => {
+                format!("\"action\":\"initialize\",\"start\":{},\"goal\":{}", coords(*start), coords(*goal))
+            },
+            TraceAction::Push { coords: node_coords, f, previous_f } => {
+                format!("\"action\":\"push\",\"coords\":{},\"f\":{},\"previous_f\":{}",
+                    coords(*node_coords), f, optional(previous_f.map(|f| f.to_string())))
+            },
+            TraceAction::Pop { coords: node_coords, f } => {
+                format!("\"action\":\"pop\",\"coords\":{},\"f\":{}", coords(*node_coords), f)
+            },
+            TraceAction::ScoreUpdate { coords: node_coords, g, previous_g } => {
+                format!("\"action\":\"score_update\",\"coords\":{},\"g\":{},\"previous_g\":{}",
+                    coords(*node_coords), g, optional(previous_g.map(|g| g.to_string())))
+            },
+            TraceAction::ParentChange { coords: node_coords, parent, previous_parent } => {
+                format!("\"action\":\"parent_change\",\"coords\":{},\"parent\":{},\"previous_parent\":{}",
+                    coords(*node_coords), coords(*parent), optional(previous_parent.map(coords)))
+            },
+            TraceAction::PathFound { path } => {
+                let cells: Vec<String> = path.iter().map(|cell_coords| coords(*cell_coords)).collect();
+
+                format!("\"action\":\"path_found\",\"path\":[{}]", cells.join(","))
+            },
+        };
+
+        return format!("{{\"step\":{},{}}}", event.step, fields);
+    }
+
+    fn event_from_json(line: &str) -> Result<TraceEvent, String> {
+        let object = match Json::parse(line)? {
+            Json::Object(fields) => fields,
+            _ => return Err("expected an object".to_string()),
+        };
+
+        let field = |name: &str| -> Result<&Json, String> {
+            return object.iter().find(|(key, _)| key == name).map(|(_, value)| value).ok_or(format!("missing field {}", name));
+        };
+
+        let number = |name: &str| -> Result<i64, String> { return field(name)?.as_number(); };
+        let coords = |name: &str| -> Result<Coords, String> { return field(name)?.as_coords(); };
+
+        let action = match field("action")? {
+            Json::String(action) => action.as_str(),
+            _ => return Err("action should be a string".to_string()),
+        };
+
+        let action = match action {
+            "initialize" => TraceAction::Initialize { start: coords("start")?, goal: coords("goal")? },
+            "push" => TraceAction::Push {
+                coords: coords("coords")?,
+                f: number("f")? as i32,
+                previous_f: field("previous_f")?.as_optional(|value| value.as_number())?.map(|f| f as i32),
+            },
+            "pop" => TraceAction::Pop { coords: coords("coords")?, f: number("f")? as i32 },
+            "score_update" => TraceAction::ScoreUpdate {
+                coords: coords("coords")?,
+                g: number("g")? as i32,
+                previous_g: field("previous_g")?.as_optional(|value| value.as_number())?.map(|g| g as i32),
+            },
+            "parent_change" => TraceAction::ParentChange {
+                coords: coords("coords")?,
+                parent: coords("parent")?,
+                previous_parent: field("previous_parent")?.as_optional(|value| value.as_coords())?,
+            },
+            "path_found" => match field("path")? {
+                Json::Array(cells) => TraceAction::PathFound { path: cells.iter().map(|cell| cell.as_coords()).collect::<Result<_, _>>()? },
+                _ => return Err("path should be an array".to_string()),
+            },
+            other => return Err(format!("unknown action {}", other)),
+        };
+
+        return Ok(TraceEvent { step: number("step")? as usize, action });
+    }
+}
+
+// Just enough JSON to read traces back in.
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(text: &str) -> Result<Json, String> {
+        let chars: Vec<char> = text.chars().collect();
+
+        let mut position = 0;
+
+        let value = Json::parse_value(&chars, &mut position)?;
+
+        Json::skip_whitespace(&chars, &mut position);
+
+        if position != chars.len() { return Err(format!("unexpected text at {}", position)); }
+
+        return Ok(value);
+    }
+
+    fn skip_whitespace(chars: &[char], position: &mut usize) {
+        while *position < chars.len() && chars[*position].is_whitespace() {
+            *position += 1;
+        }
+    }
+
+    fn expect(chars: &[char], position: &mut usize, expected: char) -> Result<(), String> {
+        Json::skip_whitespace(chars, position);
+
+        if chars.get(*position) != Some(&expected) { return Err(format!("expected '{}' at {}", expected, position)); }
+
+        *position += 1;
+
+        return Ok(());
+    }
+
+    fn parse_value(chars: &[char], position: &mut usize) -> Result<Json, String> {
+        Json::skip_whitespace(chars, position);
+
+        match chars.get(*position) {
+            None => return Err("unexpected end of line".to_string()),
+            Some('n') => {
+                if chars[*position..].iter().take(4).collect::<String>() != "null" { return Err(format!("bad literal at {}", position)); }
+
+                *position += 4;
+
+                return Ok(Json::Null);
+            },
+            Some('"') => {
+                *position += 1;
+
+                let start = *position;
+
+                while *position < chars.len() && chars[*position] != '"' {
+                    *position += 1;
+                }
+
+                if *position == chars.len() { return Err("unterminated string".to_string()); }
+
+                *position += 1;
+
+                return Ok(Json::String(chars[start..*position - 1].iter().collect()));
+            },
+            Some('[') => {
+                *position += 1;
+
+                let mut items: Vec<Json> = vec![];
+
+                Json::skip_whitespace(chars, position);
+
+                if chars.get(*position) == Some(&']') {
+                    *position += 1;
+
+                    return Ok(Json::Array(items));
+                }
+
+                loop {
+                    items.push(Json::parse_value(chars, position)?);
+
+                    Json::skip_whitespace(chars, position);
+
+                    match chars.get(*position) {
+                        Some(',') => *position += 1,
+                        Some(']') => {
+                            *position += 1;
+
+                            return Ok(Json::Array(items));
+                        },
+                        _ => return Err(format!("expected ',' or ']' at {}", position)),
+                    }
+                }
+            },
+            Some('{') => {
+                *position += 1;
+
+                let mut fields: Vec<(String, Json)> = vec![];
+
+                Json::skip_whitespace(chars, position);
+
+                if chars.get(*position) == Some(&'}') {
+                    *position += 1;
+
+                    return Ok(Json::Object(fields));
+                }
+
+                loop {
+                    let key = match Json::parse_value(chars, position)? {
+                        Json::String(key) => key,
+                        _ => return Err(format!("expected a key at {}", position)),
+                    };
+
+                    Json::expect(chars, position, ':')?;
+
+                    fields.push((key, Json::parse_value(chars, position)?));
+
+                    Json::skip_whitespace(chars, position);
+
+                    match chars.get(*position) {
+                        Some(',') => *position += 1,
+                        Some('}') => {
+                            *position += 1;
+
+                            return Ok(Json::Object(fields));
+                        },
+                        _ => return Err(format!("expected ',' or '}}' at {}", position)),
+                    }
+                }
+            },
+            Some(_) => {
+                let start = *position;
+
+                while *position < chars.len() && (chars[*position] == '-' || chars[*position].is_ascii_digit()) {
+                    *position += 1;
+                }
+
+                let digits: String = chars[start..*position].iter().collect();
+
+                return digits.parse::<i64>().map(Json::Number).map_err(|_| format!("bad number at {}", start));
+            },
+        }
+    }
+
+    fn as_number(&self) -> Result<i64, String> {
+        match self {
+            Json::Number(number) => return Ok(*number),
+            _ => return Err("expected a number".to_string()),
+        }
+    }
+
+    fn as_coords(&self) -> Result<Coords, String> {
+        match self {
+            Json::Array(items) if items.len() == 2 => return Ok((items[0].as_number()? as i32, items[1].as_number()? as i32)),
+            _ => return Err("expected a pair of coordinates".to_string()),
+        }
+    }
+
+    fn as_optional<T>(&self, convert: impl Fn(&Json) -> Result<T, String>) -> Result<Option<T>, String> {
+        match self {
+            Json::Null => return Ok(None),
+            value => return convert(value).map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::{SearchTrace, TraceAction};
+
+    // Every action, with options both set and unset and coordinates on either side of zero.
+    fn sample_trace() -> SearchTrace {
+        let mut trace = SearchTrace::new();
+
+        trace.record(TraceAction::Initialize { start: (0, 0), goal: (12, -3) });
+        trace.next_step();
+        trace.record(TraceAction::Push { coords: (1, 0), f: 17, previous_f: None });
+        trace.record(TraceAction::Push { coords: (1, 0), f: 15, previous_f: Some(17) });
+        trace.record(TraceAction::Pop { coords: (-1, 4), f: -2 });
+        trace.next_step();
+        trace.record(TraceAction::ScoreUpdate { coords: (2, 0), g: 3, previous_g: None });
+        trace.record(TraceAction::ScoreUpdate { coords: (2, 0), g: 2, previous_g: Some(3) });
+        trace.record(TraceAction::ParentChange { coords: (2, 0), parent: (1, 0), previous_parent: None });
+        trace.record(TraceAction::ParentChange { coords: (2, 0), parent: (2, 1), previous_parent: Some((1, 0)) });
+        trace.next_step();
+        trace.record(TraceAction::PathFound { path: vec![] });
+        trace.record(TraceAction::PathFound { path: vec![(2, 0), (1, 0), (0, 0)] });
+
+        return trace;
+    }
+
+    #[test]
+    fn every_action_survives_a_round_trip() {
+        let trace = sample_trace();
+
+        let text = trace.to_json_lines();
+
+        assert_eq!(text.lines().count(), trace.get_events().len());
+        assert_eq!(SearchTrace::from_json_lines(&text), Ok(trace));
+    }
+
+    #[test]
+    fn writes_one_object_per_line() {
+        let lines: Vec<String> = sample_trace().to_json_lines().lines().map(|line| line.to_string()).collect();
+
+        assert_eq!(lines[0], r#"{"step":0,"action":"initialize","start":[0,0],"goal":[12,-3]}"#);
+        assert_eq!(lines[2], r#"{"step":1,"action":"push","coords":[1,0],"f":15,"previous_f":17}"#);
+        assert_eq!(lines[6], r#"{"step":2,"action":"parent_change","coords":[2,0],"parent":[1,0],"previous_parent":null}"#);
+        assert_eq!(lines[9], r#"{"step":3,"action":"path_found","path":[[2,0],[1,0],[0,0]]}"#);
+    }
+
+    #[test]
+    fn reads_spacing_and_field_order_other_writers_use() {
+        let text = "\n{ \"action\" : \"pop\", \"f\" : 4, \"coords\" : [ 1 , -2 ], \"step\" : 7 }\n\n";
+
+        let trace = SearchTrace::from_json_lines(text).unwrap();
+
+        assert_eq!(trace.get_events().len(), 1);
+        assert_eq!(trace.get_events()[0].step, 7);
+        assert_eq!(trace.get_events()[0].action, TraceAction::Pop { coords: (1, -2), f: 4 });
+    }
+
+    #[test]
+    fn rejects_malformed_lines_with_their_line_number() {
+        let cases = [
+            r#"{"step":0,"action":"teleport"}"#,
+            r#"{"step":0,"action":"pop","coords":[1,2]}"#,
+            r#"{"step":0,"action":"pop","coords":[1],"f":3}"#,
+            r#"{"step":0,"action":"pop","coords":[1,2],"f":3"#,
+            r#"{"step":0,"action":"pop","coords":[1,2],"f":3} trailing"#,
+            r#"{"step":0,"action":"push","coords":[1,2],"f":3,"previous_f":"x"}"#,
+            r#"[1,2]"#,
+        ];
+
+        for case in cases {
+            let text = format!("{}\n{}\n", sample_trace().to_json_lines().lines().next().unwrap(), case);
+
+            let error = SearchTrace::from_json_lines(&text).unwrap_err();
+
+            assert!(error.starts_with("line 2:"), "{} gave {}", case, error);
+        }
+    }
+
+    #[test]
+    fn save_then_load_gives_back_the_same_trace() {
+        let path = env::temp_dir().join(format!("maze_runner_trace_{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let trace = sample_trace();
+
+        trace.save(path).unwrap();
+
+        let loaded = SearchTrace::load(path);
+
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded, Ok(trace));
+    }
+}
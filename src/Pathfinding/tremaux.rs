@@ -0,0 +1,289 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use super::Graph;
+use super::Node::Coords;
+use super::solver::Solver;
+use super::stats::SearchStats;
+
+pub struct Tremaux {
+    start_coords: Coords,
+    goal_coords: Coords,
+    current_coords: Coords,
+    previous_coords: Option<Coords>,
+
+    marks: HashMap<(Coords, Coords), u8>,
+    visited: HashSet<Coords>,
+    trail: Vec<Coords>,
+
+    // Ties between unmarked passages are broken from the same seed on every run.
+    seed: u64,
+    rng: StdRng,
+
+    stats: SearchStats,
+
+    path: Vec<Coords>,
+    is_finished: bool
+}
+
+impl Tremaux {
+    pub fn new() -> Tremaux {
+        let seed = rand::random();
+
+        return Tremaux {
+            start_coords: (0, 0),
+            goal_coords: (0, 0),
+            current_coords: (0, 0),
+            previous_coords: None,
+            marks: HashMap::new(),
+            visited: HashSet::new(),
+            trail: vec![],
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            stats: SearchStats::default(),
+            path: vec![],
+            is_finished: true
+        };
+    }
+
+    fn passage(node_coords: Coords, neighbor_coords: Coords) -> (Coords, Coords) {
+        return (Coords::min(node_coords, neighbor_coords), Coords::max(node_coords, neighbor_coords));
+    }
+
+    fn get_marks(&self, node_coords: Coords, neighbor_coords: Coords) -> u8 {
+        return *self.marks.get(&Tremaux::passage(node_coords, neighbor_coords)).unwrap_or(&0);
+    }
+
+    fn walk(&mut self, neighbor_coords: Coords) {
+        *self.marks.entry(Tremaux::passage(self.current_coords, neighbor_coords)).or_insert(0) += 1;
+
+        self.previous_coords = Some(self.current_coords);
+        self.current_coords = neighbor_coords;
+
+        self.trail.push(neighbor_coords);
+
+        self.stats.record_expansion();
+        self.stats.record_generated(1);
+        self.stats.record_frontier(1);
+    }
+
+    // Passages walked exactly once lead straight from the start to the goal.
+    fn reconstruct_path(&mut self, graph: &Graph::Graph) {
+        let mut came_from: HashMap<Coords, Coords> = HashMap::new();
+        let mut queue: VecDeque<Coords> = VecDeque::from([self.start_coords]);
+
+        while let Some(node_coords) = queue.pop_front() {
+            if node_coords == self.goal_coords { break; }
+
+            for connection_coords in graph.iter_connections(node_coords) {
+                if self.get_marks(node_coords, connection_coords) != 1 { continue; }
+
+                if connection_coords == self.start_coords || came_from.contains_key(&connection_coords) { continue; }
+
+                came_from.insert(connection_coords, node_coords);
+
+                queue.push_back(connection_coords);
+            }
+        }
+
+        let mut node_coords = self.goal_coords;
+
+        self.path.push(node_coords);
+
+        while let Some(parent_coords) = came_from.get(&node_coords) {
+            node_coords = *parent_coords;
+
+            self.path.push(node_coords);
+        }
+    }
+}
+
+impl Default for Tremaux {
+    fn default() -> Tremaux {
+        return Tremaux::new();
+    }
+}
+
+impl Solver for Tremaux {
+    fn initialize(&mut self, start_coords: Coords, goal_coords: Coords) {
+        self.start_coords = start_coords;
+        self.goal_coords = goal_coords;
+        self.current_coords = start_coords;
+        self.previous_coords = None;
+        self.marks = HashMap::new();
+        self.visited = HashSet::new();
+        self.trail = vec![start_coords];
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.stats = SearchStats::default();
+        self.path = vec![];
+        self.is_finished = false;
+    }
+
+    fn generate_path(&mut self, graph: &Graph::Graph) {
+        if self.is_finished { return; }
+
+        if self.current_coords == self.goal_coords {
+            self.reconstruct_path(graph);
+
+            self.is_finished = true;
+
+            return;
+        }
+
+        let is_revisit = !self.visited.insert(self.current_coords);
+
+        // Coming back to a known cell along a fresh passage means a loop was closed, so turn around.
+        if let Some(previous_coords) = self.previous_coords {
+            if is_revisit && self.get_marks(self.current_coords, previous_coords) == 1 {
+                self.walk(previous_coords);
+
+                return;
+            }
+        }
+
+        let mut connections = graph.get_connections(self.current_coords);
+
+        connections.sort();
+
+        let mut fewest_marks = 2;
+        let mut candidates: Vec<Coords> = vec![];
+
+        for connection_coords in connections {
+            // Leaving by the way in is the last resort, handled below.
+            if Some(connection_coords) == self.previous_coords { continue; }
+
+            let marks = self.get_marks(self.current_coords, connection_coords);
+
+            if marks < fewest_marks {
+                fewest_marks = marks;
+                candidates = vec![];
+            }
+
+            if marks == fewest_marks && marks < 2 {
+                candidates.push(connection_coords);
+            }
+        }
+
+        if candidates.is_empty() {
+            if let Some(previous_coords) = self.previous_coords {
+                if self.get_marks(self.current_coords, previous_coords) < 2 {
+                    self.walk(previous_coords);
+
+                    return;
+                }
+            }
+
+            // Every passage has been walked twice, so the goal cannot be reached.
+            self.is_finished = true;
+
+            return;
+        }
+
+        let index = self.rng.gen_range(0..candidates.len());
+
+        self.walk(candidates[index]);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    fn get_start_coords(&self) -> Coords {
+        return self.start_coords;
+    }
+
+    fn get_current_coords(&self) -> Coords {
+        return self.current_coords;
+    }
+
+    fn get_goal_coords(&self) -> Coords {
+        return self.goal_coords;
+    }
+
+    fn get_open_set(&self) -> Vec<Coords> {
+        return vec![];
+    }
+
+    fn get_trail(&self) -> Vec<Coords> {
+        return self.trail.clone();
+    }
+
+    fn get_stats(&self) -> SearchStats {
+        return self.stats;
+    }
+
+    fn get_stats_mut(&mut self) -> &mut SearchStats {
+        return &mut self.stats;
+    }
+
+    fn get_path(&self) -> Vec<Coords> {
+        return self.path.clone();
+    }
+
+    fn is_finished(&self) -> bool {
+        return self.is_finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::Tremaux;
+    use crate::Pathfinding::Graph;
+    use crate::Pathfinding::solver::Solver;
+    use crate::Pathfinding::testing;
+    use crate::Pathfinding::topology::Shape;
+
+    fn check_walk(seed: u64, graph: &Graph::Graph) {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let (start_coords, goal_coords) = (testing::random_coords(&mut rng, graph), testing::random_coords(&mut rng, graph));
+
+        let mut tremaux = Tremaux::new();
+
+        tremaux.set_seed(seed);
+
+        let path = testing::solve(&mut tremaux, graph, start_coords, goal_coords);
+
+        match testing::dijkstra(graph, start_coords, goal_coords) {
+            None => assert!(path.is_empty(), "seed {}: a route was found that is not there", seed),
+            Some(_) => {
+                assert_eq!((path.last(), path.first()), (Some(&start_coords), Some(&goal_coords)), "seed {}", seed);
+
+                testing::get_path_cost(graph, &path);
+            },
+        }
+
+        let mut crossings: HashMap<(_, _), usize> = HashMap::new();
+
+        for step in tremaux.get_trail().windows(2) {
+            assert!(graph.is_connected(step[0], step[1]), "seed {}: walked through a wall from {:?} to {:?}", seed, step[0], step[1]);
+
+            *crossings.entry(Tremaux::passage(step[0], step[1])).or_insert(0) += 1;
+        }
+
+        for (passage, count) in crossings {
+            assert!(count <= 2, "seed {}: {:?} was crossed {} times", seed, passage, count);
+        }
+    }
+
+    #[test]
+    fn reaches_the_goal_crossing_each_passage_at_most_twice() {
+        for seed in 0..40 {
+            check_walk(seed, &testing::generate_maze(12).graph);
+        }
+
+        for seed in 0..80 {
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            let graph = testing::random_grid(&mut rng, Shape::Square, 10, [0.5, 0.7, 1.0][seed as usize % 3]);
+
+            check_walk(seed, &graph);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use super::Graph;
+use super::Node::Coords;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    // A passage through a wall that is open one way but not the other, without set_one_way having made it so.
+    Asymmetric(Coords, Coords),
+    // A connection from a cell to somewhere outside the grid.
+    OutOfBounds(Coords, Coords),
+}
+
+// What a graph looks like as a maze. Passages are counted once whichever ways they are open, and
+// steps across corners are left out since they follow from the walls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub problems: Vec<Problem>,
+    pub component_count: usize,
+    pub passage_count: usize,
+    // The first passage found to close a loop, if any does.
+    pub opt_cycle: Option<(Coords, Coords)>,
+}
+
+impl ValidationReport {
+    pub fn new(graph: &Graph::Graph) -> ValidationReport {
+        let topology = graph.get_topology();
+
+        let mut problems: Vec<Problem> = vec![];
+
+        for node_coords in graph.iter_nodes() {
+            for connection_coords in graph.iter_connections(node_coords) {
+                if !graph.is_valid_node_coordinate(connection_coords) {
+                    problems.push(Problem::OutOfBounds(node_coords, connection_coords));
+                }
+            }
+
+            for neighbor_coords in graph.iter_wall_neighbors(node_coords) {
+                if graph.is_connected(node_coords, neighbor_coords) && !graph.is_connected(neighbor_coords, node_coords) && !graph.is_set_one_way(node_coords, neighbor_coords) {
+                    problems.push(Problem::Asymmetric(node_coords, neighbor_coords));
+                }
+            }
+        }
+
+        // Joins the two ends of every passage, so a passage whose ends are already joined closes a loop.
+        let mut parents: Vec<usize> = (0..topology.get_cell_count()).collect();
+
+        let mut component_count = topology.get_cell_count();
+        let mut passage_count = 0;
+        let mut opt_cycle: Option<(Coords, Coords)> = None;
+
+        for (node_coords, other_coords) in ValidationReport::get_passages(graph) {
+            passage_count += 1;
+
+            let root = ValidationReport::find(&mut parents, topology.get_index(node_coords).unwrap());
+            let other_root = ValidationReport::find(&mut parents, topology.get_index(other_coords).unwrap());
+
+            if root == other_root {
+                if opt_cycle.is_none() {
+                    opt_cycle = Some((node_coords, other_coords));
+                }
+
+                continue;
+            }
+
+            parents[root] = other_root;
+
+            component_count -= 1;
+        }
+
+        return ValidationReport { problems, component_count, passage_count, opt_cycle };
+    }
+
+    // Each passage through a wall and each portal once, from the first of its ends in storage order.
+    fn get_passages(graph: &Graph::Graph) -> Vec<(Coords, Coords)> {
+        let topology = graph.get_topology();
+
+        let mut passages: Vec<(Coords, Coords)> = vec![];
+
+        for node_coords in graph.iter_nodes() {
+            for neighbor_coords in graph.iter_wall_neighbors(node_coords) {
+                let is_open = graph.is_connected(node_coords, neighbor_coords) || graph.is_connected(neighbor_coords, node_coords);
+
+                if is_open && topology.get_index(node_coords) < topology.get_index(neighbor_coords) {
+                    passages.push((node_coords, neighbor_coords));
+                }
+            }
+        }
+
+        for (entry_coords, exit_coords) in graph.get_portals() {
+            let is_paired = graph.is_portal(exit_coords, entry_coords);
+
+            // A pair of portals between the same cells is one passage open both ways.
+            if !is_paired || topology.get_index(entry_coords) < topology.get_index(exit_coords) {
+                passages.push((entry_coords, exit_coords));
+            }
+        }
+
+        return passages;
+    }
+
+    fn find(parents: &mut [usize], index: usize) -> usize {
+        let mut index = index;
+
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+
+            index = parents[index];
+        }
+
+        return index;
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        return self.problems.is_empty();
+    }
+
+    // A perfect maze is a spanning tree: every cell reachable from every other by exactly one route.
+    pub fn is_perfect_maze(&self) -> bool {
+        return self.is_consistent() && self.component_count == 1 && self.opt_cycle.is_none();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Problem, ValidationReport};
+    use crate::Maze;
+    use crate::Pathfinding::Graph;
+    use crate::Pathfinding::topology::{CornerCutting, SquareTopology};
+
+    fn generate(size: i32) -> Maze::Maze {
+        let mut maze = Maze::Maze::new(size);
+
+        while !maze.is_finished() {
+            maze.generate_maze();
+        }
+
+        return maze;
+    }
+
+    #[test]
+    fn generated_mazes_are_perfect_spanning_trees() {
+        for size in [1, 2, 5, 17, 40] {
+            let maze = generate(size);
+
+            let report = ValidationReport::new(&maze.graph);
+
+            assert!(report.is_perfect_maze(), "size {}: {:?}", size, report);
+            assert_eq!(report.passage_count, (size * size) as usize - 1);
+        }
+    }
+
+    #[test]
+    fn steps_across_corners_leave_a_maze_perfect() {
+        let mut maze = generate(12);
+
+        maze.graph.set_topology(Box::new(SquareTopology::with_diagonals(12, CornerCutting::Allowed)));
+
+        assert!(ValidationReport::new(&maze.graph).is_perfect_maze());
+    }
+
+    #[test]
+    fn reports_one_sided_and_out_of_bounds_connections() {
+        let mut graph = Graph::Graph::new(3);
+
+        graph.add_connection((0, 0), (1, 0));
+        graph.add_connection((0, 0), (-1, 0));
+
+        let report = ValidationReport::new(&graph);
+
+        assert!(report.problems.contains(&Problem::Asymmetric((0, 0), (1, 0))));
+        assert!(report.problems.contains(&Problem::OutOfBounds((0, 0), (-1, 0))));
+        assert!(!report.is_perfect_maze());
+    }
+
+    #[test]
+    fn one_way_passages_made_on_purpose_are_not_problems() {
+        let mut maze = generate(8);
+
+        for node_coords in [(0, 0), (3, 4), (7, 7)] {
+            let neighbor_coords = maze.graph.get_connections(node_coords)[0];
+
+            maze.graph.set_one_way(neighbor_coords, node_coords);
+        }
+
+        let report = ValidationReport::new(&maze.graph);
+
+        assert!(report.is_perfect_maze(), "{:?}", report);
+
+        // Closing the other way by hand is still a broken wall.
+        let neighbor_coords = maze.graph.get_connections((5, 5))[0];
+
+        maze.graph.remove_connection(neighbor_coords, (5, 5));
+
+        assert_eq!(ValidationReport::new(&maze.graph).problems, vec![Problem::Asymmetric((5, 5), neighbor_coords)]);
+    }
+
+    #[test]
+    fn counts_components_and_finds_loops() {
+        let mut graph = Graph::Graph::new(3);
+
+        assert_eq!(ValidationReport::new(&graph).component_count, 9);
+
+        for (node_coords, neighbor_coords) in [((0, 0), (1, 0)), ((1, 0), (1, 1)), ((1, 1), (0, 1))] {
+            graph.add_connection(node_coords, neighbor_coords);
+            graph.add_connection(neighbor_coords, node_coords);
+        }
+
+        let report = ValidationReport::new(&graph);
+
+        assert_eq!(report.component_count, 6);
+        assert!(report.opt_cycle.is_none());
+
+        graph.add_connection((0, 1), (0, 0));
+        graph.add_connection((0, 0), (0, 1));
+
+        let report = ValidationReport::new(&graph);
+
+        assert_eq!(report.component_count, 6);
+        assert!(report.opt_cycle.is_some());
+        assert!(report.is_consistent());
+    }
+
+    #[test]
+    fn portals_count_as_passages() {
+        let mut maze = generate(6);
+
+        maze.graph.add_portal((0, 0), (5, 5));
+
+        let report = ValidationReport::new(&maze.graph);
+
+        assert!(report.is_consistent());
+        assert!(report.opt_cycle.is_some());
+    }
+}
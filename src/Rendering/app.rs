@@ -8,28 +8,78 @@ use core::time;
 use glutin_window::GlutinWindow as Window;
 use opengl_graphics::{GlGraphics, OpenGL};
 use piston::event_loop::{EventSettings, Events};
-use piston::input::{RenderArgs, UpdateArgs};
-use piston::window::WindowSettings;
+use piston::input::{Button, Key, RenderArgs, UpdateArgs};
+use piston::window::{AdvancedWindow, WindowSettings};
 
 use rand::Rng;
 
 use crate::Pathfinding::Node::Coords;
 use crate::Pathfinding::{Graph};
+use crate::Pathfinding::astar::TieBreak;
+use crate::Pathfinding::replay::TraceReplay;
+use crate::Pathfinding::schedule::Schedule;
+use crate::Pathfinding::solver::{SolverKind, DEFAULT_NODE_BUDGET};
+use crate::Pathfinding::stats::SearchStatus;
+use crate::Pathfinding::topology::Shape;
+use crate::Pathfinding::trace::SearchTrace;
+use crate::Pathfinding::validation::ValidationReport;
 use crate::Maze;
 
+const TRACE_PATH: &str = "search_trace.jsonl";
+const GENERATION_TRACE_PATH: &str = "generation_trace.jsonl";
+
+const KEY_COUNT: usize = 3;
+
+const GATE_COUNT: usize = 60;
+
+// Timesteps per second when a finished route is walked and the gates move along with it.
+const WALK_SPEED: f64 = 4.0;
+
+// A door is drawn in the colour of the key that opens it.
+const KEY_COLORS: [[f32; 4]; 4] = [
+    [0.9, 0.7, 0.0, 1.0],
+    [0.0, 0.6, 0.9, 1.0],
+    [0.9, 0.2, 0.2, 1.0],
+    [0.2, 0.7, 0.2, 1.0],
+];
+
 pub struct Application {
     window: Window,
     graphics: GlGraphics,
     events: Events,
+    title: String,
 
     path_timer: f64,
+
+    solver_kind: SolverKind,
+    tie_break: TieBreak,
+    node_budget: usize,
+    waypoint_count: usize,
+    waypoints: Vec<Coords>,
+    agent_count: usize,
+    agents: Vec<(Coords, Coords)>,
+    // Handed to every solver, so a chased target wanders the same way whichever strategy chases it.
+    seed: u64,
+    show_search_tree: bool,
+    is_replaying: bool,
+    replay_speed: i32,
+    is_generation_paused: bool,
+
+    window_size: [f64; 2],
+    hovered: Option<Coords>,
+
+    // What the last key press did, such as saving a trace, until the next one.
+    opt_notice: Option<String>,
+
+    // The last check of the finished maze, with the change count it was made at.
+    opt_validation: Option<(usize, ValidationReport)>,
 }
 
 impl Application {
     pub fn new(title: String, width: u32, height: u32) -> Application {
         let opengl = OpenGL::V3_2;
 
-        let window: Window = WindowSettings::new(title, [width, height])
+        let window: Window = WindowSettings::new(title.clone(), [width, height])
             .graphics_api(opengl)
             .exit_on_esc(true)
             .build()
@@ -39,7 +89,29 @@ impl Application {
 
         let events = Events::new(EventSettings::new());
 
-        let app = Application { graphics, window, events, path_timer: 0.0 };
+        let app = Application {
+            graphics,
+            window,
+            events,
+            title,
+            path_timer: 0.0,
+            solver_kind: SolverKind::Astar,
+            tie_break: TieBreak::LargerG,
+            node_budget: DEFAULT_NODE_BUDGET,
+            waypoint_count: 6,
+            waypoints: vec![],
+            agent_count: 4,
+            agents: vec![],
+            seed: rand::random(),
+            show_search_tree: true,
+            is_replaying: false,
+            replay_speed: 1,
+            is_generation_paused: false,
+            window_size: [f64::from(width), f64::from(height)],
+            hovered: None,
+            opt_notice: None,
+            opt_validation: None
+        };
 
         return app;
     }
@@ -51,6 +123,12 @@ impl Application {
     pub fn render(&mut self, maze: &Maze::Maze, args: &RenderArgs) {
         let (width, height) = (args.window_size[0], args.window_size[1]);
 
+        let show_search_tree = self.show_search_tree;
+        let hovered = self.hovered;
+        let time = Application::get_walk_time(maze, self.path_timer);
+
+        self.window_size = args.window_size;
+
         self.graphics.draw(args.viewport(), |context, gl| {
             Application::clear_screen(gl);
 
@@ -58,15 +136,41 @@ impl Application {
                 Application::render_maze_generation(&maze.graph, maze.get_current_coords(), width, height, &context, gl);
             }
 
-            if !maze.astar.is_finished() {
+            if show_search_tree {
+                Application::render_closed(maze, width, height, &context, gl);
+            }
+
+            if !maze.solver.is_finished() {
+                Application::render_forgotten(maze, width, height, &context, gl);
                 Application::render_openset(maze, width, height, &context, gl);
             }
+
+            if show_search_tree {
+                Application::render_parents(maze, width, height, &context, gl);
+            }
             
+            Application::render_filled(maze, width, height, &context, gl);
+            Application::render_trail(maze, width, height, &context, gl);
             Application::render_path(maze, width, height, &context, gl);
+            Application::render_legs(maze, width, height, &context, gl);
             Application::render_ends(maze, width, height, &context, gl);
+            Application::render_agents(maze, width, height, &context, gl);
+            Application::render_walker(maze, time, width, height, &context, gl);
+            Application::render_waypoints(maze, width, height, &context, gl);
+            Application::render_keys(maze, width, height, &context, gl);
+            Application::render_unknown(maze, width, height, &context, gl);
+            Application::render_clusters(maze, width, height, &context, gl);
+
+            if let Some(hovered_coords) = hovered {
+                Application::render_inspector(maze, hovered_coords, width, height, &context, gl);
+            }
 
             Application::render_border(width, height, &context, gl);
             Application::render_connections(&maze.graph, width, height, &context, gl);
+            Application::render_diagonals(&maze.graph, width, height, &context, gl);
+            Application::render_portals(&maze.graph, width, height, &context, gl);
+            Application::render_doors(&maze.graph, width, height, &context, gl);
+            Application::render_gates(&maze.graph, time, width, height, &context, gl);
         });
     }
 
@@ -78,27 +182,272 @@ impl Application {
 
     fn render_openset(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
         const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+        const PURPLE: [f32; 4] = [0.5, 0.0, 0.5, 1.0];
 
-        for current_coords in maze.astar.get_open_set() {
+        for current_coords in maze.solver.get_open_set() {
             Application::draw_coords(&maze.graph, BLUE, current_coords, width, height, context, gl);
         }
+
+        for current_coords in maze.solver.get_backward_open_set() {
+            Application::draw_coords(&maze.graph, PURPLE, current_coords, width, height, context, gl);
+        }
+    }
+
+    // Early expansions are pale and later ones darker, so the order the search grew in stays visible.
+    fn render_closed(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const EARLY: [f32; 4] = [0.85, 0.93, 1.0, 1.0];
+        const LATE: [f32; 4] = [0.35, 0.55, 0.85, 1.0];
+
+        let closed_set = maze.solver.get_closed_set();
+
+        let last = usize::max(closed_set.len(), 2) - 1;
+
+        for (index, current_coords) in closed_set.iter().enumerate() {
+            let t = index as f32 / last as f32;
+
+            let mut color = EARLY;
+
+            for channel in 0..3 {
+                color[channel] = EARLY[channel] + (LATE[channel] - EARLY[channel]) * t;
+            }
+
+            Application::draw_coords(&maze.graph, color, *current_coords, width, height, context, gl);
+        }
+    }
+
+    fn render_parents(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const DARK_GREY: [f32; 4] = [0.25, 0.25, 0.25, 0.8];
+
+        let transform = context.transform;
+
+        for (node_coords, parent_coords) in maze.solver.get_came_from() {
+            let from = Application::get_cell_center(&maze.graph, node_coords, width, height);
+            let to = Application::get_cell_center(&maze.graph, parent_coords, width, height);
+
+            let tile = Application::get_cell_rect(&maze.graph, node_coords, width, height);
+
+            // Stop short of the parent's centre so arrows along a corridor do not run together.
+            let tip = (from.0 + (to.0 - from.0) * 0.7, from.1 + (to.1 - from.1) * 0.7);
+
+            let length = f64::hypot(to.0 - from.0, to.1 - from.1);
+
+            if length == 0.0 { continue; }
+
+            let direction = ((to.0 - from.0) / length, (to.1 - from.1) / length);
+
+            let head = f64::min(tile[2], tile[3]) * 0.2;
+
+            graphics::line(DARK_GREY, 0.75, [from.0, from.1, tip.0, tip.1], transform, gl);
+
+            for side in [1.0, -1.0] {
+                let barb = (
+                    tip.0 - direction.0 * head - direction.1 * head * 0.6 * side,
+                    tip.1 - direction.1 * head + direction.0 * head * 0.6 * side
+                );
+
+                graphics::line(DARK_GREY, 0.75, [tip.0, tip.1, barb.0, barb.1], transform, gl);
+            }
+        }
+    }
+
+    fn render_forgotten(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const GREY: [f32; 4] = [0.75, 0.75, 0.75, 1.0];
+
+        for current_coords in maze.solver.get_forgotten_set() {
+            Application::draw_coords(&maze.graph, GREY, current_coords, width, height, context, gl);
+        }
     }
 
     fn render_ends(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
         const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
         const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
         const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+        const YELLOW: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+
+        Application::draw_coords(&maze.graph, GREEN, maze.solver.get_start_coords(), width, height, context, gl);
+        Application::draw_coords(&maze.graph, BLACK, maze.solver.get_current_coords(), width, height, context, gl);
+        Application::draw_coords(&maze.graph, RED, maze.solver.get_goal_coords(), width, height, context, gl);
+
+        if let Some(meeting_coords) = maze.solver.get_meeting_coords() {
+            Application::draw_coords(&maze.graph, YELLOW, meeting_coords, width, height, context, gl);
+        }
+    }
+
+    fn render_unknown(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const FOG: [f32; 4] = [0.2, 0.2, 0.2, 0.35];
 
-        Application::draw_coords(&maze.graph, GREEN, maze.astar.get_start_coords(), width, height, context, gl);
-        Application::draw_coords(&maze.graph, BLACK, maze.astar.get_current_coords(), width, height, context, gl);
-        Application::draw_coords(&maze.graph, RED, maze.astar.get_goal_coords(), width, height, context, gl);
+        for current_coords in maze.solver.get_unknown_set() {
+            Application::draw_coords(&maze.graph, FOG, current_coords, width, height, context, gl);
+        }
+    }
+
+    fn render_filled(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const DARK_GREY: [f32; 4] = [0.4, 0.4, 0.4, 1.0];
+
+        for current_coords in maze.solver.get_filled_set() {
+            Application::draw_coords(&maze.graph, DARK_GREY, current_coords, width, height, context, gl);
+        }
+    }
+
+    fn render_trail(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const LIGHT_BLUE: [f32; 4] = [0.6, 0.8, 1.0, 1.0];
+
+        for current_coords in maze.solver.get_trail() {
+            Application::draw_coords(&maze.graph, LIGHT_BLUE, current_coords, width, height, context, gl);
+        }
     }
 
     fn render_path(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
         const ORANGE: [f32; 4] = [1.0, 0.64, 0.0, 1.0];
 
-        for current_coords in maze.astar.get_path() {
-            Application::draw_coords(&maze.graph, ORANGE, current_coords, width, height, context, gl);
+        const DARK_ORANGE: [f32; 4] = [0.8, 0.4, 0.0, 1.0];
+
+        let path = maze.solver.get_path();
+
+        for current_coords in &path {
+            Application::draw_coords(&maze.graph, ORANGE, *current_coords, width, height, context, gl);
+        }
+
+        // Cells alone do not show which way a path cuts across corners, so trace its steps too.
+        if maze.graph.get_topology().get_corner_cutting().is_none() { return; }
+
+        for pair in path.windows(2) {
+            let from = Application::get_cell_center(&maze.graph, pair[0], width, height);
+            let to = Application::get_cell_center(&maze.graph, pair[1], width, height);
+
+            graphics::line(DARK_ORANGE, 1.5, [from.0, from.1, to.0, to.1], context.transform, gl);
+        }
+    }
+
+    fn render_legs(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const PALETTE: [[f32; 4]; 6] = [
+            [1.0, 0.64, 0.0, 1.0],
+            [0.0, 0.7, 0.7, 1.0],
+            [0.8, 0.2, 0.6, 1.0],
+            [0.5, 0.8, 0.1, 1.0],
+            [0.3, 0.4, 0.9, 1.0],
+            [0.9, 0.8, 0.1, 1.0],
+        ];
+
+        for (index, leg) in maze.solver.get_legs().iter().enumerate() {
+            for current_coords in leg {
+                Application::draw_coords(&maze.graph, PALETTE[index % PALETTE.len()], *current_coords, width, height, context, gl);
+            }
+        }
+    }
+
+    fn render_agents(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const MAGENTA: [f32; 4] = [1.0, 0.0, 1.0, 0.6];
+        const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+        for current_coords in maze.solver.get_conflicts() {
+            Application::draw_coords(&maze.graph, MAGENTA, current_coords, width, height, context, gl);
+        }
+
+        for current_coords in maze.solver.get_agents() {
+            Application::draw_coords(&maze.graph, BLACK, current_coords, width, height, context, gl);
+        }
+    }
+
+    fn render_waypoints(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        for (index, current_coords) in maze.solver.get_waypoints().iter().enumerate() {
+            Application::draw_number(&maze.graph, index as i32 + 1, *current_coords, width, height, context, gl);
+        }
+    }
+
+    // Where the route has got to at the current timestep, which is where the gates are drawn at too.
+    fn render_walker(maze: &Maze::Maze, time: usize, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const DARK_BLUE: [f32; 4] = [0.1, 0.1, 0.6, 1.0];
+
+        if maze.graph.get_gates().is_empty() || !maze.solver.is_finished() { return; }
+
+        let path = maze.solver.get_path();
+
+        if path.is_empty() { return; }
+
+        // Paths run from the goal back to the start.
+        let current_coords = path[path.len() - 1 - usize::min(time, path.len() - 1)];
+
+        Application::draw_coords(&maze.graph, DARK_BLUE, current_coords, width, height, context, gl);
+    }
+
+    // Keys as coloured dots, numbered in the order the route picks them up.
+    fn render_keys(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        for (coords, key) in maze.graph.get_keys() {
+            let rect = Application::get_cell_rect(&maze.graph, coords, width, height);
+
+            let dot = [rect[0] + rect[2] * 0.25, rect[1] + rect[3] * 0.25, rect[2] * 0.5, rect[3] * 0.5];
+
+            graphics::ellipse(KEY_COLORS[key % KEY_COLORS.len()], dot, context.transform, gl);
+        }
+
+        for (index, coords) in maze.solver.get_collected_keys().iter().enumerate() {
+            Application::draw_number(&maze.graph, index as i32 + 1, *coords, width, height, context, gl);
+        }
+    }
+
+    fn render_clusters(maze: &Maze::Maze, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const GRID: [f32; 4] = [0.3, 0.3, 0.9, 0.5];
+        const DARK_GREEN: [f32; 4] = [0.0, 0.5, 0.0, 1.0];
+
+        let opt_cluster_size = maze.solver.get_cluster_size();
+
+        if opt_cluster_size.is_none() { return; }
+
+        let cluster_size = opt_cluster_size.unwrap();
+
+        let graph = &maze.graph;
+        let topology = graph.get_topology();
+
+        let transform = context.transform;
+
+        let get_cluster = |coords: Coords| (coords.0.div_euclid(cluster_size), coords.1.div_euclid(cluster_size));
+
+        // Cluster borders are drawn over the walls between cells that fall in different clusters.
+        for node_coords in graph.iter_nodes() {
+            for (direction, offset) in topology.get_directions(node_coords)[..topology.get_wall_count(node_coords)].iter().enumerate() {
+                let neighbor_coords = (node_coords.0 + offset.0, node_coords.1 + offset.1);
+
+                if !graph.is_valid_node_coordinate(neighbor_coords) || get_cluster(node_coords) == get_cluster(neighbor_coords) { continue; }
+
+                let wall = Application::to_screen(graph, topology.get_wall(node_coords, direction), width, height);
+
+                graphics::line(GRID, 1.5, wall, transform, gl);
+            }
+        }
+
+        for pair in maze.solver.get_abstract_path().windows(2) {
+            let from = Application::get_cell_center(graph, pair[0], width, height);
+            let to = Application::get_cell_center(graph, pair[1], width, height);
+
+            graphics::line(DARK_GREEN, 2.0, [from.0, from.1, to.0, to.1], transform, gl);
+        }
+    }
+
+    fn render_inspector(maze: &Maze::Maze, coords: Coords, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+        let transform = context.transform;
+
+        graphics::Rectangle::new_border(RED, 1.5).draw(
+            Application::get_cell_rect(&maze.graph, coords, width, height), &context.draw_state, transform, gl
+        );
+
+        let opt_scores = maze.solver.get_cell_scores(coords);
+
+        if opt_scores.is_none() { return; }
+
+        let scores = opt_scores.unwrap();
+
+        if let Some(f) = scores.get_f() {
+            Application::draw_number(&maze.graph, f, coords, width, height, context, gl);
+        }
+
+        if let Some(parent_coords) = scores.parent {
+            let from = Application::get_cell_center(&maze.graph, coords, width, height);
+            let to = Application::get_cell_center(&maze.graph, parent_coords, width, height);
+
+            graphics::line(RED, 1.5, [from.0, from.1, to.0, to.1], transform, gl);
         }
     }
 
@@ -123,88 +472,672 @@ impl Application {
     }
 
     fn render_connections(graph: &Graph::Graph, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
-        for node_coords in graph.get_nodes() {
-            for neighbor_coords in graph.get_neighbors(node_coords) {
+        const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+        let topology = graph.get_topology();
+
+        for node_coords in graph.iter_nodes() {
+            let wall_count = topology.get_wall_count(node_coords);
+
+            for (direction, offset) in topology.get_directions(node_coords)[..wall_count].iter().enumerate() {
+                let neighbor_coords = (node_coords.0 + offset.0, node_coords.1 + offset.1);
+
+                // The edge of the grid, which rows that end half a cell short leave inside the window.
+                if !graph.is_valid_node_coordinate(neighbor_coords) {
+                    let wall = Application::to_screen(graph, topology.get_wall(node_coords, direction), width, height);
+
+                    graphics::line(BLACK, 1.0, wall, context.transform, gl);
+
+                    continue;
+                }
+
+                // A passage open only one way is drawn as an arrow in place of its wall.
+                if graph.is_one_way(node_coords, neighbor_coords) {
+                    if !graph.is_connected(node_coords, neighbor_coords) { continue; }
+
+                    let from = Application::get_cell_center(graph, node_coords, width, height);
+                    let to = Application::get_cell_center(graph, neighbor_coords, width, height);
+
+                    Application::draw_arrow(BLACK, from, to, 0.3, 0.7, context, gl);
+
+                    continue;
+                }
+
                 if graph.is_connected(node_coords, neighbor_coords) { continue; }
 
-                Application::render_connection(graph, node_coords, neighbor_coords, width, height, context, gl);
+                let wall = Application::to_screen(graph, topology.get_wall(node_coords, direction), width, height);
+
+                graphics::line(BLACK, 1.0, wall, context.transform, gl);
             }
         }
     }
 
-    fn render_connection(
-        graph: &Graph::Graph, node_coords: Coords, neighbor_coords: Coords, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics
-    ) {
-        const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+    fn render_doors(graph: &Graph::Graph, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        let topology = graph.get_topology();
 
-        let node_coords_f = (f64::from(node_coords.0), f64::from(node_coords.1));
+        for (node_coords, neighbor_coords, key) in graph.get_doors() {
+            let opt_direction = topology.get_direction(node_coords, neighbor_coords);
 
-        let graph_size = f64::from(graph.get_size());
+            if opt_direction.is_none() { continue; }
 
-        let tile_width = width / graph_size;
-        let tile_height = height / graph_size;
+            let wall = Application::to_screen(graph, topology.get_wall(node_coords, opt_direction.unwrap()), width, height);
 
-        let top_left = ((node_coords_f.0 / graph_size) * width, (node_coords_f.1 / graph_size) * height);
-        let top_right = (top_left.0 + tile_width, top_left.1);
-        let bottom_left = (top_left.0, top_left.1 + tile_height);
-        let bottom_right = (top_left.0 + tile_width, top_left.1 + tile_height);
+            graphics::line(KEY_COLORS[key % KEY_COLORS.len()], 2.5, wall, context.transform, gl);
+        }
+    }
 
-        let transform = context.transform;
+    // Open gates as thin strokes across their passage and closed ones as thick bars, with bridges in their own colours.
+    fn render_gates(graph: &Graph::Graph, time: usize, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const GREEN: [f32; 4] = [0.1, 0.7, 0.2, 1.0];
+        const RED: [f32; 4] = [0.8, 0.1, 0.1, 1.0];
+        const BROWN: [f32; 4] = [0.6, 0.4, 0.2, 1.0];
+        const GREY: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
+
+        let topology = graph.get_topology();
+
+        for (node_coords, neighbor_coords, schedule) in graph.get_gates() {
+            let opt_direction = topology.get_direction(node_coords, neighbor_coords);
 
-        if node_coords.0 == neighbor_coords.0 {
-            if node_coords.1 < neighbor_coords.1 { // Connection from bottom.
-                graphics::line(BLACK, 1.0, [bottom_right.0, bottom_right.1, bottom_left.0, bottom_left.1], transform, gl);
-            } else { // Connection from top.
-                graphics::line(BLACK, 1.0, [top_left.0, top_left.1, top_right.0, top_right.1], transform, gl);
+            if opt_direction.is_none() { continue; }
+
+            let wall = Application::to_screen(graph, topology.get_wall(node_coords, opt_direction.unwrap()), width, height);
+
+            let (color, thickness) = match (schedule, schedule.is_open(time)) {
+                (Schedule::Periodic { .. }, true) => (GREEN, 0.5),
+                (Schedule::Periodic { .. }, false) => (RED, 2.5),
+                (Schedule::Window { .. }, true) => (BROWN, 0.5),
+                (Schedule::Window { .. }, false) => (GREY, 2.5),
+            };
+
+            graphics::line(color, thickness, wall, context.transform, gl);
+        }
+    }
+
+    // A ring on each end of a portal, joined by an arrow from the entry to the exit.
+    fn render_portals(graph: &Graph::Graph, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const PURPLE: [f32; 4] = [0.5, 0.1, 0.8, 0.8];
+
+        for (entry_coords, exit_coords) in graph.get_portals() {
+            for coords in [entry_coords, exit_coords] {
+                let rect = Application::get_cell_rect(graph, coords, width, height);
+
+                let ring = [rect[0] + rect[2] * 0.15, rect[1] + rect[3] * 0.15, rect[2] * 0.7, rect[3] * 0.7];
+
+                graphics::Ellipse::new_border(PURPLE, 1.0).draw(ring, &context.draw_state, context.transform, gl);
             }
-        } else {
-            if node_coords.0 < neighbor_coords.0 { // Connection from right.
-                graphics::line(BLACK, 1.0, [bottom_right.0, bottom_right.1, top_right.0, top_right.1], transform, gl);
-            } else { // Connection from left.
-                graphics::line(BLACK, 1.0, [top_left.0, top_left.1, bottom_left.0, bottom_left.1], transform, gl);
+
+            let from = Application::get_cell_center(graph, entry_coords, width, height);
+            let to = Application::get_cell_center(graph, exit_coords, width, height);
+
+            Application::draw_arrow(PURPLE, from, to, 0.0, 1.0, context, gl);
+        }
+    }
+
+    // A line over part of the span between two points, from start to end as fractions of it, with a head at the end.
+    fn draw_arrow(color: [f32; 4], from: (f64, f64), to: (f64, f64), start: f64, end: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+
+        let tail = (from.0 + dx * start, from.1 + dy * start);
+        let tip = (from.0 + dx * end, from.1 + dy * end);
+
+        graphics::line(color, 1.0, [tail.0, tail.1, tip.0, tip.1], context.transform, gl);
+
+        let length = f64::sqrt(dx * dx + dy * dy);
+
+        if length == 0.0 { return; }
+
+        // The head stays a fixed size however long the arrow is.
+        let (ux, uy) = (dx / length, dy / length);
+
+        let head = 5.0;
+
+        for side in [-1.0, 1.0] {
+            let barb = (tip.0 - head * (ux - side * uy * 0.6), tip.1 - head * (uy + side * ux * 0.6));
+
+            graphics::line(color, 1.0, [tip.0, tip.1, barb.0, barb.1], context.transform, gl);
+        }
+    }
+
+    // Steps across corners get a short stroke over the corner they cross.
+    fn render_diagonals(graph: &Graph::Graph, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
+        const GREY: [f32; 4] = [0.5, 0.5, 0.5, 0.8];
+
+        let topology = graph.get_topology();
+
+        if topology.get_corner_cutting().is_none() { return; }
+
+        for node_coords in graph.iter_nodes() {
+            let wall_count = topology.get_wall_count(node_coords);
+
+            for offset in topology.get_directions(node_coords).iter().skip(wall_count) {
+                let neighbor_coords = (node_coords.0 + offset.0, node_coords.1 + offset.1);
+
+                // Each passage is drawn once, from the cell above it.
+                if offset.1 < 0 || !graph.is_connected(node_coords, neighbor_coords) { continue; }
+
+                let from = Application::get_cell_center(graph, node_coords, width, height);
+                let to = Application::get_cell_center(graph, neighbor_coords, width, height);
+
+                let (start, end) = (0.3, 0.7);
+
+                let stroke = [
+                    from.0 + (to.0 - from.0) * start, from.1 + (to.1 - from.1) * start,
+                    from.0 + (to.0 - from.0) * end, from.1 + (to.1 - from.1) * end
+                ];
+
+                graphics::line(GREY, 1.0, stroke, context.transform, gl);
             }
         }
     }
 
+    // Scales a box or a segment given in the topology's cell units to window pixels.
+    fn to_screen(graph: &Graph::Graph, shape: [f64; 4], width: f64, height: f64) -> [f64; 4] {
+        let (extent_width, extent_height) = graph.get_topology().get_extent();
+
+        let (scale_x, scale_y) = (width / extent_width, height / extent_height);
+
+        return [shape[0] * scale_x, shape[1] * scale_y, shape[2] * scale_x, shape[3] * scale_y];
+    }
+
+    fn get_cell_rect(graph: &Graph::Graph, coords: Coords, width: f64, height: f64) -> [f64; 4] {
+        return Application::to_screen(graph, graph.get_topology().get_cell_bounds(coords), width, height);
+    }
+
+    fn get_cell_center(graph: &Graph::Graph, coords: Coords, width: f64, height: f64) -> (f64, f64) {
+        let rect = Application::get_cell_rect(graph, coords, width, height);
+
+        return (rect[0] + rect[2] / 2.0, rect[1] + rect[3] / 2.0);
+    }
+
     fn draw_coords(graph: &Graph::Graph, color: [f32; 4], current_coords: Coords, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics) {
-        let current_coords_f = (f64::from(current_coords.0), f64::from(current_coords.1));
+        graphics::rectangle(color, Application::get_cell_rect(graph, current_coords, width, height), context.transform, gl);
+    }
+
+    fn start_replay(&mut self, maze: &mut Maze::Maze, trace: SearchTrace) {
+        self.is_replaying = true;
+        self.replay_speed = 1;
+
+        maze.solver = Box::new(TraceReplay::new(trace));
+        maze.solver.set_metric(maze.graph.get_metric());
+    }
+
+    fn update_title(&mut self, maze: &Maze::Maze) {
+        let mut title = format!("{} - {:?}, {:?} ties", self.title, self.solver_kind, self.tie_break);
+
+        match self.solver_kind {
+            SolverKind::SmaStar => title = format!("{} - node budget {}", title, self.node_budget),
+            SolverKind::Tour => title = format!("{} - {} waypoints", title, self.waypoint_count),
+            SolverKind::ConflictBased => title = format!("{} - {} extra agents", title, self.agent_count),
+            _ => {},
+        }
+
+        if !self.show_search_tree {
+            title = format!("{} - search tree hidden", title);
+        }
+
+        if maze.is_finished() {
+            title = format!("{} - {}", title, self.describe_maze(maze));
+        }
+
+        match maze.graph.get_topology().get_shape() {
+            Shape::Square => {},
+            Shape::Diagonal(corner_cutting) => title = format!("{} - diagonals, corner cutting {:?}", title, corner_cutting),
+            Shape::Brick => title = format!("{} - bricks", title),
+        }
+
+        if !maze.is_finished() || self.is_generation_paused {
+            let (position, length) = maze.get_generation_position();
+
+            let state = if self.is_generation_paused { "paused" } else { "running" };
+
+            title = format!("{} - generation step {} of {}, {}", title, position, length, state);
+        }
+
+        if !maze.graph.get_gates().is_empty() {
+            title = format!("{} - {} gates at timestep {}", title, maze.graph.get_gates().len(), Application::get_walk_time(maze, self.path_timer));
+        }
+
+        if !maze.graph.get_portals().is_empty() {
+            title = format!("{} - {} portals", title, maze.graph.get_portals().len());
+        }
+
+        if !maze.graph.get_doors().is_empty() {
+            title = format!("{} - {} locked doors", title, maze.graph.get_doors().len());
+        }
+
+        if !self.solver_kind.handles(&maze.graph) {
+            title = format!("{} - no solver keeps to both doors and gates", title);
+        }
+
+        if let Some((position, length)) = maze.solver.get_playback_position() {
+            title = format!("{} - replaying event {} of {} at speed {}", title, position, length, self.replay_speed);
+        }
+
+        if let Some(threshold) = maze.solver.get_threshold() {
+            title = format!("{} - f threshold {}", title, threshold);
+        }
+
+        if let Some(report) = maze.solver.get_chase_report() {
+            title = format!("{} - {} ticks, {} expansions", title, report.ticks, report.expansions);
+        }
+
+        if maze.is_finished() {
+            let result = maze.solver.get_result(&maze.graph);
+
+            title = format!("{} - {:?}", title, result.status);
+
+            if result.status == SearchStatus::Found {
+                title = format!("{}: {} cells, cost {}", title, result.path_length, result.path_cost);
+            }
+
+            title = format!("{} - {} expanded, {} generated, peak frontier {}", title, result.stats.expansions, result.stats.generated, result.stats.max_frontier);
+
+            if maze.solver.is_finished() {
+                title = format!("{} in {:?}", title, result.stats.elapsed);
+            }
+
+            if let Some(baseline_expansions) = result.stats.opt_baseline_expansions {
+                title = format!("{} - {} expanded without landmarks", title, baseline_expansions);
+            }
+        }
+
+        if let Some(notice) = &self.opt_notice {
+            title = format!("{} - {}", title, notice);
+        }
+
+        if let Some(hovered_coords) = self.hovered {
+            title = format!("{} - {}", title, Application::describe_cell(maze, hovered_coords));
+        }
+
+        if self.window.get_title() != title {
+            self.window.set_title(title);
+        }
+    }
+
+    // Checks the finished maze is the spanning tree generation is meant to carve, again whenever it changes.
+    fn describe_maze(&mut self, maze: &Maze::Maze) -> String {
+        let change_count = maze.graph.get_change_count();
+
+        if self.opt_validation.as_ref().is_none_or(|(checked_count, _)| *checked_count != change_count) {
+            self.opt_validation = Some((change_count, ValidationReport::new(&maze.graph)));
+        }
+
+        let report = &self.opt_validation.as_ref().unwrap().1;
+
+        if report.is_perfect_maze() {
+            return format!("perfect maze, {} passages", report.passage_count);
+        }
+
+        let mut description = format!("{} passages, {} components", report.passage_count, report.component_count);
+
+        if report.opt_cycle.is_some() {
+            description = format!("{}, loops", description);
+        }
+
+        if !report.problems.is_empty() {
+            description = format!("{}, {} broken walls", description, report.problems.len());
+        }
+
+        return description;
+    }
+
+    fn describe_cell(maze: &Maze::Maze, coords: Coords) -> String {
+        let mut connections = maze.graph.get_connections(coords);
 
-        let graph_size = f64::from(graph.get_size());
+        connections.sort();
 
-        let tile_width = width / graph_size;
-        let tile_height = height / graph_size;
+        let mut description = format!("{:?} connects to {:?}", coords, connections);
 
-        let top_left = ((current_coords_f.0 / graph_size) * width, (current_coords_f.1 / graph_size) * height);
+        if let Some(scores) = maze.solver.get_cell_scores(coords) {
+            let show = |opt_value: Option<i32>| opt_value.map_or("-".to_string(), |value| value.to_string());
 
-        graphics::rectangle(color, [top_left.0, top_left.1, tile_width, tile_height], context.transform, gl);
+            description = format!("{} - g {} h {} f {}", description, show(scores.g), scores.h, show(scores.get_f()));
+
+            if let Some(parent_coords) = scores.parent {
+                description = format!("{} - parent {:?}", description, parent_coords);
+            }
+        }
+
+        return description;
+    }
+
+    // Draws a number across a cell with seven-segment digits, since there is no font to hand.
+    fn draw_number(
+        graph: &Graph::Graph, number: i32, coords: Coords, width: f64, height: f64, context: &graphics::Context, gl: &mut GlGraphics
+    ) {
+        const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+        const SEGMENTS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+        const MINUS: u8 = 0x40;
+
+        let mut glyphs: Vec<u8> = number.unsigned_abs().to_string().bytes().map(|digit| SEGMENTS[(digit - b'0') as usize]).collect();
+
+        if number < 0 {
+            glyphs.insert(0, MINUS);
+        }
+
+        let [left_edge, top_edge, tile_width, tile_height] = Application::get_cell_rect(graph, coords, width, height);
+
+        let top_left = (left_edge, top_edge);
+
+        let glyph_width = tile_width / glyphs.len() as f64;
+
+        let (pad_x, pad_y) = (glyph_width * 0.2, tile_height * 0.15);
+
+        let thickness = f64::max(tile_height / 20.0, 0.5);
+
+        for (index, glyph) in glyphs.iter().enumerate() {
+            let left = top_left.0 + glyph_width * index as f64 + pad_x;
+            let right = left + glyph_width - 2.0 * pad_x;
+            let top = top_left.1 + pad_y;
+            let bottom = top_left.1 + tile_height - pad_y;
+            let middle = (top + bottom) / 2.0;
+
+            let lines = [
+                [left, top, right, top],
+                [right, top, right, middle],
+                [right, middle, right, bottom],
+                [left, bottom, right, bottom],
+                [left, middle, left, bottom],
+                [left, top, left, middle],
+                [left, middle, right, middle],
+            ];
+
+            for (segment, line) in lines.iter().enumerate() {
+                if glyph & (1 << segment) == 0 { continue; }
+
+                graphics::line(BLACK, thickness, *line, context.transform, gl);
+            }
+        }
+    }
+
+    fn random_coords(graph: &Graph::Graph) -> Coords {
+        let mut rng = rand::thread_rng();
+
+        let topology = graph.get_topology();
+
+        return topology.get_coords(rng.gen_range(0..topology.get_cell_count()));
+    }
+
+    // The timestep a finished route has been walked to. Gates hold still at the first one while the search runs.
+    fn get_walk_time(maze: &Maze::Maze, path_timer: f64) -> usize {
+        if !maze.solver.is_finished() { return 0; }
+
+        return (path_timer * WALK_SPEED) as usize;
+    }
+
+    fn restart_solver(&mut self, maze: &mut Maze::Maze, start_coords: Coords, goal_coords: Coords) {
+        self.is_replaying = false;
+        self.path_timer = 0.0;
+
+        // Solvers that would walk through locked doors or closed gates, or that cannot steer on this grid, are passed over.
+        let mut solver_kind = self.solver_kind;
+
+        while !solver_kind.handles(&maze.graph) {
+            solver_kind = solver_kind.next();
+
+            if solver_kind == self.solver_kind { break; }
+        }
+
+        self.solver_kind = solver_kind;
+
+        maze.solver = self.solver_kind.create();
+        maze.solver.set_tie_break(self.tie_break);
+        maze.solver.set_node_budget(self.node_budget);
+        maze.solver.set_waypoints(self.waypoints.clone());
+        maze.solver.set_agents(self.agents.clone());
+        maze.solver.set_seed(self.seed);
+        maze.solver.set_metric(maze.graph.get_metric());
+        maze.solver.initialize(start_coords, goal_coords);
     }
 
     pub fn update(&mut self, maze: &mut Maze::Maze, args: &UpdateArgs) {
+        self.update_title(maze);
+
         if !maze.is_finished() {
-            for _ in 0..100 {
-                maze.generate_maze();
+            self.opt_validation = None;
+
+            if !self.is_generation_paused {
+                for _ in 0..100 {
+                    maze.generate_maze();
+                }
             }
 
             return;
         }
 
-        if !maze.astar.is_finished() {
+        if self.is_replaying {
+            maze.solver.step(&maze.graph);
+
+            return;
+        }
+
+        if !maze.solver.is_finished() {
             for _ in 0..3 {
-                maze.astar.generate_path(&maze.graph);
+                maze.solver.step(&maze.graph);
             }
-        } else if self.path_timer > 1.0 {
-            let size = maze.graph.get_size();
+        } else if self.path_timer > 1.0 + Application::get_walk_seconds(maze) {
+            let graph = &maze.graph;
 
-            let mut rng = rand::thread_rng();
+            // Doors are only sure to open in order from where their keys were placed.
+            let start_coords = match maze.get_key_origin() {
+                None => Application::random_coords(graph),
+                Some(origin_coords) => origin_coords,
+            };
 
-            let start_coords = (rng.gen_range(0..size), rng.gen_range(0..size));
-            let goal_coords = (rng.gen_range(0..size), rng.gen_range(0..size));
+            let goal_coords = Application::random_coords(graph);
 
-            maze.astar.initialize(start_coords, goal_coords);
+            self.waypoints = (0..self.waypoint_count).map(|_| Application::random_coords(graph)).collect();
+
+            self.agents = (0..self.agent_count).map(|_| (Application::random_coords(graph), Application::random_coords(graph))).collect();
+
+            maze.solver.set_waypoints(self.waypoints.clone());
+            maze.solver.set_agents(self.agents.clone());
+            maze.solver.initialize(start_coords, goal_coords);
 
             self.path_timer = 0.0;
         } else {
             self.path_timer += args.dt;
         }
     }
+
+    // How long walking the route takes, so the next query waits until the walk is over.
+    fn get_walk_seconds(maze: &Maze::Maze) -> f64 {
+        if maze.graph.get_gates().is_empty() { return 0.0; }
+
+        return maze.solver.get_path().len() as f64 / WALK_SPEED;
+    }
+
+    pub fn hover(&mut self, maze: &Maze::Maze, position: [f64; 2]) {
+        let topology = maze.graph.get_topology();
+
+        let (extent_width, extent_height) = topology.get_extent();
+
+        self.hovered = topology.locate((position[0] / self.window_size[0] * extent_width, position[1] / self.window_size[1] * extent_height));
+    }
+
+    pub fn press(&mut self, maze: &mut Maze::Maze, button: &Button) {
+        self.opt_notice = None;
+
+        match button {
+            Button::Keyboard(Key::S) => {
+                self.solver_kind = self.solver_kind.next();
+
+                let (start_coords, goal_coords) = (maze.solver.get_start_coords(), maze.solver.get_goal_coords());
+
+                self.restart_solver(maze, start_coords, goal_coords);
+            },
+            Button::Keyboard(Key::T) => {
+                self.tie_break = self.tie_break.next();
+
+                maze.solver.set_tie_break(self.tie_break);
+            },
+            Button::Keyboard(Key::V) => {
+                self.show_search_tree = !self.show_search_tree;
+            },
+            Button::Keyboard(Key::E) => {
+                let notice = match maze.solver.get_trace() {
+                    None => format!("{:?} does not record a trace", self.solver_kind),
+                    Some(trace) => match trace.save(TRACE_PATH) {
+                        Err(error) => format!("could not save {}: {}", TRACE_PATH, error),
+                        Ok(()) => format!("saved {} events to {}", trace.get_events().len(), TRACE_PATH),
+                    }
+                };
+
+                self.opt_notice = Some(notice);
+            },
+            Button::Keyboard(Key::R) => {
+                if self.is_replaying {
+                    let (start_coords, goal_coords) = (maze.solver.get_start_coords(), maze.solver.get_goal_coords());
+
+                    self.restart_solver(maze, start_coords, goal_coords);
+
+                    return;
+                }
+
+                match maze.solver.get_trace().cloned() {
+                    None => self.opt_notice = Some(format!("{:?} does not record a trace", self.solver_kind)),
+                    Some(trace) => self.start_replay(maze, trace),
+                }
+            },
+            Button::Keyboard(Key::L) => {
+                match SearchTrace::load(TRACE_PATH) {
+                    Err(error) => self.opt_notice = Some(format!("could not load {}: {}", TRACE_PATH, error)),
+                    Ok(trace) => self.start_replay(maze, trace),
+                }
+            },
+            Button::Keyboard(Key::G) => {
+                self.is_generation_paused = !self.is_generation_paused;
+            },
+            Button::Keyboard(Key::X) => {
+                let notice = match maze.save_history(GENERATION_TRACE_PATH) {
+                    Err(error) => format!("could not save {}: {}", GENERATION_TRACE_PATH, error),
+                    Ok(()) => format!("saved {} generation steps to {}", maze.get_generation_position().0, GENERATION_TRACE_PATH),
+                };
+
+                self.opt_notice = Some(notice);
+            },
+            Button::Keyboard(Key::Left) | Button::Keyboard(Key::Right) | Button::Keyboard(Key::Up) | Button::Keyboard(Key::Down)
+                if self.is_generation_paused && !self.is_replaying => {
+                let steps = match button {
+                    Button::Keyboard(Key::Left) | Button::Keyboard(Key::Right) => 1,
+                    _ => 100,
+                };
+
+                let is_forward = matches!(button, Button::Keyboard(Key::Right) | Button::Keyboard(Key::Up));
+
+                for _ in 0..steps {
+                    if is_forward {
+                        maze.generate_maze();
+                    } else {
+                        maze.step_back();
+                    }
+                }
+            },
+            Button::Keyboard(Key::Left) | Button::Keyboard(Key::Right) | Button::Keyboard(Key::Space) => {
+                if !self.is_replaying { return; }
+
+                // Pressing a direction again plays faster, the other way turns playback around.
+                self.replay_speed = match button {
+                    Button::Keyboard(Key::Space) => 0,
+                    Button::Keyboard(Key::Right) if self.replay_speed > 0 => i32::min(self.replay_speed * 2, 1024),
+                    Button::Keyboard(Key::Left) if self.replay_speed < 0 => i32::max(self.replay_speed * 2, -1024),
+                    Button::Keyboard(Key::Right) => 1,
+                    _ => -1,
+                };
+
+                maze.solver.set_playback_speed(self.replay_speed);
+            },
+            Button::Keyboard(Key::D) => {
+                let topology = maze.graph.get_topology();
+
+                // Cells are numbered row by row, so the last one sits in the far corner.
+                let size = topology.get_coords(topology.get_cell_count() - 1).0 + 1;
+
+                maze.graph.set_topology(topology.get_shape().next().create(size));
+
+                let (start_coords, goal_coords) = (maze.solver.get_start_coords(), maze.solver.get_goal_coords());
+
+                self.restart_solver(maze, start_coords, goal_coords);
+            },
+            Button::Keyboard(Key::P) | Button::Keyboard(Key::O) => {
+                if !maze.is_finished() { return; }
+
+                if *button == Button::Keyboard(Key::P) {
+                    maze.add_random_portal();
+                } else {
+                    for _ in 0..20 {
+                        maze.make_random_one_way();
+                    }
+                }
+
+                let (start_coords, goal_coords) = (maze.solver.get_start_coords(), maze.solver.get_goal_coords());
+
+                self.restart_solver(maze, start_coords, goal_coords);
+            },
+            Button::Keyboard(Key::K) => {
+                if !maze.is_finished() { return; }
+
+                let (start_coords, goal_coords) = (maze.solver.get_start_coords(), maze.solver.get_goal_coords());
+
+                if maze.get_key_origin().is_none() {
+                    maze.place_keys(start_coords, KEY_COUNT);
+                } else {
+                    maze.clear_keys();
+                }
+
+                self.restart_solver(maze, start_coords, goal_coords);
+            },
+            Button::Keyboard(Key::B) => {
+                if !maze.is_finished() { return; }
+
+                if maze.graph.get_gates().is_empty() {
+                    maze.add_random_gates(GATE_COUNT);
+                } else {
+                    maze.graph.clear_gates();
+                }
+
+                let (start_coords, goal_coords) = (maze.solver.get_start_coords(), maze.solver.get_goal_coords());
+
+                self.restart_solver(maze, start_coords, goal_coords);
+            },
+            Button::Keyboard(Key::C) => {
+                if !maze.solver.is_finished() { return; }
+
+                maze.solver.compare_with_baseline(&maze.graph);
+            },
+            Button::Keyboard(Key::M) => {
+                if !maze.is_finished() { return; }
+
+                for _ in 0..20 {
+                    maze.toggle_random_wall();
+                }
+
+                maze.solver.notify_graph_changed();
+            },
+            Button::Keyboard(Key::LeftBracket) | Button::Keyboard(Key::RightBracket) => {
+                self.waypoint_count = if *button == Button::Keyboard(Key::LeftBracket) {
+                    self.waypoint_count.saturating_sub(1)
+                } else {
+                    self.waypoint_count + 1
+                };
+            },
+            Button::Keyboard(Key::Comma) | Button::Keyboard(Key::Period) => {
+                self.agent_count = if *button == Button::Keyboard(Key::Comma) {
+                    self.agent_count.saturating_sub(1)
+                } else {
+                    self.agent_count + 1
+                };
+            },
+            Button::Keyboard(Key::Minus) | Button::Keyboard(Key::Equals) => {
+                self.node_budget = if *button == Button::Keyboard(Key::Minus) {
+                    usize::max(self.node_budget / 2, 2)
+                } else {
+                    self.node_budget * 2
+                };
+
+                maze.solver.set_node_budget(self.node_budget);
+            },
+            _ => {}
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,3 @@
+pub mod Maze;
+pub mod Rendering;
+pub mod Pathfinding;
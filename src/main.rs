@@ -1,8 +1,6 @@
-mod Maze;
-mod Rendering;
-mod Pathfinding;
+use maze_runner::{Maze, Rendering};
 
-use piston::input::{RenderEvent, UpdateEvent};
+use piston::input::{MouseCursorEvent, PressEvent, RenderEvent, UpdateEvent};
 
 fn main() {
     let size = 50;
@@ -19,5 +17,13 @@ fn main() {
         if let Some(args) = e.update_args() {
             app.update(&mut maze, &args);
         }
+
+        if let Some(position) = e.mouse_cursor_args() {
+            app.hover(&maze, position);
+        }
+
+        if let Some(button) = e.press_args() {
+            app.press(&mut maze, &button);
+        }
     }
 }